@@ -54,7 +54,7 @@ fn main() {
         .write_to_file(out_path.join("cronet_bindings.rs"))
         .expect("Couldn't write bindings!");
 
-    // 2. Compile Protos (Standard Prost)
+    // 2. Compile Protos (Prost messages + Tonic service)
     let proto_file = "proto/cronet_engine.proto";
 
     // Check if proto exists
@@ -66,21 +66,97 @@ fn main() {
         // prost-build type_attribute "." applies to everything including enums which causes error.
         // We will apply it to the main request/response types.
         config.type_attribute("cronet.engine.v1.ExecuteRequest", "#[serde(default)]");
+        config.type_attribute(
+            "cronet.engine.v1.ExecuteBatchRequest",
+            "#[serde(default)]",
+        );
         config.type_attribute("cronet.engine.v1.TargetRequest", "#[serde(default)]");
         config.type_attribute("cronet.engine.v1.ExecutionConfig", "#[serde(default)]");
+        config.type_attribute("cronet.engine.v1.ProxyConfig", "#[serde(default)]");
         config.type_attribute("cronet.engine.v1.ExecuteResponse", "#[serde(default)]");
+        config.type_attribute(
+            "cronet.engine.v1.ExecuteStreamResponse",
+            "#[serde(default)]",
+        );
+        config.type_attribute("cronet.engine.v1.Error", "#[serde(default)]");
+        config.type_attribute("cronet.engine.v1.RetryPolicy", "#[serde(default)]");
+        config.type_attribute("cronet.engine.v1.AttemptError", "#[serde(default)]");
 
-        // Serialize body fields as hex strings instead of byte arrays
+        // Serialize body fields as strings in the request's body encoding
         config.field_attribute(
             "cronet.engine.v1.TargetRequest.body",
-            "#[serde(with = \"hex::serde\")]",
+            "#[serde(with = \"crate::body_encoding\")]",
         );
         config.field_attribute(
             "cronet.engine.v1.TargetResponse.body",
-            "#[serde(with = \"hex::serde\")]",
+            "#[serde(with = \"crate::body_encoding\")]",
+        );
+        // Connect clients send proto3 JSON names (lowerCamelCase); accept those too
+        for (field, alias) in [
+            ("ExecuteRequest.request_id", "requestId"),
+            ("ExecuteBatchRequest.max_concurrency", "maxConcurrency"),
+            ("ExecuteBatchRequest.fail_fast", "failFast"),
+            ("ExecutionConfig.timeout_ms", "timeoutMs"),
+            ("ExecutionConfig.body_encoding", "bodyEncoding"),
+            ("ExecutionConfig.follow_redirects", "followRedirects"),
+            ("ExecutionConfig.max_redirects", "maxRedirects"),
+            ("ExecutionConfig.session_id", "sessionId"),
+            ("ExecutionConfig.cookie_jar", "cookieJar"),
+            ("ExecutionConfig.capture_netlog", "captureNetlog"),
+            ("RetryPolicy.max_attempts", "maxAttempts"),
+            ("RetryPolicy.initial_backoff_ms", "initialBackoffMs"),
+            ("RetryPolicy.max_backoff_ms", "maxBackoffMs"),
+            ("RetryPolicy.retry_on_status", "retryOnStatus"),
+            ("RetryPolicy.retry_non_idempotent", "retryNonIdempotent"),
+            ("RetryPolicy.ignore_retry_after", "ignoreRetryAfter"),
+            (
+                "ExecutionConfig.deny_cross_origin_redirects",
+                "denyCrossOriginRedirects",
+            ),
+            (
+                "ExecutionConfig.deny_insecure_redirects",
+                "denyInsecureRedirects",
+            ),
+        ] {
+            config.field_attribute(
+                format!("cronet.engine.v1.{}", field),
+                format!("#[serde(alias = \"{}\")]", alias),
+            );
+        }
+        // Enum fields are read by number or name, as proto3 JSON sends them
+        let fds = config
+            .load_fds(&[proto_file], &["proto"])
+            .expect("failed to load protos");
+        for file in &fds.file {
+            for message in &file.message_type {
+                enum_field_attributes(&mut config, file.package(), message);
+            }
+        }
+        // Error kinds are written by name too
+        config.field_attribute(
+            "cronet.engine.v1.Error.kind",
+            "#[serde(serialize_with = \"crate::enum_name::serialize::<crate::cronet_pb::ErrorKind, _>\")]",
+        );
+        config.field_attribute(
+            "cronet.engine.v1.RetryPolicy.retry_on_errors",
+            "#[serde(alias = \"retryOnErrors\", serialize_with = \"crate::enum_name::serialize_list::<crate::cronet_pb::ErrorKind, _>\")]",
+        );
+        // Only captured on request, and large
+        config.field_attribute(
+            "cronet.engine.v1.ExecuteResponse.netlog",
+            "#[serde(skip_serializing_if = \"String::is_empty\")]",
+        );
+        // Stream messages carry either the head or a chunk, so omit the other
+        config.field_attribute(
+            "cronet.engine.v1.ExecuteStreamResponse.head",
+            "#[serde(skip_serializing_if = \"Option::is_none\")]",
+        );
+        config.field_attribute(
+            "cronet.engine.v1.ExecuteStreamResponse.chunk",
+            "#[serde(with = \"crate::body_encoding\", skip_serializing_if = \"Vec::is_empty\")]",
         );
-        config
-            .compile_protos(&[proto_file], &["proto"])
+        tonic_build::configure()
+            .compile_fds_with_config(config, fds)
             .expect("failed to compile protos");
     }
 
@@ -134,3 +210,55 @@ fn main() {
 
     println!("cargo:rerun-if-changed=build.rs");
 }
+
+/// Adds the `enum_name` deserializer to every enum field of `message` and
+/// its nested messages. `scope` is the proto path the message is in.
+fn enum_field_attributes(
+    config: &mut prost_build::Config,
+    scope: &str,
+    message: &prost_types::DescriptorProto,
+) {
+    use prost_types::field_descriptor_proto::{Label, Type};
+
+    let path = format!("{}.{}", scope, message.name());
+    for field in &message.field {
+        if field.r#type() != Type::Enum {
+            continue;
+        }
+        let function = if field.label() == Label::Repeated {
+            "deserialize_list"
+        } else {
+            "deserialize"
+        };
+        config.field_attribute(
+            format!("{}.{}", path, field.name()),
+            format!(
+                "#[serde(deserialize_with = \"crate::enum_name::{}::<{}, _>\")]",
+                function,
+                rust_type(field.type_name())
+            ),
+        );
+    }
+    for nested in &message.nested_type {
+        enum_field_attributes(config, &path, nested);
+    }
+}
+
+/// Rust path of a generated type from its proto name, e.g.
+/// `.cronet.engine.v1.ProxyConfig.ProxyType` to
+/// `crate::cronet_pb::proxy_config::ProxyType`.
+fn rust_type(proto_name: &str) -> String {
+    let name = proto_name.trim_start_matches(".cronet.engine.v1.");
+    let (parents, name) = name.rsplit_once('.').unwrap_or(("", name));
+    let mut path = String::from("crate::cronet_pb::");
+    for parent in parents.split('.').filter(|parent| !parent.is_empty()) {
+        for (i, c) in parent.chars().enumerate() {
+            if c.is_ascii_uppercase() && i > 0 {
+                path.push('_');
+            }
+            path.push(c.to_ascii_lowercase());
+        }
+        path.push_str("::");
+    }
+    path + name
+}
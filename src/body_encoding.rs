@@ -0,0 +1,179 @@
+//! Encoding of the `bytes` body fields (`TargetRequest.body`,
+//! `TargetResponse.body`, `ExecuteStreamResponse.chunk`) in JSON.
+//!
+//! The encoding is picked per request through `ExecutionConfig.body_encoding`,
+//! so it has to be known while serde runs: `from_json_slice` reads it from the
+//! request before decoding the rest, and `with` exposes it to the field
+//! (de)serializers through a thread-local.
+
+use crate::cronet_pb::{BodyEncoding, ExecuteBatchRequest};
+use axum::body::Bytes;
+use axum::extract::{FromRequest, Request};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use base64::Engine as _;
+use serde::de::{DeserializeOwned, Error as _};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::cell::Cell;
+
+thread_local! {
+    static CURRENT: Cell<BodyEncoding> = const { Cell::new(BodyEncoding::Base64) };
+}
+
+/// Runs `f` with `encoding` applied to body fields.
+pub fn with<R>(encoding: BodyEncoding, f: impl FnOnce() -> R) -> R {
+    let previous = CURRENT.with(|current| current.replace(encoding));
+    let result = f();
+    CURRENT.with(|current| current.set(previous));
+    result
+}
+
+/// Parses a JSON request, decoding its body fields in the encoding the
+/// request itself asks for.
+pub fn from_json_slice<T: DeserializeOwned>(
+    data: &[u8],
+) -> Result<(T, BodyEncoding), serde_json::Error> {
+    from_json_value(serde_json::from_slice(data)?)
+}
+
+fn from_json_value<T: DeserializeOwned>(
+    value: serde_json::Value,
+) -> Result<(T, BodyEncoding), serde_json::Error> {
+    let encoding = requested(&value);
+    let parsed = with(encoding, || serde_json::from_value(value))?;
+    Ok((parsed, encoding))
+}
+
+/// Parses a JSON `ExecuteBatchRequest`. Each request may pick its own body
+/// encoding, returned in the same order as `requests`.
+pub fn batch_from_json_slice(
+    data: &[u8],
+) -> Result<(ExecuteBatchRequest, Vec<BodyEncoding>), serde_json::Error> {
+    let mut value: serde_json::Value = serde_json::from_slice(data)?;
+    let requests = value
+        .get_mut("requests")
+        .map(serde_json::Value::take)
+        .unwrap_or_default();
+    let mut batch: ExecuteBatchRequest = serde_json::from_value(value)?;
+    let requests: Vec<serde_json::Value> = match requests {
+        serde_json::Value::Null => Vec::new(),
+        requests => serde_json::from_value(requests)?,
+    };
+
+    let mut encodings = Vec::with_capacity(requests.len());
+    for request in requests {
+        let (request, encoding) = from_json_value(request)?;
+        batch.requests.push(request);
+        encodings.push(encoding);
+    }
+    Ok((batch, encodings))
+}
+
+/// Serializes a JSON response with its body fields in `encoding`.
+pub fn to_json_vec<T: Serialize>(value: &T, encoding: BodyEncoding) -> Vec<u8> {
+    with(encoding, || serde_json::to_vec(value)).unwrap_or_default()
+}
+
+// `config.body_encoding` of a JSON ExecuteRequest, base64 if absent
+fn requested(value: &serde_json::Value) -> BodyEncoding {
+    let config = match value.get("config") {
+        Some(config) => config,
+        None => return BodyEncoding::Base64,
+    };
+    let encoding = match config
+        .get("body_encoding")
+        .or_else(|| config.get("bodyEncoding"))
+    {
+        Some(serde_json::Value::Number(n)) => n
+            .as_i64()
+            .and_then(|n| BodyEncoding::try_from(n as i32).ok()),
+        Some(serde_json::Value::String(name)) => crate::enum_name::parse(name),
+        _ => None,
+    };
+    encoding.unwrap_or(BodyEncoding::Base64)
+}
+
+/// Converts a response body to UTF-8 from the charset in its `Content-Type`.
+/// Bodies without a known charset are assumed to be UTF-8 already.
+pub fn to_utf8(body: Vec<u8>, content_type: Option<&str>) -> Vec<u8> {
+    let charset = content_type.and_then(|content_type| {
+        content_type.split(';').skip(1).find_map(|param| {
+            let (name, value) = param.split_once('=')?;
+            name.trim()
+                .eq_ignore_ascii_case("charset")
+                .then(|| value.trim().trim_matches('"'))
+        })
+    });
+    let encoding = charset
+        .and_then(|charset| encoding_rs::Encoding::for_label(charset.as_bytes()))
+        .unwrap_or(encoding_rs::UTF_8);
+    if encoding == encoding_rs::UTF_8 {
+        return body;
+    }
+    let (text, _, _) = encoding.decode(&body);
+    text.into_owned().into_bytes()
+}
+
+pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    match CURRENT.with(Cell::get) {
+        BodyEncoding::Base64 => {
+            serializer.serialize_str(&base64::engine::general_purpose::STANDARD.encode(bytes))
+        }
+        BodyEncoding::Hex => serializer.serialize_str(&hex::encode(bytes)),
+        BodyEncoding::Utf8 => serializer.serialize_str(&String::from_utf8_lossy(bytes)),
+    }
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+    let text = String::deserialize(deserializer)?;
+    match CURRENT.with(Cell::get) {
+        BodyEncoding::Base64 => {
+            // Like proto3 JSON, accept both alphabets, with or without padding
+            let text = text.trim_end_matches('=');
+            let engine = if text.contains(['-', '_']) {
+                base64::engine::general_purpose::URL_SAFE_NO_PAD
+            } else {
+                base64::engine::general_purpose::STANDARD_NO_PAD
+            };
+            engine.decode(text).map_err(D::Error::custom)
+        }
+        BodyEncoding::Hex => hex::decode(&text).map_err(D::Error::custom),
+        BodyEncoding::Utf8 => Ok(text.into_bytes()),
+    }
+}
+
+/// JSON request or response whose body fields use the request's encoding.
+pub struct EncodedJson<T>(pub T, pub BodyEncoding);
+
+#[axum::async_trait]
+impl<T, S> FromRequest<S> for EncodedJson<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let data = Bytes::from_request(req, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        let (value, encoding) = from_json_slice(&data).map_err(|e| {
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!("Failed to deserialize the JSON body: {}", e),
+            )
+                .into_response()
+        })?;
+        Ok(EncodedJson(value, encoding))
+    }
+}
+
+impl<T: Serialize> IntoResponse for EncodedJson<T> {
+    fn into_response(self) -> Response {
+        (
+            [(header::CONTENT_TYPE, "application/json")],
+            to_json_vec(&self.0, self.1),
+        )
+            .into_response()
+    }
+}
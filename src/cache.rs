@@ -0,0 +1,38 @@
+//! Per-request HTTP cache control (`ExecutionConfig.cache`) and cache size
+//! accounting for the admin endpoint.
+
+use crate::cronet_pb::CachePolicy;
+use crate::errors::RequestError;
+use std::path::Path;
+
+/// Rejects `only-if-cached` for methods other than GET and HEAD. Cronet has
+/// no cache-only load, so a miss still reaches the origin and is cancelled
+/// once its headers arrive; only safe methods may make that round trip.
+pub fn check(policy: i32, method: &str) -> Result<(), RequestError> {
+    let safe = method.is_empty()
+        || method.eq_ignore_ascii_case("GET")
+        || method.eq_ignore_ascii_case("HEAD");
+    if policy == CachePolicy::CacheOnlyIfCached as i32 && !safe {
+        return Err(RequestError::invalid(format!(
+            "Unsupported: only-if-cached needs GET or HEAD, not {}",
+            method
+        )));
+    }
+    Ok(())
+}
+
+/// Total size in bytes of the files under `path`. Unreadable entries are
+/// skipped, so this is a lower bound while the engine is writing.
+pub fn disk_usage(path: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.file_type() {
+            Ok(kind) if kind.is_dir() => disk_usage(&entry.path()),
+            Ok(_) => entry.metadata().map_or(0, |metadata| metadata.len()),
+            Err(_) => 0,
+        })
+        .sum()
+}
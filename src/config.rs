@@ -0,0 +1,676 @@
+//! Server configuration.
+//!
+//! Settings come from an optional TOML or YAML file, then environment
+//! variables, then command-line flags, each overriding the one before.
+//! Anything left unset keeps the built-in default.
+
+use crate::cronet::{EngineConfig, HttpCacheMode, PublicKeyPins, QuicHint};
+use base64::Engine as _;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use tracing_subscriber::filter::LevelFilter;
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "cronet-cloak",
+    version,
+    about = "HTTP requests with Chrome's network stack"
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[command(flatten)]
+    pub overrides: Overrides,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Validate the configuration, print the effective settings and exit
+    CheckConfig,
+}
+
+/// Settings that can be given as flags or environment variables.
+#[derive(Debug, Default, Args)]
+pub struct Overrides {
+    /// Configuration file (.toml, .yaml or .yml)
+    #[arg(long, short, global = true, env = "CRONET_CLOAK_CONFIG")]
+    pub config: Option<PathBuf>,
+
+    /// Address of the HTTP API
+    #[arg(long, global = true, env = "CRONET_CLOAK_LISTEN")]
+    pub listen: Option<String>,
+
+    /// Also serve gRPC on this port
+    #[arg(long, global = true, env = "GRPC_PORT")]
+    pub grpc_port: Option<u16>,
+
+    /// trace, debug, info, warn, error or off
+    #[arg(long, global = true, env = "CRONET_CLOAK_LOG_LEVEL")]
+    pub log_level: Option<String>,
+
+    /// Default User-Agent of the engine
+    #[arg(long, global = true, env = "CRONET_CLOAK_USER_AGENT")]
+    pub user_agent: Option<String>,
+
+    /// Default Accept-Language of the engine
+    #[arg(long, global = true, env = "CRONET_CLOAK_ACCEPT_LANGUAGE")]
+    pub accept_language: Option<String>,
+
+    /// auto, force or off
+    #[arg(long, global = true, env = "CRONET_CLOAK_QUIC_MODE")]
+    pub quic_mode: Option<QuicMode>,
+
+    #[arg(long, global = true, env = "CRONET_CLOAK_ENABLE_HTTP2")]
+    pub enable_http2: Option<bool>,
+
+    #[arg(long, global = true, env = "CRONET_CLOAK_ENABLE_BROTLI")]
+    pub enable_brotli: Option<bool>,
+
+    #[arg(long, global = true, env = "CRONET_CLOAK_CACHE_MODE")]
+    pub cache_mode: Option<CacheMode>,
+
+    /// Maximum HTTP cache size in bytes
+    #[arg(long, global = true, env = "CRONET_CLOAK_CACHE_MAX_SIZE")]
+    pub cache_max_size: Option<u64>,
+
+    /// Directory for the disk cache and other engine state
+    #[arg(long, global = true, env = "CRONET_CLOAK_STORAGE_PATH")]
+    pub storage_path: Option<PathBuf>,
+
+    /// Skip pin checks for certificates issued by a locally installed root
+    #[arg(
+        long,
+        global = true,
+        env = "CRONET_CLOAK_PIN_BYPASS_FOR_LOCAL_TRUST_ANCHORS"
+    )]
+    pub pin_bypass_for_local_trust_anchors: Option<bool>,
+
+    /// Maximum upstream requests in flight (0 = unlimited)
+    #[arg(long, global = true, env = "CRONET_CLOAK_MAX_IN_FLIGHT")]
+    pub max_in_flight: Option<usize>,
+
+    /// Maximum size of a JSON API request body in bytes
+    #[arg(long, global = true, env = "CRONET_CLOAK_MAX_BODY_SIZE")]
+    pub max_body_size: Option<usize>,
+
+    /// Maximum number of requests in one batch
+    #[arg(long, global = true, env = "CRONET_CLOAK_MAX_BATCH_SIZE")]
+    pub max_batch_size: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Address of the HTTP API (also serves gRPC).
+    pub listen: String,
+    /// Port for a dedicated gRPC listener on the same IP as `listen`.
+    pub grpc_port: Option<u16>,
+    pub log_level: String,
+    pub engine: EngineSettings,
+    pub limits: Limits,
+    pub netlog: NetLogSettings,
+    /// Named engines, selected per request with `config.profile`.
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// An engine of its own plus headers for the requests that use it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    /// Keys not set here are taken from `[engine]`, except `storage_path`:
+    /// every engine needs a directory of its own.
+    pub engine: EngineSettings,
+    /// Headers added to requests that do not set them.
+    pub headers: BTreeMap<String, String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            listen: "0.0.0.0:3000".to_string(),
+            grpc_port: None,
+            log_level: "info".to_string(),
+            engine: EngineSettings::default(),
+            limits: Limits::default(),
+            netlog: NetLogSettings::default(),
+            profiles: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EngineSettings {
+    pub user_agent: String,
+    /// Default `Accept-Language`, e.g. `en-US,en;q=0.9`.
+    pub accept_language: Option<String>,
+    /// Whether QUIC is used, and whether hinted hosts may fall back to TCP.
+    pub quic_mode: QuicMode,
+    /// Hosts known to speak QUIC, tried over HTTP/3 from the first request.
+    pub quic_hints: Vec<QuicHintSettings>,
+    pub enable_http2: bool,
+    pub enable_brotli: bool,
+    pub cache_mode: CacheMode,
+    /// Maximum HTTP cache size in bytes.
+    pub cache_max_size: u64,
+    /// Directory for the disk cache and other engine state. Must exist.
+    pub storage_path: Option<PathBuf>,
+    /// Nice value of the network thread.
+    pub network_thread_priority: Option<f64>,
+    /// Experimental Chromium options, passed to Cronet as JSON.
+    pub experimental_options: Option<serde_json::Map<String, serde_json::Value>>,
+    /// Public keys the certificate chains of these hosts must contain.
+    pub public_key_pins: Vec<PinSettings>,
+    /// Skip pin checks for certificates issued by a locally installed root,
+    /// as Chrome does, e.g. behind a TLS-inspecting corporate proxy.
+    pub pin_bypass_for_local_trust_anchors: bool,
+}
+
+/// `host:port` serves HTTP/3 on UDP `alternate_port`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct QuicHintSettings {
+    pub host: String,
+    #[serde(default = "https_port")]
+    pub port: u16,
+    #[serde(default = "https_port")]
+    pub alternate_port: u16,
+}
+
+fn https_port() -> u16 {
+    443
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum QuicMode {
+    /// HTTP/3 where the server offers it, TCP otherwise.
+    #[default]
+    Auto,
+    /// Requests to the hinted hosts go over HTTP/3 only, without falling
+    /// back to TCP. Needs `quic_hints`.
+    Force,
+    /// Never QUIC.
+    Off,
+}
+
+/// Pins for one host.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PinSettings {
+    pub host: String,
+    /// `sha256/<base64>` hashes of SubjectPublicKeyInfo; one key in the
+    /// chain must match.
+    pub pins: Vec<String>,
+    #[serde(default)]
+    pub include_subdomains: bool,
+    /// Seconds since the Unix epoch; pins without it never expire. Expired
+    /// pins are left out of the engine, with a warning.
+    #[serde(default)]
+    pub expires_at: Option<i64>,
+}
+
+impl Default for EngineSettings {
+    fn default() -> Self {
+        EngineSettings {
+            user_agent: "CronetCloak/1.0".to_string(),
+            accept_language: None,
+            quic_mode: QuicMode::Auto,
+            quic_hints: Vec::new(),
+            enable_http2: true,
+            enable_brotli: true,
+            cache_mode: CacheMode::Disabled,
+            cache_max_size: 100 * 1024 * 1024,
+            storage_path: None,
+            network_thread_priority: None,
+            experimental_options: None,
+            public_key_pins: Vec::new(),
+            pin_bypass_for_local_trust_anchors: true,
+        }
+    }
+}
+
+impl EngineSettings {
+    fn validate(&self, prefix: &str, problems: &mut Vec<String>) {
+        if self.user_agent.trim().is_empty() {
+            problems.push(format!("{}.user_agent: must not be empty", prefix));
+        }
+        if self.cache_mode != CacheMode::Disabled && self.cache_max_size == 0 {
+            problems.push(format!(
+                "{}.cache_max_size: must be set when the cache is on",
+                prefix
+            ));
+        }
+        match &self.storage_path {
+            Some(path) if !path.is_dir() => problems.push(format!(
+                "{}.storage_path: {} is not a directory",
+                prefix,
+                path.display()
+            )),
+            None if self.cache_mode == CacheMode::Disk => problems.push(format!(
+                "{}.storage_path: required for the disk cache",
+                prefix
+            )),
+            _ => {}
+        }
+        if self.quic_mode == QuicMode::Force {
+            if self.quic_hints.is_empty() {
+                problems.push(format!("{}.quic_mode: force needs quic_hints", prefix));
+            }
+            let quic = self
+                .experimental_options
+                .as_ref()
+                .and_then(|o| o.get("QUIC"));
+            if quic.is_some_and(|quic| !quic.is_object()) {
+                problems.push(format!(
+                    "{}.experimental_options.QUIC: must be a table to force QUIC",
+                    prefix
+                ));
+            }
+        }
+        for (i, hint) in self.quic_hints.iter().enumerate() {
+            let prefix = format!("{}.quic_hints[{}]", prefix, i);
+            if hint.host.is_empty() || hint.host.contains(['/', ':']) {
+                problems.push(format!("{}.host: expected a host name", prefix));
+            }
+            if hint.port == 0 || hint.alternate_port == 0 {
+                problems.push(format!("{}: ports must be greater than 0", prefix));
+            }
+        }
+        for (i, pins) in self.public_key_pins.iter().enumerate() {
+            let prefix = format!("{}.public_key_pins[{}]", prefix, i);
+            if pins.host.is_empty() || pins.host.contains(['/', ':']) {
+                problems.push(format!("{}.host: expected a host name", prefix));
+            }
+            if pins.pins.is_empty() {
+                problems.push(format!("{}.pins: must not be empty", prefix));
+            }
+            for pin in &pins.pins {
+                if !is_sha256_pin(pin) {
+                    problems.push(format!(
+                        "{}.pins: {:?} is not sha256/ and a base64 SHA-256 hash",
+                        prefix, pin
+                    ));
+                }
+            }
+        }
+    }
+
+    pub fn engine_config(&self) -> EngineConfig {
+        let cache_mode = match self.cache_mode {
+            CacheMode::Disabled => HttpCacheMode::Disabled,
+            CacheMode::Memory => HttpCacheMode::InMemory,
+            CacheMode::Disk => HttpCacheMode::Disk,
+        };
+        let mut config = EngineConfig::new(&self.user_agent)
+            .enable_quic(self.quic_mode != QuicMode::Off)
+            .enable_http2(self.enable_http2)
+            .enable_brotli(self.enable_brotli)
+            .http_cache(cache_mode, self.cache_max_size);
+        if let Some(accept_language) = &self.accept_language {
+            config = config.accept_language(accept_language);
+        }
+        if let Some(path) = &self.storage_path {
+            config = config.storage_path(path);
+        }
+        if let Some(priority) = self.network_thread_priority {
+            config = config.network_thread_priority(priority);
+        }
+        let options = self.effective_experimental_options();
+        if !options.is_empty() {
+            let options = serde_json::Value::Object(options);
+            config = config.experimental_options(&options.to_string());
+        }
+        for hint in &self.quic_hints {
+            config = config.quic_hint(QuicHint {
+                host: hint.host.clone(),
+                port: hint.port,
+                alternate_port: hint.alternate_port,
+            });
+        }
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs() as i64);
+        for pins in &self.public_key_pins {
+            if pins.expires_at.is_some_and(|expires_at| expires_at <= now) {
+                tracing::warn!(
+                    "Public key pins for {} have expired, skipping them",
+                    pins.host
+                );
+                continue;
+            }
+            config = config.public_key_pins(PublicKeyPins {
+                host: pins.host.clone(),
+                pins_sha256: pins.pins.clone(),
+                include_subdomains: pins.include_subdomains,
+                // Cronet needs a date; the latest one stands for never
+                expiration_date: pins
+                    .expires_at
+                    .map_or(i64::MAX, |expires_at| expires_at.saturating_mul(1000)),
+            });
+        }
+        config.pin_bypass_for_local_trust_anchors(self.pin_bypass_for_local_trust_anchors)
+    }
+
+    // Adds the hinted origins to Chromium's `QUIC.origins_to_force_quic_on`,
+    // after any the options list already
+    /// `experimental_options` as passed to Cronet, with the hinted origins
+    /// added to `QUIC.origins_to_force_quic_on` when QUIC is forced.
+    pub fn effective_experimental_options(&self) -> serde_json::Map<String, serde_json::Value> {
+        let mut options = self.experimental_options.clone().unwrap_or_default();
+        if self.quic_mode == QuicMode::Force {
+            self.force_quic_on(&mut options);
+        }
+        options
+    }
+
+    fn force_quic_on(&self, options: &mut serde_json::Map<String, serde_json::Value>) {
+        let quic = options
+            .entry("QUIC")
+            .or_insert_with(|| serde_json::Value::Object(Default::default()));
+        let Some(quic) = quic.as_object_mut() else {
+            return;
+        };
+        let mut origins: Vec<String> = quic
+            .get("origins_to_force_quic_on")
+            .and_then(|origins| origins.as_str())
+            .into_iter()
+            .flat_map(|origins| origins.split(','))
+            .filter(|origin| !origin.is_empty())
+            .map(str::to_string)
+            .collect();
+        origins.extend(
+            self.quic_hints
+                .iter()
+                .map(|hint| format!("{}:{}", hint.host, hint.port)),
+        );
+        quic.insert(
+            "origins_to_force_quic_on".to_string(),
+            serde_json::Value::String(origins.join(",")),
+        );
+    }
+}
+
+// `sha256/` followed by a base64 SHA-256 hash
+fn is_sha256_pin(pin: &str) -> bool {
+    pin.strip_prefix("sha256/")
+        .and_then(|hash| base64::engine::general_purpose::STANDARD.decode(hash).ok())
+        .is_some_and(|hash| hash.len() == 32)
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheMode {
+    #[default]
+    Disabled,
+    Memory,
+    /// Needs `storage_path`.
+    Disk,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Limits {
+    /// Upstream requests in flight at once; more wait for a slot. 0 = unlimited.
+    pub max_in_flight: usize,
+    /// Maximum size of a JSON API request body in bytes. Streaming uploads
+    /// are not limited.
+    pub max_body_size: usize,
+    /// Maximum number of requests in one batch.
+    pub max_batch_size: usize,
+    /// Proxy engines kept in the pool.
+    pub proxy_pool_size: usize,
+    /// Seconds before an unused proxy engine is shut down.
+    pub proxy_idle_timeout_secs: u64,
+    /// Sessions alive at once, each with an engine of its own.
+    pub max_sessions: usize,
+    /// Seconds before an unused session expires, unless it sets its own TTL.
+    pub session_ttl_secs: u64,
+    /// Cookie jars kept at once; a new jar replaces the least recently used.
+    pub max_cookie_jars: usize,
+    /// Seconds before an unused cookie jar is dropped.
+    pub cookie_jar_ttl_secs: u64,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_in_flight: 0,
+            max_body_size: 2 * 1024 * 1024,
+            max_batch_size: 1000,
+            proxy_pool_size: crate::engine_pool::DEFAULT_MAX_SIZE,
+            proxy_idle_timeout_secs: crate::engine_pool::DEFAULT_IDLE_TIMEOUT.as_secs(),
+            max_sessions: 100,
+            session_ttl_secs: 1800,
+            max_cookie_jars: crate::cookies::DEFAULT_MAX_JARS,
+            cookie_jar_ttl_secs: crate::cookies::DEFAULT_JAR_TTL.as_secs(),
+        }
+    }
+}
+
+/// Where NetLog captures are written, and how large they may grow.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NetLogSettings {
+    /// Created on the first capture, readable by its owner only.
+    pub dir: PathBuf,
+    /// Default size limit of a capture started through the admin API, in
+    /// bytes.
+    pub max_size: u64,
+}
+
+impl Default for NetLogSettings {
+    fn default() -> Self {
+        NetLogSettings {
+            dir: std::env::temp_dir().join("cronet-cloak-netlog"),
+            max_size: 100 * 1024 * 1024,
+        }
+    }
+}
+
+impl Config {
+    /// Loads the file named by `overrides` (if any), applies the overrides
+    /// and validates the result.
+    pub fn from_overrides(overrides: &Overrides) -> Result<Config, String> {
+        let mut config = match &overrides.config {
+            Some(path) => Config::read(path, overrides)?,
+            None => Config::default(),
+        };
+        overrides.apply(&mut config);
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Reads a TOML or YAML file, picked by extension.
+    pub fn load(path: &Path) -> Result<Config, String> {
+        Config::read(path, &Overrides::default())
+    }
+
+    // Engine overrides go into `[engine]` here, before profiles inherit it
+    fn read(path: &Path, overrides: &Overrides) -> Result<Config, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+        let value = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => toml::from_str(&text).map_err(|e| e.to_string()),
+            Some("yaml") | Some("yml") => serde_yaml::from_str(&text).map_err(|e| e.to_string()),
+            _ => Err("expected a .toml, .yaml or .yml file".to_string()),
+        };
+        value
+            .and_then(|value| Config::from_value(value, overrides))
+            .map_err(|e| format!("Invalid config file {}: {}", path.display(), e))
+    }
+
+    pub fn from_toml(text: &str) -> Result<Config, String> {
+        let value = toml::from_str(text).map_err(|e| e.to_string())?;
+        Config::from_value(value, &Overrides::default())
+    }
+
+    pub fn from_yaml(text: &str) -> Result<Config, String> {
+        let value = serde_yaml::from_str(text).map_err(|e| e.to_string())?;
+        Config::from_value(value, &Overrides::default())
+    }
+
+    // Fills in each profile's engine from `[engine]`, with the engine
+    // overrides applied, before deserializing
+    fn from_value(mut value: serde_json::Value, overrides: &Overrides) -> Result<Config, String> {
+        let engine = value.get("engine").cloned().unwrap_or_default();
+        let mut engine: EngineSettings = match engine {
+            serde_json::Value::Null => EngineSettings::default(),
+            engine => serde_json::from_value(engine).map_err(|e| e.to_string())?,
+        };
+        overrides.apply_engine(&mut engine);
+        let mut base = serde_json::to_value(engine)
+            .map_err(|e| e.to_string())?
+            .as_object()
+            .cloned()
+            .unwrap_or_default();
+        base.remove("storage_path");
+        if let Some(profiles) = value
+            .get_mut("profiles")
+            .and_then(|profiles| profiles.as_object_mut())
+        {
+            for profile in profiles.values_mut() {
+                let Some(profile) = profile.as_object_mut() else {
+                    continue;
+                };
+                let mut engine = base.clone();
+                if let Some(own) = profile.get("engine").and_then(|own| own.as_object()) {
+                    engine.extend(own.clone());
+                }
+                profile.insert("engine".to_string(), serde_json::Value::Object(engine));
+            }
+        }
+        serde_json::from_value(value).map_err(|e| e.to_string())
+    }
+
+    /// Checks the settings, reporting every problem found, one per line.
+    pub fn validate(&self) -> Result<(), String> {
+        let mut problems = Vec::new();
+        if self.listen.parse::<SocketAddr>().is_err() {
+            problems.push(format!("listen: invalid address {:?}", self.listen));
+        }
+        if self.log_level.parse::<LevelFilter>().is_err() {
+            problems.push(format!("log_level: unknown level {:?}", self.log_level));
+        }
+
+        self.engine.validate("engine", &mut problems);
+        let mut storage_paths: Vec<_> = self.engine.storage_path.iter().collect();
+        for (name, profile) in &self.profiles {
+            let prefix = format!("profiles.{}.engine", name);
+            if name.is_empty() {
+                problems.push("profiles: names must not be empty".to_string());
+            }
+            profile.engine.validate(&prefix, &mut problems);
+            if let Some(path) = &profile.engine.storage_path {
+                if storage_paths.contains(&path) {
+                    problems.push(format!(
+                        "{}.storage_path: {} is used by another engine",
+                        prefix,
+                        path.display()
+                    ));
+                }
+                storage_paths.push(path);
+            }
+        }
+
+        let limits = &self.limits;
+        for (name, value) in [
+            ("max_body_size", limits.max_body_size),
+            ("max_batch_size", limits.max_batch_size),
+            ("proxy_pool_size", limits.proxy_pool_size),
+        ] {
+            if value == 0 {
+                problems.push(format!("limits.{}: must be greater than 0", name));
+            }
+        }
+
+        if self.netlog.max_size == 0 {
+            problems.push("netlog.max_size: must be greater than 0".to_string());
+        }
+        if self.netlog.dir.is_file() {
+            problems.push(format!(
+                "netlog.dir: {} is not a directory",
+                self.netlog.dir.display()
+            ));
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems.join("\n"))
+        }
+    }
+
+    /// Address of the dedicated gRPC listener, if configured.
+    pub fn grpc_listen(&self) -> Option<SocketAddr> {
+        let port = self.grpc_port?;
+        let mut addr = self.listen.parse::<SocketAddr>().ok()?;
+        addr.set_port(port);
+        Some(addr)
+    }
+
+    pub fn log_level(&self) -> LevelFilter {
+        self.log_level.parse().unwrap_or(LevelFilter::INFO)
+    }
+}
+
+impl Overrides {
+    pub fn apply(&self, config: &mut Config) {
+        if let Some(listen) = &self.listen {
+            config.listen = listen.clone();
+        }
+        if self.grpc_port.is_some() {
+            config.grpc_port = self.grpc_port;
+        }
+        if let Some(log_level) = &self.log_level {
+            config.log_level = log_level.clone();
+        }
+        self.apply_engine(&mut config.engine);
+
+        let limits = &mut config.limits;
+        if let Some(max_in_flight) = self.max_in_flight {
+            limits.max_in_flight = max_in_flight;
+        }
+        if let Some(max_body_size) = self.max_body_size {
+            limits.max_body_size = max_body_size;
+        }
+        if let Some(max_batch_size) = self.max_batch_size {
+            limits.max_batch_size = max_batch_size;
+        }
+    }
+
+    /// Applies the engine settings only, as profiles inherit them.
+    pub fn apply_engine(&self, engine: &mut EngineSettings) {
+        if let Some(user_agent) = &self.user_agent {
+            engine.user_agent = user_agent.clone();
+        }
+        if self.accept_language.is_some() {
+            engine.accept_language = self.accept_language.clone();
+        }
+        if let Some(quic_mode) = self.quic_mode {
+            engine.quic_mode = quic_mode;
+        }
+        if let Some(enable_http2) = self.enable_http2 {
+            engine.enable_http2 = enable_http2;
+        }
+        if let Some(enable_brotli) = self.enable_brotli {
+            engine.enable_brotli = enable_brotli;
+        }
+        if let Some(cache_mode) = self.cache_mode {
+            engine.cache_mode = cache_mode;
+        }
+        if let Some(cache_max_size) = self.cache_max_size {
+            engine.cache_max_size = cache_max_size;
+        }
+        if self.storage_path.is_some() {
+            engine.storage_path = self.storage_path.clone();
+        }
+        if let Some(bypass) = self.pin_bypass_for_local_trust_anchors {
+            engine.pin_bypass_for_local_trust_anchors = bypass;
+        }
+    }
+}
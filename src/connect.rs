@@ -0,0 +1,207 @@
+//! Connect protocol support.
+//!
+//! Unary calls carry a bare message (`application/json` or
+//! `application/proto`, or the `message` query parameter for GET) and report
+//! failures as a JSON error with a matching HTTP status.
+//!
+//! Streaming messages travel in envelopes: a flags byte, a big-endian u32
+//! length, then the encoded message. The last envelope has the end-stream
+//! flag set and carries a JSON object with an optional error.
+//!
+//! Errors of failed requests carry the structured `Error` in `details`.
+
+use crate::body_encoding;
+use crate::cronet_pb::{BodyEncoding, Error, ErrorKind};
+use crate::errors;
+use axum::body::Bytes;
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use base64::Engine as _;
+use std::collections::HashMap;
+
+/// Envelope flag marking a compressed message.
+pub const FLAG_COMPRESSED: u8 = 0x01;
+/// Envelope flag marking the end-of-stream message.
+pub const FLAG_END_STREAM: u8 = 0x02;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    Json,
+    Proto,
+}
+
+impl Codec {
+    /// Picks the codec from a streaming `Content-Type`.
+    pub fn from_headers(headers: &HeaderMap) -> Option<Codec> {
+        let content_type = headers.get(header::CONTENT_TYPE)?.to_str().ok()?;
+        let content_type = content_type.split(';').next()?.trim();
+        match content_type {
+            "application/connect+json" => Some(Codec::Json),
+            "application/connect+proto" => Some(Codec::Proto),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Codec::Json => "application/connect+json",
+            Codec::Proto => "application/connect+proto",
+        }
+    }
+
+    /// Picks the codec from a unary `Content-Type`.
+    pub fn from_unary_headers(headers: &HeaderMap) -> Option<Codec> {
+        let content_type = headers.get(header::CONTENT_TYPE)?.to_str().ok()?;
+        let content_type = content_type.split(';').next()?.trim();
+        match content_type {
+            "application/json" => Some(Codec::Json),
+            "application/proto" => Some(Codec::Proto),
+            _ => None,
+        }
+    }
+
+    pub fn unary_content_type(self) -> &'static str {
+        match self {
+            Codec::Json => "application/json",
+            Codec::Proto => "application/proto",
+        }
+    }
+
+    /// Decodes a request, along with the body encoding it asks for in JSON.
+    pub fn decode<T>(self, data: &[u8]) -> Result<(T, BodyEncoding), String>
+    where
+        T: prost::Message + Default + serde::de::DeserializeOwned,
+    {
+        match self {
+            Codec::Json => body_encoding::from_json_slice(data).map_err(|e| e.to_string()),
+            Codec::Proto => T::decode(data)
+                .map(|message| (message, BodyEncoding::Base64))
+                .map_err(|e| e.to_string()),
+        }
+    }
+
+    pub fn encode<T>(self, message: &T, encoding: BodyEncoding) -> Vec<u8>
+    where
+        T: prost::Message + serde::Serialize,
+    {
+        match self {
+            Codec::Json => body_encoding::to_json_vec(message, encoding),
+            Codec::Proto => message.encode_to_vec(),
+        }
+    }
+}
+
+/// Wraps a message in an envelope.
+pub fn envelope(flags: u8, payload: &[u8]) -> Bytes {
+    let mut frame = Vec::with_capacity(5 + payload.len());
+    frame.push(flags);
+    frame.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    frame.extend_from_slice(payload);
+    Bytes::from(frame)
+}
+
+/// Builds the end-of-stream envelope, with `(message, error)` if the RPC
+/// failed.
+pub fn end_stream(error: Option<(&str, &Error)>) -> Bytes {
+    let body = match error {
+        Some((message, error)) => serde_json::json!({ "error": error_json(message, error) }),
+        None => serde_json::json!({}),
+    };
+    envelope(FLAG_END_STREAM, body.to_string().as_bytes())
+}
+
+/// The Connect error object for a failed request: the code for its kind,
+/// `message`, and `error` as a detail, both encoded and as JSON.
+pub fn error_json(message: &str, error: &Error) -> serde_json::Value {
+    serde_json::json!({
+        "code": error_code(error.kind()),
+        "message": message,
+        "details": [{
+            "type": errors::ERROR_TYPE,
+            "value": base64::engine::general_purpose::STANDARD_NO_PAD
+                .encode(prost::Message::encode_to_vec(error)),
+            "debug": error,
+        }],
+    })
+}
+
+/// Extracts the single request message from an enveloped request body.
+pub fn read_envelope(body: &[u8]) -> Result<&[u8], String> {
+    if body.len() < 5 {
+        return Err("Truncated request envelope".to_string());
+    }
+    if body[0] & FLAG_COMPRESSED != 0 {
+        return Err("Compressed request messages are not supported".to_string());
+    }
+    let len = u32::from_be_bytes([body[1], body[2], body[3], body[4]]) as usize;
+    body.get(5..5 + len)
+        .ok_or_else(|| "Truncated request envelope".to_string())
+}
+
+/// Extracts the codec and encoded message of a unary GET request from its
+/// query parameters (`encoding`, `message`, `base64`, `compression`).
+pub fn read_get_message(params: &HashMap<String, String>) -> Result<(Codec, Vec<u8>), String> {
+    let codec = match params.get("encoding").map(String::as_str) {
+        Some("json") => Codec::Json,
+        Some("proto") => Codec::Proto,
+        Some(other) => return Err(format!("Unsupported encoding: {}", other)),
+        None => return Err("Missing encoding parameter".to_string()),
+    };
+    if let Some(compression) = params.get("compression") {
+        if compression != "identity" {
+            return Err(format!("Unsupported compression: {}", compression));
+        }
+    }
+    let message = params
+        .get("message")
+        .map(String::as_str)
+        .unwrap_or_default();
+    let message = if params.get("base64").map(String::as_str) == Some("1") {
+        // URL-safe alphabet, padding optional
+        base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(message.trim_end_matches('='))
+            .map_err(|e| format!("Invalid base64 message: {}", e))?
+    } else {
+        message.as_bytes().to_vec()
+    };
+    Ok((codec, message))
+}
+
+/// HTTP status for a Connect error code.
+pub fn http_status(code: &str) -> StatusCode {
+    match code {
+        "canceled" => StatusCode::from_u16(499).unwrap(),
+        "invalid_argument" | "failed_precondition" | "out_of_range" => StatusCode::BAD_REQUEST,
+        "deadline_exceeded" => StatusCode::GATEWAY_TIMEOUT,
+        "not_found" => StatusCode::NOT_FOUND,
+        "already_exists" | "aborted" => StatusCode::CONFLICT,
+        "permission_denied" => StatusCode::FORBIDDEN,
+        "resource_exhausted" => StatusCode::TOO_MANY_REQUESTS,
+        "unimplemented" => StatusCode::NOT_IMPLEMENTED,
+        "unavailable" => StatusCode::SERVICE_UNAVAILABLE,
+        "unauthenticated" => StatusCode::UNAUTHORIZED,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Unary error response: `{"code": ..., "message": ..., "details": ...}`
+/// with the matching HTTP status.
+pub fn error_response(message: &str, error: &Error) -> Response {
+    (
+        http_status(error_code(error.kind())),
+        [(header::CONTENT_TYPE, "application/json")],
+        error_json(message, error).to_string(),
+    )
+        .into_response()
+}
+
+/// Maps the kind of a failed request to a Connect error code.
+pub fn error_code(kind: ErrorKind) -> &'static str {
+    match kind {
+        ErrorKind::Timeout => "deadline_exceeded",
+        ErrorKind::Canceled => "canceled",
+        ErrorKind::InvalidRequest => "invalid_argument",
+        ErrorKind::NotCached => "not_found",
+        _ => "unavailable",
+    }
+}
@@ -0,0 +1,575 @@
+//! RFC 6265 cookie jars, one per `ExecutionConfig.session_id`.
+//!
+//! Cronet's C API has no cookie store, so the service keeps one: cookies from
+//! `Set-Cookie` response headers are stored in the session's jar, and the
+//! matching ones are sent in a `Cookie` header on the session's next requests.
+//! Public suffixes are not known, so only single-label domains (`com`) are
+//! refused as cookie domains.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use url::Url;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+    /// Lowercase, without a leading dot.
+    pub domain: String,
+    #[serde(default = "default_path")]
+    pub path: String,
+    /// Only sent to `domain` itself, not its subdomains.
+    #[serde(default)]
+    pub host_only: bool,
+    #[serde(default)]
+    pub secure: bool,
+    #[serde(default)]
+    pub http_only: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub same_site: Option<String>,
+    /// Seconds since the Unix epoch; `None` for a session cookie.
+    #[serde(default)]
+    pub expires: Option<i64>,
+}
+
+fn default_path() -> String {
+    "/".to_string()
+}
+
+impl Cookie {
+    fn is_expired(&self, now: i64) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
+
+    fn same_key(&self, other: &Cookie) -> bool {
+        self.name == other.name && self.domain == other.domain && self.path == other.path
+    }
+
+    fn matches(&self, url: &Url, now: i64) -> bool {
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+        let host = host.to_ascii_lowercase();
+        let domain_ok = if self.host_only {
+            host == self.domain
+        } else {
+            domain_match(&host, &self.domain)
+        };
+        domain_ok
+            && path_match(url.path(), &self.path)
+            && (!self.secure || matches!(url.scheme(), "https" | "wss"))
+            && !self.is_expired(now)
+    }
+}
+
+/// Cookies kept per jar, as in Chrome; beyond it the oldest are dropped.
+pub const MAX_COOKIES: usize = 3300;
+/// Cookies kept per domain, as in Chrome; beyond it the domain's oldest are
+/// dropped.
+pub const MAX_COOKIES_PER_DOMAIN: usize = 180;
+
+/// Cookies of one session, in the order they were first set.
+#[derive(Debug, Default, Clone)]
+pub struct CookieJar {
+    cookies: Vec<Cookie>,
+}
+
+impl CookieJar {
+    /// Stores the cookie in a `Set-Cookie` header received from `url`.
+    /// Returns false if the header was ignored.
+    pub fn store_header(&mut self, url: &Url, header: &str) -> bool {
+        let now = now();
+        match parse_set_cookie(url, header, now) {
+            Some(cookie) => {
+                self.insert(cookie, now);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Stores `cookie`, replacing the one with the same name, domain and
+    /// path. An expired cookie only removes the one it replaces.
+    pub fn insert(&mut self, mut cookie: Cookie, now: i64) {
+        cookie.domain = cookie.domain.trim_start_matches('.').to_ascii_lowercase();
+        let existing = self.cookies.iter().position(|c| c.same_key(&cookie));
+        match (existing, cookie.is_expired(now)) {
+            (Some(i), true) => {
+                self.cookies.remove(i);
+            }
+            (Some(i), false) => self.cookies[i] = cookie,
+            (None, true) => {}
+            (None, false) => {
+                let domain = cookie.domain.clone();
+                self.cookies.push(cookie);
+                self.evict(&domain, now);
+            }
+        }
+    }
+
+    // Brings the jar and `domain` back within their limits: expired cookies
+    // go first, then the oldest
+    fn evict(&mut self, domain: &str, now: i64) {
+        let in_domain = |cookies: &[Cookie]| cookies.iter().filter(|c| c.domain == domain).count();
+        if self.cookies.len() <= MAX_COOKIES && in_domain(&self.cookies) <= MAX_COOKIES_PER_DOMAIN {
+            return;
+        }
+        self.cookies.retain(|c| !c.is_expired(now));
+        let mut excess = in_domain(&self.cookies).saturating_sub(MAX_COOKIES_PER_DOMAIN);
+        self.cookies.retain(|c| {
+            let evicted = excess > 0 && c.domain == domain;
+            excess -= evicted as usize;
+            !evicted
+        });
+        let excess = self.cookies.len().saturating_sub(MAX_COOKIES);
+        self.cookies.drain(..excess);
+    }
+
+    /// `Cookie` header value for a request to `url`, if any cookie matches.
+    /// Longer paths come first, then older cookies.
+    pub fn header_for(&self, url: &Url) -> Option<String> {
+        let now = now();
+        let mut matching: Vec<&Cookie> = self
+            .cookies
+            .iter()
+            .filter(|c| c.matches(url, now))
+            .collect();
+        if matching.is_empty() {
+            return None;
+        }
+        matching.sort_by_key(|c| std::cmp::Reverse(c.path.len()));
+        let pairs: Vec<String> = matching
+            .iter()
+            .map(|c| format!("{}={}", c.name, c.value))
+            .collect();
+        Some(pairs.join("; "))
+    }
+
+    /// Unexpired cookies, optionally only those for `domain` and its
+    /// subdomains.
+    pub fn list(&self, domain: Option<&str>) -> Vec<Cookie> {
+        let now = now();
+        let domain = domain.map(|d| d.trim_start_matches('.').to_ascii_lowercase());
+        self.cookies
+            .iter()
+            .filter(|c| !c.is_expired(now))
+            .filter(|c| domain.as_ref().is_none_or(|d| domain_match(&c.domain, d)))
+            .cloned()
+            .collect()
+    }
+
+    /// Removes the cookies matching every given field. Returns how many
+    /// were removed.
+    pub fn remove(
+        &mut self,
+        name: Option<&str>,
+        domain: Option<&str>,
+        path: Option<&str>,
+    ) -> usize {
+        let domain = domain.map(|d| d.trim_start_matches('.').to_ascii_lowercase());
+        let before = self.cookies.len();
+        self.cookies.retain(|c| {
+            !(name.is_none_or(|n| c.name == n)
+                && domain.as_ref().is_none_or(|d| c.domain == *d)
+                && path.is_none_or(|p| c.path == p))
+        });
+        before - self.cookies.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.cookies.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cookies.is_empty()
+    }
+
+    /// Netscape `cookies.txt`, as read by curl and wget. Session cookies are
+    /// written with an expiry of 0.
+    pub fn to_netscape(&self) -> String {
+        let mut out = String::from("# Netscape HTTP Cookie File\n");
+        for c in self.list(None) {
+            let domain = if c.host_only {
+                c.domain.clone()
+            } else {
+                format!(".{}", c.domain)
+            };
+            out.push_str(&format!(
+                "{}{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+                if c.http_only { "#HttpOnly_" } else { "" },
+                domain,
+                netscape_bool(!c.host_only),
+                c.path,
+                netscape_bool(c.secure),
+                c.expires.unwrap_or(0),
+                c.name,
+                c.value
+            ));
+        }
+        out
+    }
+
+    /// Adds the cookies of a Netscape `cookies.txt`. Returns how many were
+    /// imported.
+    pub fn import_netscape(&mut self, text: &str) -> Result<usize, String> {
+        let now = now();
+        let mut cookies = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let (line, http_only) = match line.strip_prefix("#HttpOnly_") {
+                Some(line) => (line, true),
+                None => (line, false),
+            };
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split('\t').collect();
+            if fields.len() != 7 {
+                return Err(format!(
+                    "Invalid cookie file: line {} has {} fields, expected 7",
+                    number + 1,
+                    fields.len()
+                ));
+            }
+            let expires: i64 = fields[4].trim().parse().map_err(|_| {
+                format!("Invalid cookie file: line {} has a bad expiry", number + 1)
+            })?;
+            cookies.push(Cookie {
+                name: fields[5].to_string(),
+                value: fields[6].trim_end_matches('\r').to_string(),
+                domain: fields[0].to_string(),
+                path: fields[2].to_string(),
+                host_only: !fields[1].eq_ignore_ascii_case("TRUE"),
+                secure: fields[3].eq_ignore_ascii_case("TRUE"),
+                http_only,
+                same_site: None,
+                expires: (expires != 0).then_some(expires),
+            });
+        }
+        let count = cookies.len();
+        for cookie in cookies {
+            self.insert(cookie, now);
+        }
+        Ok(count)
+    }
+
+    /// Adds `cookies`, e.g. from a JSON export. Returns how many were
+    /// imported.
+    pub fn import(&mut self, cookies: Vec<Cookie>) -> Result<usize, String> {
+        if let Some(cookie) = cookies
+            .iter()
+            .find(|c| c.name.is_empty() || c.domain.is_empty())
+        {
+            return Err(format!(
+                "Invalid cookie {:?}: name and domain are required",
+                cookie.name
+            ));
+        }
+        let now = now();
+        let count = cookies.len();
+        for cookie in cookies {
+            self.insert(cookie, now);
+        }
+        Ok(count)
+    }
+}
+
+fn netscape_bool(value: bool) -> &'static str {
+    if value {
+        "TRUE"
+    } else {
+        "FALSE"
+    }
+}
+
+/// Jars kept by default.
+pub const DEFAULT_MAX_JARS: usize = 10_000;
+/// Default time an unused jar is kept.
+pub const DEFAULT_JAR_TTL: Duration = Duration::from_secs(1800);
+
+/// Cookie jars by session id, created on first use. Jars unused for longer
+/// than the TTL are dropped, and at most `max_jars` are kept: a new jar
+/// replaces the least recently used one.
+pub struct CookieJars {
+    jars: Mutex<HashMap<String, JarEntry>>,
+    max_jars: usize,
+    ttl: Duration,
+}
+
+struct JarEntry {
+    jar: CookieJar,
+    last_used: Instant,
+}
+
+impl Default for CookieJars {
+    fn default() -> Self {
+        CookieJars::new(DEFAULT_MAX_JARS, DEFAULT_JAR_TTL)
+    }
+}
+
+impl CookieJars {
+    pub fn new(max_jars: usize, ttl: Duration) -> Self {
+        CookieJars {
+            jars: Mutex::new(HashMap::new()),
+            max_jars,
+            ttl,
+        }
+    }
+
+    /// Runs `f` on the jar of `session`, creating it if needed.
+    pub fn with<R>(&self, session: &str, f: impl FnOnce(&mut CookieJar) -> R) -> R {
+        let mut jars = self.jars.lock().unwrap();
+        if !jars.contains_key(session) && jars.len() >= self.max_jars {
+            let oldest = jars
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(key) = oldest {
+                jars.remove(&key);
+            }
+        }
+        let entry = jars.entry(session.to_string()).or_insert_with(|| JarEntry {
+            jar: CookieJar::default(),
+            last_used: Instant::now(),
+        });
+        entry.last_used = Instant::now();
+        f(&mut entry.jar)
+    }
+
+    /// Runs `f` on the jar of `session` if it exists.
+    pub fn with_existing<R>(
+        &self,
+        session: &str,
+        f: impl FnOnce(&mut CookieJar) -> R,
+    ) -> Option<R> {
+        let mut jars = self.jars.lock().unwrap();
+        let entry = jars.get_mut(session)?;
+        entry.last_used = Instant::now();
+        Some(f(&mut entry.jar))
+    }
+
+    /// A copy of the jar of `session`, if it exists.
+    pub fn get(&self, session: &str) -> Option<CookieJar> {
+        let jars = self.jars.lock().unwrap();
+        jars.get(session).map(|entry| entry.jar.clone())
+    }
+
+    /// Drops the jar of `session`. Returns false if there was none.
+    pub fn remove(&self, session: &str) -> bool {
+        self.jars.lock().unwrap().remove(session).is_some()
+    }
+
+    /// Drops jars unused for longer than the TTL. Returns how many.
+    pub fn evict_idle(&self) -> usize {
+        let mut jars = self.jars.lock().unwrap();
+        let before = jars.len();
+        let now = Instant::now();
+        jars.retain(|_, entry| now.duration_since(entry.last_used) < self.ttl);
+        before - jars.len()
+    }
+
+    /// Number of jars.
+    pub fn len(&self) -> usize {
+        self.jars.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64)
+}
+
+/// Parses a `Set-Cookie` header received from `url` (RFC 6265 section 5.2)
+/// and applies the storage rules of section 5.3.
+pub fn parse_set_cookie(url: &Url, header: &str, now: i64) -> Option<Cookie> {
+    let host = url.host_str()?.to_ascii_lowercase();
+    let mut parts = header.split(';');
+    let (name, value) = parts.next()?.split_once('=')?;
+    let (name, value) = (name.trim(), value.trim());
+    if name.is_empty() {
+        return None;
+    }
+
+    let mut expires = None;
+    let mut max_age = None;
+    let mut domain = None;
+    let mut path = None;
+    let mut secure = false;
+    let mut http_only = false;
+    let mut same_site = None;
+    for attribute in parts {
+        let (key, val) = match attribute.split_once('=') {
+            Some((key, val)) => (key.trim(), val.trim()),
+            None => (attribute.trim(), ""),
+        };
+        match key.to_ascii_lowercase().as_str() {
+            "expires" => expires = parse_cookie_date(val).or(expires),
+            "max-age" => {
+                let digits = val.strip_prefix('-').unwrap_or(val);
+                if !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()) {
+                    // Huge values saturate; non-positive ones expire at once
+                    let seconds = val.parse::<i64>().unwrap_or(if val.starts_with('-') {
+                        i64::MIN
+                    } else {
+                        i64::MAX
+                    });
+                    max_age = Some(if seconds <= 0 {
+                        i64::MIN
+                    } else {
+                        now.saturating_add(seconds)
+                    });
+                }
+            }
+            "domain" if !val.is_empty() => {
+                domain = Some(val.trim_start_matches('.').to_ascii_lowercase())
+            }
+            "path" => path = val.starts_with('/').then(|| val.to_string()),
+            "secure" => secure = true,
+            "httponly" => http_only = true,
+            "samesite" if !val.is_empty() => same_site = Some(val.to_string()),
+            _ => {}
+        }
+    }
+
+    if let Some(domain) = domain.as_ref().filter(|domain| **domain != host) {
+        let is_ip = host.trim_matches(['[', ']']).parse::<IpAddr>().is_ok();
+        if is_ip || !domain.contains('.') || !domain_match(&host, domain) {
+            return None;
+        }
+    }
+    // Secure cookies can only be set over a secure connection (RFC 6265bis)
+    if secure && !matches!(url.scheme(), "https" | "wss") {
+        return None;
+    }
+
+    Some(Cookie {
+        name: name.to_string(),
+        value: value.to_string(),
+        host_only: domain.is_none(),
+        domain: domain.unwrap_or(host),
+        path: path.unwrap_or_else(|| default_cookie_path(url.path())),
+        secure,
+        http_only,
+        same_site,
+        expires: max_age.or(expires),
+    })
+}
+
+/// `host` is `domain` or one of its subdomains (RFC 6265 section 5.1.3).
+fn domain_match(host: &str, domain: &str) -> bool {
+    host == domain
+        || (host.ends_with(domain)
+            && host[..host.len() - domain.len()].ends_with('.')
+            && host.trim_matches(['[', ']']).parse::<IpAddr>().is_err())
+}
+
+/// RFC 6265 section 5.1.4.
+fn path_match(request_path: &str, cookie_path: &str) -> bool {
+    request_path == cookie_path
+        || (request_path.starts_with(cookie_path)
+            && (cookie_path.ends_with('/') || request_path[cookie_path.len()..].starts_with('/')))
+}
+
+/// Directory of the request path, the default cookie path.
+fn default_cookie_path(request_path: &str) -> String {
+    match request_path.rfind('/') {
+        Some(0) | None => "/".to_string(),
+        Some(i) => request_path[..i].to_string(),
+    }
+}
+
+/// Parses a cookie date (RFC 6265 section 5.1.1) into seconds since the Unix
+/// epoch.
+pub fn parse_cookie_date(date: &str) -> Option<i64> {
+    let is_delimiter = |c: char| matches!(c, '\x09' | '\x20'..='\x2f' | '\x3b'..='\x40' | '\x5b'..='\x60' | '\x7b'..='\x7e');
+    let mut time = None;
+    let mut day = None;
+    let mut month = None;
+    let mut year = None;
+    for token in date.split(is_delimiter).filter(|t| !t.is_empty()) {
+        if time.is_none() {
+            if let Some(parsed) = parse_time(token) {
+                time = Some(parsed);
+                continue;
+            }
+        }
+        if day.is_none() {
+            if let Some(parsed) = leading_digits(token, 1, 2) {
+                day = Some(parsed);
+                continue;
+            }
+        }
+        if month.is_none() {
+            let prefix = token.get(..3).unwrap_or("").to_ascii_lowercase();
+            const MONTHS: [&str; 12] = [
+                "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+            ];
+            if let Some(i) = MONTHS.iter().position(|m| *m == prefix) {
+                month = Some(i as i64 + 1);
+                continue;
+            }
+        }
+        if year.is_none() {
+            if let Some(parsed) = leading_digits(token, 2, 4) {
+                year = Some(parsed);
+                continue;
+            }
+        }
+    }
+
+    let (hour, minute, second) = time?;
+    let (day, month, mut year) = (day?, month?, year?);
+    if (70..=99).contains(&year) {
+        year += 1900;
+    } else if (0..=69).contains(&year) {
+        year += 2000;
+    }
+    if !(1..=31).contains(&day) || year < 1601 || hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+    let days = days_from_civil(year, month, day);
+    Some(days * 86400 + hour * 3600 + minute * 60 + second)
+}
+
+// hh:mm:ss, each part one or two digits, optionally followed by non-digits
+fn parse_time(token: &str) -> Option<(i64, i64, i64)> {
+    let mut parts = token.splitn(3, ':');
+    let hour = exact_digits(parts.next()?, 1, 2)?;
+    let minute = exact_digits(parts.next()?, 1, 2)?;
+    let second = leading_digits(parts.next()?, 1, 2)?;
+    Some((hour, minute, second))
+}
+
+fn exact_digits(token: &str, min: usize, max: usize) -> Option<i64> {
+    if !(min..=max).contains(&token.len()) || !token.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    token.parse().ok()
+}
+
+// `min` to `max` digits, optionally followed by non-digits
+fn leading_digits(token: &str, min: usize, max: usize) -> Option<i64> {
+    let len = token.bytes().take_while(|b| b.is_ascii_digit()).count();
+    if !(min..=max).contains(&len) {
+        return None;
+    }
+    token[..len].parse().ok()
+}
+
+// Days since 1970-01-01 of a proleptic Gregorian date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_index = (month + 9) % 12;
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
@@ -1,8 +1,30 @@
 use crate::cronet_c::*;
 use crate::cronet_pb::proxy_config::ProxyType;
+use crate::cronet_pb::{CachePolicy, ErrorKind, Idempotency, RequestPriority};
+use crate::engine_pool::EnginePool;
+use crate::errors::RequestError;
+use crate::metrics;
 use std::ffi::{c_void, CStr, CString};
+use std::path::{Path, PathBuf};
 use std::ptr;
-use tokio::sync::oneshot;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::AsyncReadExt;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::{mpsc, oneshot};
+
+/// Size of each read buffer handed to Cronet.
+const READ_BUFFER_SIZE: u64 = 32 * 1024;
+
+/// Body chunks a streaming response may queue before reads are paused.
+const STREAM_CHANNEL_CAPACITY: usize = 8;
+
+/// Engines with a storage path keep their state in a numbered subdirectory,
+/// `engines/engine-<n>`, so clearing the cache can move to an empty one.
+/// Nothing else under the storage path is touched.
+const GENERATIONS_DIR: &str = "engines";
+const GENERATION_PREFIX: &str = "engine-";
 
 // -----------------------------------------------------------------------------
 // Cronet Engine
@@ -10,90 +32,543 @@ use tokio::sync::oneshot;
 
 // C Wrapper removed. Using pure Rust implementation.
 
+/// Settings an engine is started with, mirroring `Cronet_EngineParams`.
+/// Defaults: QUIC, HTTP/2 and Brotli on, no HTTP cache.
+#[derive(Debug, Clone)]
+pub struct EngineConfig {
+    user_agent: String,
+    accept_language: Option<String>,
+    enable_quic: bool,
+    enable_http2: bool,
+    enable_brotli: bool,
+    http_cache_mode: HttpCacheMode,
+    http_cache_max_size: i64,
+    storage_path: Option<PathBuf>,
+    network_thread_priority: Option<f64>,
+    enable_check_result: bool,
+    quic_hints: Vec<QuicHint>,
+    public_key_pins: Vec<PublicKeyPins>,
+    pin_bypass_for_local_trust_anchors: bool,
+    experimental_options: Option<String>,
+    proxy_rules: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HttpCacheMode {
+    #[default]
+    Disabled,
+    InMemory,
+    /// Disk cache that does not store HTTP data, only e.g. HTTP/2 settings.
+    DiskNoHttp,
+    Disk,
+}
+
+impl HttpCacheMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HttpCacheMode::Disabled => "disabled",
+            HttpCacheMode::InMemory => "memory",
+            HttpCacheMode::DiskNoHttp => "disk_no_http",
+            HttpCacheMode::Disk => "disk",
+        }
+    }
+}
+
+/// Tells the engine that `host:port` speaks QUIC on `alternate_port`, so
+/// HTTP/3 is used without an Alt-Svc round trip first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuicHint {
+    pub host: String,
+    pub port: u16,
+    pub alternate_port: u16,
+}
+
+/// Public key pins for `host`. Each pin is `sha256/<base64 SPKI hash>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublicKeyPins {
+    pub host: String,
+    pub pins_sha256: Vec<String>,
+    pub include_subdomains: bool,
+    /// Milliseconds since the Unix epoch, after which the pins are ignored.
+    pub expiration_date: i64,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        EngineConfig::new("CronetCloak/1.0")
+    }
+}
+
+impl EngineConfig {
+    pub fn new(user_agent: &str) -> Self {
+        EngineConfig {
+            user_agent: user_agent.to_string(),
+            accept_language: None,
+            enable_quic: true,
+            enable_http2: true,
+            enable_brotli: true,
+            http_cache_mode: HttpCacheMode::Disabled,
+            http_cache_max_size: 0,
+            storage_path: None,
+            network_thread_priority: None,
+            enable_check_result: true,
+            quic_hints: Vec::new(),
+            public_key_pins: Vec::new(),
+            pin_bypass_for_local_trust_anchors: true,
+            experimental_options: None,
+            proxy_rules: None,
+        }
+    }
+
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.user_agent = user_agent.to_string();
+        self
+    }
+
+    /// Default `Accept-Language`, e.g. `en-US,en;q=0.9`.
+    pub fn accept_language(mut self, accept_language: &str) -> Self {
+        self.accept_language = Some(accept_language.to_string());
+        self
+    }
+
+    pub fn enable_quic(mut self, enable: bool) -> Self {
+        self.enable_quic = enable;
+        self
+    }
+
+    pub fn enable_http2(mut self, enable: bool) -> Self {
+        self.enable_http2 = enable;
+        self
+    }
+
+    pub fn enable_brotli(mut self, enable: bool) -> Self {
+        self.enable_brotli = enable;
+        self
+    }
+
+    /// Disk modes need a `storage_path`.
+    pub fn http_cache(mut self, mode: HttpCacheMode, max_size: u64) -> Self {
+        self.http_cache_mode = mode;
+        self.http_cache_max_size = max_size.min(i64::MAX as u64) as i64;
+        self
+    }
+
+    /// Directory for the disk cache and other persistent state, kept in an
+    /// `engines/engine-<n>` subdirectory. No two running engines may share it.
+    pub fn storage_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.storage_path = Some(path.into());
+        self
+    }
+
+    /// Priority of the network thread, as an Android/Linux nice value.
+    pub fn network_thread_priority(mut self, priority: f64) -> Self {
+        self.network_thread_priority = Some(priority);
+        self
+    }
+
+    /// Whether Cronet checks the results of its API calls. On by default.
+    pub fn enable_check_result(mut self, enable: bool) -> Self {
+        self.enable_check_result = enable;
+        self
+    }
+
+    pub fn quic_hint(mut self, hint: QuicHint) -> Self {
+        self.quic_hints.push(hint);
+        self
+    }
+
+    pub fn public_key_pins(mut self, pins: PublicKeyPins) -> Self {
+        self.public_key_pins.push(pins);
+        self
+    }
+
+    /// Whether certificates chaining to a locally installed root skip pin
+    /// checks, as Chrome does. On by default.
+    pub fn pin_bypass_for_local_trust_anchors(mut self, enable: bool) -> Self {
+        self.pin_bypass_for_local_trust_anchors = enable;
+        self
+    }
+
+    /// Experimental Chromium options as a JSON object.
+    pub fn experimental_options(mut self, options: &str) -> Self {
+        self.experimental_options = Some(options.to_string());
+        self
+    }
+
+    /// Sends all traffic through `proxy_rules`, e.g. `socks5://host:1080`.
+    pub fn proxy_rules(mut self, proxy_rules: &str) -> Self {
+        self.proxy_rules = Some(proxy_rules.to_string());
+        self
+    }
+
+    // QUIC does not go through proxies
+    fn uses_quic(&self) -> bool {
+        self.enable_quic && self.proxy_rules.is_none()
+    }
+
+    /// The same settings for an engine behind a proxy.
+    pub(crate) fn for_proxy(&self, proxy_rules: &str) -> EngineConfig {
+        self.without_storage().proxy_rules(proxy_rules)
+    }
+
+    /// The same settings for another engine alongside this one. The storage
+    /// path is locked by the engine using it, so a disk cache becomes
+    /// in-memory.
+    pub(crate) fn without_storage(&self) -> EngineConfig {
+        let mut config = self.clone();
+        if config.storage_path.take().is_some()
+            && matches!(
+                config.http_cache_mode,
+                HttpCacheMode::Disk | HttpCacheMode::DiskNoHttp
+            )
+        {
+            config.http_cache_mode = HttpCacheMode::InMemory;
+        }
+        config
+    }
+
+    /// Creates and starts an engine with these settings.
+    unsafe fn start_engine(&self) -> Result<Cronet_EnginePtr, String> {
+        let params = Cronet_EngineParams_Create();
+        if let Err(e) = self.apply(params) {
+            Cronet_EngineParams_Destroy(params);
+            return Err(e);
+        }
+        let engine = Cronet_Engine_Create();
+        let res = Cronet_Engine_StartWithParams(engine, params);
+        Cronet_EngineParams_Destroy(params);
+
+        if res != Cronet_RESULT_Cronet_RESULT_SUCCESS {
+            Cronet_Engine_Destroy(engine);
+            return Err(format!("StartWithParams returned {:?}", res));
+        }
+        Ok(engine)
+    }
+
+    // Cronet copies every value, so the CStrings only need to outlive the call
+    unsafe fn apply(&self, params: Cronet_EngineParamsPtr) -> Result<(), String> {
+        let user_agent = c_string("user_agent", &self.user_agent)?;
+        Cronet_EngineParams_user_agent_set(params, user_agent.as_ptr());
+        if let Some(accept_language) = &self.accept_language {
+            let accept_language = c_string("accept_language", accept_language)?;
+            Cronet_EngineParams_accept_language_set(params, accept_language.as_ptr());
+        }
+
+        Cronet_EngineParams_enable_quic_set(params, self.enable_quic);
+        Cronet_EngineParams_enable_http2_set(params, self.enable_http2);
+        Cronet_EngineParams_enable_brotli_set(params, self.enable_brotli);
+
+        let cache_mode = match self.http_cache_mode {
+            HttpCacheMode::Disabled => {
+                Cronet_EngineParams_HTTP_CACHE_MODE_Cronet_EngineParams_HTTP_CACHE_MODE_DISABLED
+            }
+            HttpCacheMode::InMemory => {
+                Cronet_EngineParams_HTTP_CACHE_MODE_Cronet_EngineParams_HTTP_CACHE_MODE_IN_MEMORY
+            }
+            HttpCacheMode::DiskNoHttp => {
+                Cronet_EngineParams_HTTP_CACHE_MODE_Cronet_EngineParams_HTTP_CACHE_MODE_DISK_NO_HTTP
+            }
+            HttpCacheMode::Disk => {
+                Cronet_EngineParams_HTTP_CACHE_MODE_Cronet_EngineParams_HTTP_CACHE_MODE_DISK
+            }
+        };
+        Cronet_EngineParams_http_cache_mode_set(params, cache_mode);
+        Cronet_EngineParams_http_cache_max_size_set(params, self.http_cache_max_size);
+        if let Some(path) = &self.storage_path {
+            let path = c_string("storage_path", &path.to_string_lossy())?;
+            Cronet_EngineParams_storage_path_set(params, path.as_ptr());
+        }
+
+        if let Some(priority) = self.network_thread_priority {
+            Cronet_EngineParams_network_thread_priority_set(params, priority);
+        }
+        Cronet_EngineParams_enable_check_result_set(params, self.enable_check_result);
+
+        for hint in &self.quic_hints {
+            let host = c_string("quic hint host", &hint.host)?;
+            let quic_hint = Cronet_QuicHint_Create();
+            Cronet_QuicHint_host_set(quic_hint, host.as_ptr());
+            Cronet_QuicHint_port_set(quic_hint, hint.port as i32);
+            Cronet_QuicHint_alternate_port_set(quic_hint, hint.alternate_port as i32);
+            Cronet_EngineParams_quic_hints_add(params, quic_hint);
+            Cronet_QuicHint_Destroy(quic_hint);
+        }
+
+        for pins in &self.public_key_pins {
+            let host = c_string("pin host", &pins.host)?;
+            let hashes = pins
+                .pins_sha256
+                .iter()
+                .map(|pin| c_string("pin", pin))
+                .collect::<Result<Vec<_>, _>>()?;
+            let key_pins = Cronet_PublicKeyPins_Create();
+            Cronet_PublicKeyPins_host_set(key_pins, host.as_ptr());
+            for hash in &hashes {
+                Cronet_PublicKeyPins_pins_sha256_add(key_pins, hash.as_ptr());
+            }
+            Cronet_PublicKeyPins_include_subdomains_set(key_pins, pins.include_subdomains);
+            Cronet_PublicKeyPins_expiration_date_set(key_pins, pins.expiration_date);
+            Cronet_EngineParams_public_key_pins_add(params, key_pins);
+            Cronet_PublicKeyPins_Destroy(key_pins);
+        }
+        Cronet_EngineParams_enable_public_key_pinning_bypass_for_local_trust_anchors_set(
+            params,
+            self.pin_bypass_for_local_trust_anchors,
+        );
+
+        if let Some(options) = &self.experimental_options {
+            let options = c_string("experimental_options", options)?;
+            Cronet_EngineParams_experimental_options_set(params, options.as_ptr());
+        }
+        if let Some(proxy_rules) = &self.proxy_rules {
+            let proxy_rules = c_string("proxy rules", proxy_rules)?;
+            Cronet_EngineParams_proxy_rules_set(params, proxy_rules.as_ptr());
+        }
+        Ok(())
+    }
+}
+
+fn c_string(name: &str, value: &str) -> Result<CString, String> {
+    CString::new(value).map_err(|_| format!("Invalid {}: contains a NUL byte", name))
+}
+
 pub struct CronetEngine {
-    ptr: Cronet_EnginePtr,
+    // Engine for direct requests. Replaced by a fresh one when the cache is
+    // cleared; requests keep the engine they started on.
+    current: Mutex<Arc<EngineHandle>>,
+    // Serializes cache clears
+    clearing: Mutex<()>,
+    // Proxy engines are started with the same settings
+    config: EngineConfig,
+    // Engines for proxied requests, one per proxy configuration
+    proxy_engines: EnginePool,
+}
+
+/// HTTP cache settings and usage of an engine.
+#[derive(Debug, Clone)]
+pub struct CacheStats {
+    pub mode: HttpCacheMode,
+    pub max_size: u64,
+    /// Bytes on disk, `None` for engines without a storage path (Cronet does
+    /// not report the size of an in-memory cache).
+    pub disk_usage: Option<u64>,
+    pub storage_path: Option<PathBuf>,
 }
 
 impl CronetEngine {
+    /// Starts an engine with the default settings and `user_agent`.
+    /// Panics if the engine fails to start.
     pub fn new(user_agent: &str) -> Self {
-        unsafe {
-            let engine_ptr = Cronet_Engine_Create();
-            let params_ptr = Cronet_EngineParams_Create();
+        CronetEngine::with_config(EngineConfig::new(user_agent)).unwrap_or_else(|e| panic!("{}", e))
+    }
 
-            let c_ua = CString::new(user_agent).unwrap();
-            Cronet_EngineParams_user_agent_set(params_ptr, c_ua.as_ptr());
+    pub fn with_config(config: EngineConfig) -> Result<Self, String> {
+        let generation = config.storage_path.as_deref().map_or(0, latest_generation);
+        let engine = EngineHandle::start_generation(&config, generation)
+            .map_err(|e| format!("Failed to start Cronet Engine: {}", e))?;
+        Ok(CronetEngine {
+            current: Mutex::new(Arc::new(engine)),
+            clearing: Mutex::new(()),
+            config,
+            proxy_engines: EnginePool::default(),
+        })
+    }
 
-            // Use true for params
-            Cronet_EngineParams_enable_quic_set(params_ptr, true);
-            Cronet_EngineParams_enable_http2_set(params_ptr, true);
-            Cronet_EngineParams_enable_brotli_set(params_ptr, true);
+    pub fn config(&self) -> &EngineConfig {
+        &self.config
+    }
 
-            // Start the engine
-            let res = Cronet_Engine_StartWithParams(engine_ptr, params_ptr);
-            Cronet_EngineParams_Destroy(params_ptr);
+    /// Sets the limits of the proxy engine pool: at most `max_size` pooled
+    /// engines, each shut down after `idle_timeout` without requests.
+    pub fn with_proxy_pool(mut self, max_size: usize, idle_timeout: Duration) -> Self {
+        self.proxy_engines = EnginePool::new(max_size, idle_timeout);
+        self
+    }
 
-            if res != Cronet_RESULT_Cronet_RESULT_SUCCESS {
-                panic!("Failed to start Cronet Engine: {:?}", res);
-            }
+    /// Shuts down proxy engines that have been idle longer than the pool's
+    /// idle timeout. Returns how many were evicted.
+    pub fn evict_idle_proxy_engines(&self) -> usize {
+        self.proxy_engines.evict_idle()
+    }
+
+    fn engine(&self) -> Arc<EngineHandle> {
+        self.current.lock().unwrap().clone()
+    }
+
+    pub fn cache_stats(&self) -> CacheStats {
+        let engine = self.engine();
+        CacheStats {
+            mode: self.config.http_cache_mode,
+            max_size: self.config.http_cache_max_size as u64,
+            disk_usage: engine.storage_dir.as_deref().map(crate::cache::disk_usage),
+            storage_path: engine.storage_dir.clone(),
+        }
+    }
+
+    /// Empties the HTTP cache. Returns false if the engine has none.
+    ///
+    /// Cronet cannot clear the cache of a running engine, so new requests
+    /// move to a fresh engine on an empty directory. Requests in flight finish
+    /// on the old engine, which is then shut down and its directory removed.
+    /// Pooled proxy engines, whose caches are in memory, are dropped as well.
+    ///
+    /// Blocks while engines start and shut down, so call it off the async
+    /// runtime.
+    pub fn clear_cache(&self) -> Result<bool, String> {
+        if self.config.http_cache_mode == HttpCacheMode::Disabled {
+            return Ok(false);
+        }
+        let _clearing = self.clearing.lock().unwrap();
+        let generation = self.current.lock().unwrap().generation + 1;
+        let engine = EngineHandle::start_generation(&self.config, generation)
+            .map_err(|e| format!("Failed to start Cronet Engine: {}", e))?;
+        let previous = std::mem::replace(&mut *self.current.lock().unwrap(), Arc::new(engine));
+        previous.discarded.store(true, Ordering::SeqCst);
+        drop(previous);
+        self.proxy_engines.clear();
+        Ok(true)
+    }
+
+    /// Starts writing a NetLog of the engine's direct requests to `path`.
+    /// With `log_all`, bytes and cookies are logged as well. The capture
+    /// keeps the engine alive until it is stopped.
+    pub fn start_netlog(&self, path: &Path, log_all: bool) -> Result<NetLog, String> {
+        let engine = self.engine();
+        let c_path = c_string("NetLog path", &path.to_string_lossy())?;
+        if !unsafe { Cronet_Engine_StartNetLogToFile(engine.ptr, c_path.as_ptr(), log_all) } {
+            return Err(format!("Failed to start NetLog to {}", path.display()));
+        }
+        Ok(NetLog {
+            engine,
+            path: path.to_path_buf(),
+        })
+    }
 
-            CronetEngine { ptr: engine_ptr }
+    /// A one-off engine with this engine's settings, minus the storage path,
+    /// sending every request through `proxy` when given.
+    pub fn standalone(
+        &self,
+        proxy: Option<&crate::cronet_pb::ProxyConfig>,
+    ) -> Result<CronetEngine, String> {
+        CronetEngine::with_config(self.standalone_config(proxy))
+    }
+
+    /// The settings of [`standalone`](Self::standalone) engines.
+    pub fn standalone_config(&self, proxy: Option<&crate::cronet_pb::ProxyConfig>) -> EngineConfig {
+        let config = self.config.without_storage();
+        match proxy {
+            Some(proxy) => config.proxy_rules(&proxy_rules(proxy)),
+            None => config,
         }
     }
 
-    pub fn start_request(
+    pub async fn start_request(
         &self,
         target: &crate::cronet_pb::TargetRequest,
         config: &crate::cronet_pb::ExecutionConfig,
     ) -> (
         CronetRequest,
-        oneshot::Receiver<Result<RequestResult, String>>,
+        oneshot::Receiver<Result<RequestResult, RequestError>>,
     ) {
-        unsafe {
-            eprintln!("[DEBUG] start_request entered");
-            // Determine Engine to use (Shared or New Proxy Engine)
-            let (engine_ptr, owned_engine_ptr) = if let Some(proxy) = &config.proxy {
-                // Create Ad-hoc Engine with Proxy
-                let engine = Cronet_Engine_Create();
-                let params = Cronet_EngineParams_Create();
-
-                let scheme = match ProxyType::try_from(proxy.r#type).unwrap_or(ProxyType::Http) {
-                    ProxyType::Http => "http",
-                    ProxyType::Https => "https",
-                    ProxyType::Socks5 => "socks5",
-                };
-
-                // Build proxy URL with optional authentication
-                let rules = if !proxy.username.is_empty() && !proxy.password.is_empty() {
-                    format!(
-                        "{}://{}:{}@{}:{}",
-                        scheme, proxy.username, proxy.password, proxy.host, proxy.port
-                    )
-                } else {
-                    format!("{}://{}:{}", scheme, proxy.host, proxy.port)
-                };
-                let c_rules = CString::new(rules).expect("Invalid proxy string");
-
-                Cronet_EngineParams_proxy_rules_set(params, c_rules.as_ptr());
-
-                Cronet_EngineParams_enable_quic_set(params, true);
-                Cronet_EngineParams_enable_http2_set(params, true);
+        let (tx, rx) = oneshot::channel();
+        let request = self
+            .start(
+                target,
+                config,
+                body_upload(target),
+                ResponseSink::Buffered(tx),
+            )
+            .await;
+        (request, rx)
+    }
 
-                Cronet_Engine_StartWithParams(engine, params);
-                Cronet_EngineParams_Destroy(params);
+    /// Same as `start_request`, but uploads `upload` instead of `target.body`.
+    pub async fn start_request_with_upload(
+        &self,
+        target: &crate::cronet_pb::TargetRequest,
+        config: &crate::cronet_pb::ExecutionConfig,
+        upload: UploadBody,
+    ) -> (
+        CronetRequest,
+        oneshot::Receiver<Result<RequestResult, RequestError>>,
+    ) {
+        let (tx, rx) = oneshot::channel();
+        let request = self
+            .start(target, config, Some(upload), ResponseSink::Buffered(tx))
+            .await;
+        (request, rx)
+    }
 
-                (engine, Some(engine))
-            } else {
-                (self.ptr, None)
-            };
+    /// Starts a request whose body is delivered in chunks as it arrives.
+    ///
+    /// Reads are paused while the body channel is full, so a slow consumer
+    /// applies backpressure all the way to the socket. Must be called from
+    /// within a Tokio runtime.
+    pub async fn start_streaming_request(
+        &self,
+        target: &crate::cronet_pb::TargetRequest,
+        config: &crate::cronet_pb::ExecutionConfig,
+    ) -> (CronetRequest, StreamingResponse) {
+        let (head_tx, head) = oneshot::channel();
+        let (body_tx, body) = mpsc::channel(STREAM_CHANNEL_CAPACITY);
+        let (done_tx, done) = oneshot::channel();
+        let sink = ResponseSink::Streaming(StreamSink {
+            head: Some(head_tx),
+            body: body_tx,
+            done: Some(done_tx),
+            runtime: tokio::runtime::Handle::current(),
+        });
+        let request = self.start(target, config, body_upload(target), sink).await;
+        (request, StreamingResponse { head, body, done })
+    }
 
-            // Channel to receive the final result
-            let (tx, rx) = oneshot::channel();
+    async fn start(
+        &self,
+        target: &crate::cronet_pb::TargetRequest,
+        config: &crate::cronet_pb::ExecutionConfig,
+        upload: Option<UploadBody>,
+        sink: ResponseSink,
+    ) -> CronetRequest {
+        eprintln!("[DEBUG] start_request entered");
+        // Determine Engine to use (Shared or pooled Proxy Engine)
+        let engine = match &config.proxy {
+            Some(proxy) => match self
+                .proxy_engines
+                .get(&proxy_rules(proxy), &self.config)
+                .await
+            {
+                Ok(engine) => engine,
+                Err(e) => {
+                    return CronetRequest::failed(sink, RequestError::new(ErrorKind::Proxy, e))
+                }
+            },
+            None => self.engine(),
+        };
+        unsafe {
+            let engine_ptr = engine.ptr;
+            let cache_policy = CachePolicy::try_from(config.cache).unwrap_or(CachePolicy::CacheUse);
+
+            let shared = Arc::new(RequestShared {
+                request: Mutex::new(ptr::null_mut()),
+                timed_out: AtomicBool::new(false),
+                finished: AtomicBool::new(false),
+                parked: Mutex::new(None),
+                timing: Mutex::new(None),
+            });
 
             // Create Context to hold state across callbacks
             let context = Box::new(RequestContext {
-                tx: Some(tx),
-                response_buffer: Vec::new(),
-                status_code: 0,
+                sink,
+                result: RequestResult::default(),
+                timeout_ms: config.timeout_ms,
+                shared: shared.clone(),
+                redirect_policy: RedirectPolicy::from_config(config),
+                only_if_cached: cache_policy == CachePolicy::CacheOnlyIfCached,
+                quic: engine.quic,
+                pending_result: None,
+                outcome: None,
+                // The terminal callback and the request finished listener
+                pending_events: 2,
             });
 
             let context_ptr = Box::into_raw(context);
@@ -114,6 +589,14 @@ impl CronetEngine {
             );
             Cronet_UrlRequestCallback_SetClientContext(callback_ptr, context_ptr as *mut c_void);
 
+            // Metrics listener, runs on the same executor as the callbacks
+            let finished_listener_ptr =
+                Cronet_RequestFinishedInfoListener_CreateWith(Some(on_request_finished));
+            Cronet_RequestFinishedInfoListener_SetClientContext(
+                finished_listener_ptr,
+                context_ptr as *mut c_void,
+            );
+
             // Request & Params
             let request_ptr = Cronet_UrlRequest_Create();
             let params_ptr = Cronet_UrlRequestParams_Create();
@@ -123,6 +606,20 @@ impl CronetEngine {
 
             let c_url = CString::new(target.url.as_str()).unwrap();
 
+            Cronet_UrlRequestParams_request_finished_listener_set(
+                params_ptr,
+                finished_listener_ptr,
+            );
+            Cronet_UrlRequestParams_request_finished_executor_set(params_ptr, executor_ptr);
+            if cache_policy == CachePolicy::CacheBypass {
+                Cronet_UrlRequestParams_disable_cache_set(params_ptr, true);
+            }
+            Cronet_UrlRequestParams_priority_set(params_ptr, cronet_priority(config.priority()));
+            Cronet_UrlRequestParams_idempotency_set(
+                params_ptr,
+                cronet_idempotency(config.idempotency()),
+            );
+
             // Headers
             for (key, header_values) in &target.headers {
                 let c_key = CString::new(key.as_str()).unwrap();
@@ -142,22 +639,42 @@ impl CronetEngine {
             // Upload Data Provider (Body)
             let mut upload_data_provider_ptr: Option<Cronet_UploadDataProviderPtr> = None;
 
-            // Keep body alive
-            let upload_body_data = if !target.body.is_empty() {
-                Some(target.body.clone())
-            } else {
-                None
-            };
-
-            if let Some(body) = &upload_body_data {
+            if let Some(upload) = upload {
+                let (source, length) = match upload {
+                    UploadBody::Bytes(data) => {
+                        let length = data.len() as i64;
+                        (UploadSource::Bytes { data, position: 0 }, length)
+                    }
+                    UploadBody::Stream { chunks, length } => (
+                        UploadSource::Async(Some(AsyncUpload::Stream {
+                            chunks,
+                            pending: Vec::new(),
+                        })),
+                        length.map_or(-1, |len| len as i64),
+                    ),
+                    UploadBody::File(path) => {
+                        let length = std::fs::metadata(&path).map_or(-1, |m| m.len() as i64);
+                        (
+                            UploadSource::Async(Some(AsyncUpload::File { path, file: None })),
+                            length,
+                        )
+                    }
+                };
                 eprintln!(
                     "[DEBUG] Creating Rust UploadDataProvider. Body len: {}",
-                    body.len()
+                    length
                 );
 
                 let upload_context = Box::new(UploadContext {
-                    data: body.clone(),
-                    position: 0,
+                    runtime: match source {
+                        UploadSource::Async(_) => Some(tokio::runtime::Handle::current()),
+                        UploadSource::Bytes { .. } => None,
+                    },
+                    source,
+                    length,
+                    shared: shared.clone(),
+                    closed: Arc::new(Mutex::new(false)),
+                    read_task: None,
                 });
                 let upload_context_ptr = Box::into_raw(upload_context);
 
@@ -188,46 +705,352 @@ impl CronetEngine {
             );
 
             Cronet_UrlRequestParams_Destroy(params_ptr);
+            *shared.request.lock().unwrap() = request_ptr;
 
             // Start
             eprintln!("[DEBUG] Starting Cronet Request");
             Cronet_UrlRequest_Start(request_ptr);
 
             // Return Handle that owns the cleanup
-            let request_handle = CronetRequest {
+            CronetRequest {
                 ptr: request_ptr,
                 callback_ptr,
                 executor_ptr,
-                owned_engine_ptr,
+                finished_listener_ptr,
+                engine: Some(engine),
                 upload_data_provider_ptr,
-                upload_body_data,
-            };
-
-            (request_handle, rx)
+                shared,
+            }
         }
     }
 }
 
-impl Drop for CronetEngine {
+/// A running engine. Shut down when the last reference (owner, pool entry or
+/// in-flight request) goes away.
+pub struct EngineHandle {
+    ptr: Cronet_EnginePtr,
+    generation: u64,
+    // Whether requests may go over QUIC, for the protocol counters
+    quic: bool,
+    // This engine's subdirectory of the storage path
+    storage_dir: Option<PathBuf>,
+    // Set once the cache is cleared; the directory is removed on shutdown
+    discarded: AtomicBool,
+}
+
+impl EngineHandle {
+    /// Starts an engine with `config` as is.
+    pub fn start(config: &EngineConfig) -> Result<EngineHandle, String> {
+        let ptr = unsafe { config.start_engine() }?;
+        Ok(EngineHandle {
+            ptr,
+            generation: 0,
+            quic: config.uses_quic(),
+            storage_dir: None,
+            discarded: AtomicBool::new(false),
+        })
+    }
+
+    // Starts an engine whose state lives in the `generation` subdirectory of
+    // the storage path
+    fn start_generation(config: &EngineConfig, generation: u64) -> Result<EngineHandle, String> {
+        let Some(path) = &config.storage_path else {
+            return EngineHandle::start(config);
+        };
+        let dir = path
+            .join(GENERATIONS_DIR)
+            .join(format!("{}{}", GENERATION_PREFIX, generation));
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+        let ptr = unsafe { config.clone().storage_path(&dir).start_engine() }?;
+        Ok(EngineHandle {
+            ptr,
+            generation,
+            quic: config.uses_quic(),
+            storage_dir: Some(dir),
+            discarded: AtomicBool::new(false),
+        })
+    }
+}
+
+impl Drop for EngineHandle {
     fn drop(&mut self) {
         unsafe {
             Cronet_Engine_Shutdown(self.ptr);
             Cronet_Engine_Destroy(self.ptr);
         }
+        if let Some(dir) = &self.storage_dir {
+            if self.discarded.load(Ordering::SeqCst) {
+                if let Err(e) = std::fs::remove_dir_all(dir) {
+                    tracing::warn!("Failed to remove {}: {}", dir.display(), e);
+                }
+            }
+        }
+    }
+}
+
+unsafe impl Send for EngineHandle {}
+unsafe impl Sync for EngineHandle {}
+
+/// A NetLog capture in progress. Stopped, and the file completed, when
+/// dropped.
+pub struct NetLog {
+    engine: Arc<EngineHandle>,
+    path: PathBuf,
+}
+
+impl NetLog {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for NetLog {
+    fn drop(&mut self) {
+        // Blocks until the file is written out
+        unsafe { Cronet_Engine_StopNetLog(self.engine.ptr) };
+    }
+}
+
+/// Newest engine generation under the storage path `path`. Older ones are
+/// left over from a cache clear the process did not outlive, and are removed.
+fn latest_generation(path: &Path) -> u64 {
+    let generations: Vec<(u64, PathBuf)> = std::fs::read_dir(path.join(GENERATIONS_DIR))
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|entry| {
+                    let name = entry.file_name();
+                    let generation = name.to_str()?.strip_prefix(GENERATION_PREFIX)?;
+                    Some((generation.parse().ok()?, entry.path()))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let latest = generations.iter().map(|(generation, _)| *generation).max();
+    for (generation, dir) in &generations {
+        if Some(*generation) != latest {
+            let _ = std::fs::remove_dir_all(dir);
+        }
     }
+    latest.unwrap_or(0)
 }
 
-unsafe impl Send for CronetEngine {}
-unsafe impl Sync for CronetEngine {}
+/// Proxy rules for a proxy configuration. Also the pool key, so equivalent
+/// configurations share an engine.
+pub(crate) fn proxy_rules(proxy: &crate::cronet_pb::ProxyConfig) -> String {
+    let scheme = match ProxyType::try_from(proxy.r#type).unwrap_or(ProxyType::Http) {
+        ProxyType::Http => "http",
+        ProxyType::Https => "https",
+        ProxyType::Socks5 => "socks5",
+    };
+    let host = proxy.host.trim().to_ascii_lowercase();
+
+    // Build proxy URL with optional authentication
+    if !proxy.username.is_empty() && !proxy.password.is_empty() {
+        format!(
+            "{}://{}:{}@{}:{}",
+            scheme, proxy.username, proxy.password, host, proxy.port
+        )
+    } else {
+        format!("{}://{}:{}", scheme, host, proxy.port)
+    }
+}
 
 // -----------------------------------------------------------------------------
 // Request Infrastructure
 // -----------------------------------------------------------------------------
 
-#[derive(Debug)]
+#[derive(Debug, Default, Clone)]
 pub struct RequestResult {
     pub status_code: i32,
+    pub status_text: String,
+    /// Response headers in the order the server sent them.
+    pub headers: Vec<(String, String)>,
     pub body: Vec<u8>,
+    /// Redirects followed before the final response.
+    pub redirects: Vec<RedirectHop>,
+    /// URL of the final response, after redirects.
+    pub url: String,
+    /// Every URL visited, starting with the original request URL.
+    pub url_chain: Vec<String>,
+    /// ALPN protocol, e.g. "h2", "h3" or "http/1.1".
+    pub negotiated_protocol: String,
+    /// Proxy that served the response ("host:port"), empty when direct.
+    pub proxy_server: String,
+    pub was_cached: bool,
+    /// Bytes received over the network, including headers.
+    pub received_byte_count: i64,
+}
+
+/// Timestamps and connection reuse reported by Cronet's request finished
+/// listener. Timestamps are milliseconds since the Unix epoch and are `None`
+/// when the phase did not happen, e.g. DNS and connect on a reused socket.
+#[derive(Debug, Default, Clone)]
+pub struct RequestTiming {
+    pub request_start: Option<i64>,
+    pub dns_start: Option<i64>,
+    pub dns_end: Option<i64>,
+    pub connect_start: Option<i64>,
+    pub connect_end: Option<i64>,
+    pub ssl_start: Option<i64>,
+    pub ssl_end: Option<i64>,
+    pub sending_start: Option<i64>,
+    pub sending_end: Option<i64>,
+    pub response_start: Option<i64>,
+    pub request_end: Option<i64>,
+    pub socket_reused: bool,
+    pub sent_byte_count: i64,
+    pub received_byte_count: i64,
+}
+
+/// Details of a failure reported by Cronet's `on_failed`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CronetError {
+    /// Cronet's error category, a `Cronet_Error_ERROR_CODE`.
+    pub error_code: i32,
+    /// Chromium net error number, e.g. -105 for `ERR_NAME_NOT_RESOLVED`.
+    pub internal_error_code: i32,
+    /// QUIC error code, when QUIC failed.
+    pub quic_detailed_error_code: i32,
+    pub immediately_retryable: bool,
+}
+
+/// Request body handed to Cronet.
+pub enum UploadBody {
+    /// Whole body in memory.
+    Bytes(Vec<u8>),
+    /// Chunks pulled from an async source as Cronet asks for them. Without a
+    /// `length` the body is sent chunked. Cannot be rewound, so a redirect
+    /// that resends the body fails the request.
+    Stream {
+        chunks: mpsc::Receiver<Result<Vec<u8>, String>>,
+        length: Option<u64>,
+    },
+    /// File read from disk in chunks, reopened on rewind.
+    File(PathBuf),
+}
+
+impl UploadBody {
+    /// A copy to send again, unless the body is a one-shot stream.
+    pub fn try_clone(&self) -> Option<UploadBody> {
+        match self {
+            UploadBody::Bytes(data) => Some(UploadBody::Bytes(data.clone())),
+            UploadBody::Stream { .. } => None,
+            UploadBody::File(path) => Some(UploadBody::File(path.clone())),
+        }
+    }
+}
+
+fn cronet_priority(priority: RequestPriority) -> Cronet_UrlRequestParams_REQUEST_PRIORITY {
+    match priority {
+        RequestPriority::PriorityIdle => {
+            Cronet_UrlRequestParams_REQUEST_PRIORITY_Cronet_UrlRequestParams_REQUEST_PRIORITY_REQUEST_PRIORITY_IDLE
+        }
+        RequestPriority::PriorityLowest => {
+            Cronet_UrlRequestParams_REQUEST_PRIORITY_Cronet_UrlRequestParams_REQUEST_PRIORITY_REQUEST_PRIORITY_LOWEST
+        }
+        RequestPriority::PriorityLow => {
+            Cronet_UrlRequestParams_REQUEST_PRIORITY_Cronet_UrlRequestParams_REQUEST_PRIORITY_REQUEST_PRIORITY_LOW
+        }
+        RequestPriority::PriorityDefault | RequestPriority::PriorityMedium => {
+            Cronet_UrlRequestParams_REQUEST_PRIORITY_Cronet_UrlRequestParams_REQUEST_PRIORITY_REQUEST_PRIORITY_MEDIUM
+        }
+        RequestPriority::PriorityHighest => {
+            Cronet_UrlRequestParams_REQUEST_PRIORITY_Cronet_UrlRequestParams_REQUEST_PRIORITY_REQUEST_PRIORITY_HIGHEST
+        }
+    }
+}
+
+fn cronet_idempotency(idempotency: Idempotency) -> Cronet_UrlRequestParams_IDEMPOTENCY {
+    match idempotency {
+        Idempotency::Default => {
+            Cronet_UrlRequestParams_IDEMPOTENCY_Cronet_UrlRequestParams_IDEMPOTENCY_DEFAULT_IDEMPOTENCY
+        }
+        Idempotency::Idempotent => {
+            Cronet_UrlRequestParams_IDEMPOTENCY_Cronet_UrlRequestParams_IDEMPOTENCY_IDEMPOTENT
+        }
+        Idempotency::NotIdempotent => {
+            Cronet_UrlRequestParams_IDEMPOTENCY_Cronet_UrlRequestParams_IDEMPOTENCY_NOT_IDEMPOTENT
+        }
+    }
+}
+
+// Request body taken from the proto, if any
+fn body_upload(target: &crate::cronet_pb::TargetRequest) -> Option<UploadBody> {
+    (!target.body.is_empty()).then(|| UploadBody::Bytes(target.body.clone()))
+}
+
+/// Receiving side of a streaming request.
+pub struct StreamingResponse {
+    /// Status, headers and metadata (with an empty body) once the response
+    /// starts, or the error if it never does.
+    pub head: oneshot::Receiver<Result<RequestResult, RequestError>>,
+    /// Body chunks in order. Closed when the request finishes.
+    pub body: mpsc::Receiver<Vec<u8>>,
+    /// Outcome of the whole request, available once `body` is closed.
+    pub done: oneshot::Receiver<Result<(), RequestError>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RedirectHop {
+    pub url: String,
+    pub status_code: i32,
+    pub headers: Vec<(String, String)>,
+    pub location: String,
+}
+
+/// Chromium's own redirect limit, used when the config leaves it unset.
+const DEFAULT_MAX_REDIRECTS: u32 = 20;
+
+pub(crate) struct RedirectPolicy {
+    pub(crate) follow: bool,
+    max_redirects: u32,
+    deny_cross_origin: bool,
+    deny_insecure: bool,
+}
+
+impl RedirectPolicy {
+    pub(crate) fn from_config(config: &crate::cronet_pb::ExecutionConfig) -> Self {
+        RedirectPolicy {
+            follow: config.follow_redirects.unwrap_or(true),
+            max_redirects: if config.max_redirects == 0 {
+                DEFAULT_MAX_REDIRECTS
+            } else {
+                config.max_redirects
+            },
+            deny_cross_origin: config.deny_cross_origin_redirects,
+            deny_insecure: config.deny_insecure_redirects,
+        }
+    }
+
+    /// Returns why a redirect from `from` to `to` must not be followed, if any.
+    pub(crate) fn check(&self, from: &str, to: &str, hops_so_far: usize) -> Option<RequestError> {
+        let refuse = |message: String| Some(RequestError::new(ErrorKind::Redirect, message));
+        if hops_so_far as u32 >= self.max_redirects {
+            return refuse(format!(
+                "Too many redirects: limit of {} reached",
+                self.max_redirects
+            ));
+        }
+        if !self.deny_cross_origin && !self.deny_insecure {
+            return None;
+        }
+        let (from_url, to_url) = match (url::Url::parse(from), url::Url::parse(to)) {
+            (Ok(f), Ok(t)) => (f, t),
+            _ => return refuse(format!("Redirect refused: cannot parse {} -> {}", from, to)),
+        };
+        if self.deny_insecure && from_url.scheme() == "https" && to_url.scheme() == "http" {
+            return refuse(format!(
+                "Redirect refused: HTTPS to HTTP downgrade to {}",
+                to
+            ));
+        }
+        if self.deny_cross_origin && from_url.origin() != to_url.origin() {
+            return refuse(format!("Redirect refused: cross-origin redirect to {}", to));
+        }
+        None
+    }
 }
 
 #[allow(dead_code)]
@@ -235,43 +1058,232 @@ pub struct CronetRequest {
     ptr: Cronet_UrlRequestPtr,
     callback_ptr: Cronet_UrlRequestCallbackPtr,
     executor_ptr: Cronet_ExecutorPtr,
-    owned_engine_ptr: Option<Cronet_EnginePtr>,
+    finished_listener_ptr: Cronet_RequestFinishedInfoListenerPtr,
+    // Keeps the engine alive while the request uses it
+    engine: Option<Arc<EngineHandle>>,
     upload_data_provider_ptr: Option<Cronet_UploadDataProviderPtr>,
-    upload_body_data: Option<Vec<u8>>, // Owns the body data so pointers are valid
+    shared: Arc<RequestShared>,
 }
 
 unsafe impl Send for CronetRequest {}
 
+// State shared by the request handle, the callback context and the tasks that
+// resume a paused streaming read.
+struct RequestShared {
+    // Cleared by the handle when it destroys the request. Calls made from
+    // outside Cronet's callbacks go through this lock.
+    request: Mutex<Cronet_UrlRequestPtr>,
+    // Set by the request handle when the deadline expires, read by on_canceled
+    timed_out: AtomicBool,
+    // Set once the callback context has been released, under the `parked` lock
+    finished: AtomicBool,
+    // Handle dropped while the request was still running, destroyed once the
+    // callback context has been released
+    parked: Mutex<Option<CronetRequest>>,
+    // Filled in by the request finished listener
+    timing: Mutex<Option<RequestTiming>>,
+}
+
+unsafe impl Send for RequestShared {}
+unsafe impl Sync for RequestShared {}
+
+impl RequestShared {
+    unsafe fn cancel(&self) {
+        let request = self.request.lock().unwrap();
+        if !request.is_null() {
+            Cronet_UrlRequest_Cancel(*request);
+        }
+    }
+
+    // Issues the read that was paused for backpressure, unless the request
+    // ended in the meantime, in which case the buffer is still ours to free.
+    unsafe fn resume_read(&self, buffer: Cronet_BufferPtr) {
+        let request = self.request.lock().unwrap();
+        if request.is_null() || self.finished.load(Ordering::SeqCst) {
+            Cronet_Buffer_Destroy(buffer);
+            return;
+        }
+        Cronet_UrlRequest_Read(*request, buffer);
+    }
+}
+
+// Raw pointer moved into a task that resumes a read.
+struct SendPtr<T>(*mut T);
+
+unsafe impl<T> Send for SendPtr<T> {}
+
+impl<T> SendPtr<T> {
+    fn get(&self) -> *mut T {
+        self.0
+    }
+}
+
+impl CronetRequest {
+    // Handle for a request that failed before reaching Cronet
+    fn failed(sink: ResponseSink, error: RequestError) -> CronetRequest {
+        sink.finish(Err(error), RequestResult::default());
+        CronetRequest {
+            ptr: ptr::null_mut(),
+            callback_ptr: ptr::null_mut(),
+            executor_ptr: ptr::null_mut(),
+            finished_listener_ptr: ptr::null_mut(),
+            engine: None,
+            upload_data_provider_ptr: None,
+            shared: Arc::new(RequestShared {
+                request: Mutex::new(ptr::null_mut()),
+                timed_out: AtomicBool::new(false),
+                finished: AtomicBool::new(true),
+                parked: Mutex::new(None),
+                timing: Mutex::new(None),
+            }),
+        }
+    }
+
+    /// Cancels the in-flight request because its deadline expired.
+    ///
+    /// Cronet still delivers `on_canceled`, which reports a timeout error on the
+    /// result channel.
+    pub fn cancel_timed_out(&self) {
+        self.shared.timed_out.store(true, Ordering::SeqCst);
+        unsafe {
+            self.shared.cancel();
+        }
+    }
+
+    /// Cancels the in-flight request. The result channel reports `Canceled`.
+    pub fn cancel(&self) {
+        unsafe {
+            self.shared.cancel();
+        }
+    }
+
+    /// Timing breakdown of the finished request. Available once the result
+    /// channel has completed, for failed and canceled requests too.
+    pub fn timing(&self) -> Option<RequestTiming> {
+        self.shared.timing.lock().unwrap().clone()
+    }
+
+    // Moves the Cronet objects into a new handle, leaving this one empty
+    fn take(&mut self) -> CronetRequest {
+        CronetRequest {
+            ptr: std::mem::replace(&mut self.ptr, ptr::null_mut()),
+            callback_ptr: std::mem::replace(&mut self.callback_ptr, ptr::null_mut()),
+            executor_ptr: std::mem::replace(&mut self.executor_ptr, ptr::null_mut()),
+            finished_listener_ptr: std::mem::replace(
+                &mut self.finished_listener_ptr,
+                ptr::null_mut(),
+            ),
+            engine: self.engine.take(),
+            upload_data_provider_ptr: self.upload_data_provider_ptr.take(),
+            shared: self.shared.clone(),
+        }
+    }
+}
+
 impl Drop for CronetRequest {
     fn drop(&mut self) {
+        // Dropped while the request is still running, e.g. because the API
+        // client went away. Destroying it now would leave the callback context
+        // behind, so cancel it and let release_context destroy the handle once
+        // Cronet is done with it.
+        if !self.ptr.is_null() && !self.shared.finished.load(Ordering::SeqCst) {
+            let shared = self.shared.clone();
+            let mut parked = shared.parked.lock().unwrap();
+            if !shared.finished.load(Ordering::SeqCst) {
+                *parked = Some(self.take());
+                drop(parked);
+                unsafe {
+                    shared.cancel();
+                }
+                metrics::REQUESTS_ABANDONED.inc();
+                return;
+            }
+        }
+
         unsafe {
             // Destroy Request first (blocks until callbacks complete, IF called from another thread)
             if !self.ptr.is_null() {
+                let mut request = self.shared.request.lock().unwrap();
                 Cronet_UrlRequest_Destroy(self.ptr);
+                *request = ptr::null_mut();
             }
             if !self.callback_ptr.is_null() {
                 Cronet_UrlRequestCallback_Destroy(self.callback_ptr);
             }
+            if !self.finished_listener_ptr.is_null() {
+                Cronet_RequestFinishedInfoListener_Destroy(self.finished_listener_ptr);
+            }
             if !self.executor_ptr.is_null() {
                 Cronet_Executor_Destroy(self.executor_ptr);
             }
             if let Some(dp) = self.upload_data_provider_ptr {
                 Cronet_UploadDataProvider_Destroy(dp);
             }
-            // Finally destroy engine if we own it
-            if let Some(engine_ptr) = self.owned_engine_ptr {
-                Cronet_Engine_Shutdown(engine_ptr);
-                Cronet_Engine_Destroy(engine_ptr);
+        }
+        // The engine reference is released last, once the request is gone
+    }
+}
+
+// Where a request delivers its response
+enum ResponseSink {
+    // Body accumulated in memory and sent with the result
+    Buffered(oneshot::Sender<Result<RequestResult, RequestError>>),
+    // Head sent when the response starts, body chunks through a bounded channel
+    Streaming(StreamSink),
+}
+
+impl ResponseSink {
+    // Delivers the outcome of the request
+    fn finish(self, outcome: Result<(), RequestError>, result: RequestResult) {
+        match self {
+            ResponseSink::Buffered(tx) => {
+                let _ = tx.send(outcome.map(|_| result));
+            }
+            ResponseSink::Streaming(mut sink) => {
+                // The head is still pending if the request failed before the
+                // response started, or ended on an unfollowed redirect
+                if let Some(head) = sink.head.take() {
+                    let _ = head.send(outcome.clone().map(|_| result));
+                }
+                if let Some(done) = sink.done.take() {
+                    let _ = done.send(outcome);
+                }
             }
         }
     }
 }
 
+struct StreamSink {
+    head: Option<oneshot::Sender<Result<RequestResult, RequestError>>>,
+    body: mpsc::Sender<Vec<u8>>,
+    done: Option<oneshot::Sender<Result<(), RequestError>>>,
+    // Runs the tasks that wait for channel capacity
+    runtime: tokio::runtime::Handle,
+}
+
 // Context passed to C callbacks
 struct RequestContext {
-    tx: Option<oneshot::Sender<Result<RequestResult, String>>>,
-    response_buffer: Vec<u8>,
-    status_code: i32,
+    sink: ResponseSink,
+    // Filled in progressively by the callbacks and sent on success
+    result: RequestResult,
+    timeout_ms: u32,
+    shared: Arc<RequestShared>,
+    redirect_policy: RedirectPolicy,
+    // Fail responses that do not come from the cache
+    only_if_cached: bool,
+    // Whether the response may come over HTTP/3
+    quic: bool,
+    // Outcome to report from on_canceled when we cancel the request ourselves
+    pending_result: Option<Result<(), RequestError>>,
+    // Terminal outcome, sent once every pending event has fired
+    outcome: Option<Result<(), RequestError>>,
+    pending_events: u8,
+}
+
+/// Whether an ALPN protocol, e.g. "h3" or "h3-29", is HTTP/3 over QUIC.
+/// Older Cronet builds report QUIC as "quic/1+spdy/3".
+pub fn is_http3(negotiated_protocol: &str) -> bool {
+    negotiated_protocol.starts_with("h3") || negotiated_protocol.starts_with("quic")
 }
 
 // -----------------------------------------------------------------------------
@@ -285,14 +1297,95 @@ unsafe extern "C" fn executor_execute(_self: Cronet_ExecutorPtr, command: Cronet
     Cronet_Runnable_Destroy(command);
 }
 
+// Copies a Cronet-owned string; Cronet may hand out null for unset values.
+unsafe fn string_from_c(ptr: Cronet_String) -> String {
+    if ptr.is_null() {
+        String::new()
+    } else {
+        CStr::from_ptr(ptr).to_string_lossy().into_owned()
+    }
+}
+
+// Reads all response headers, preserving order and repeated names.
+unsafe fn response_headers(info: Cronet_UrlResponseInfoPtr) -> Vec<(String, String)> {
+    let count = Cronet_UrlResponseInfo_all_headers_list_size(info);
+    let mut headers = Vec::with_capacity(count as usize);
+    for i in 0..count {
+        let header = Cronet_UrlResponseInfo_all_headers_list_at(info, i);
+        headers.push((
+            string_from_c(Cronet_HttpHeader_name_get(header)),
+            string_from_c(Cronet_HttpHeader_value_get(header)),
+        ));
+    }
+    headers
+}
+
+// Copies status line, headers and connection metadata into the result.
+unsafe fn read_response_info(result: &mut RequestResult, info: Cronet_UrlResponseInfoPtr) {
+    result.status_code = Cronet_UrlResponseInfo_http_status_code_get(info);
+    result.status_text = string_from_c(Cronet_UrlResponseInfo_http_status_text_get(info));
+    result.headers = response_headers(info);
+    result.url = string_from_c(Cronet_UrlResponseInfo_url_get(info));
+    result.url_chain = (0..Cronet_UrlResponseInfo_url_chain_size(info))
+        .map(|i| string_from_c(Cronet_UrlResponseInfo_url_chain_at(info, i)))
+        .collect();
+    result.negotiated_protocol =
+        string_from_c(Cronet_UrlResponseInfo_negotiated_protocol_get(info));
+    result.proxy_server = string_from_c(Cronet_UrlResponseInfo_proxy_server_get(info));
+    result.was_cached = Cronet_UrlResponseInfo_was_cached_get(info);
+    result.received_byte_count = Cronet_UrlResponseInfo_received_byte_count_get(info);
+}
+
 // UrlRequest Callbacks
 unsafe extern "C" fn on_redirect_received(
-    _self: Cronet_UrlRequestCallbackPtr,
+    self_: Cronet_UrlRequestCallbackPtr,
     request: Cronet_UrlRequestPtr,
-    _info: Cronet_UrlResponseInfoPtr,
-    _new_location_url: Cronet_String,
+    info: Cronet_UrlResponseInfoPtr,
+    new_location_url: Cronet_String,
 ) {
-    Cronet_UrlRequest_FollowRedirect(request);
+    tracing::debug!("on_redirect_received");
+    let context_ptr = Cronet_UrlRequestCallback_GetClientContext(self_) as *mut RequestContext;
+    let context = &mut *context_ptr;
+
+    let hop = RedirectHop {
+        url: string_from_c(Cronet_UrlResponseInfo_url_get(info)),
+        status_code: Cronet_UrlResponseInfo_http_status_code_get(info),
+        headers: response_headers(info),
+        location: string_from_c(new_location_url),
+    };
+
+    if context.only_if_cached && !Cronet_UrlResponseInfo_was_cached_get(info) {
+        context.pending_result = Some(Err(RequestError::new(
+            ErrorKind::NotCached,
+            format!("Not cached: {}", hop.url),
+        )));
+        Cronet_UrlRequest_Cancel(request);
+        return;
+    }
+
+    if !context.redirect_policy.follow {
+        // Hand the 3xx response itself back to the caller
+        read_response_info(&mut context.result, info);
+        context.pending_result = Some(Ok(()));
+        Cronet_UrlRequest_Cancel(request);
+        return;
+    }
+
+    let refusal =
+        context
+            .redirect_policy
+            .check(&hop.url, &hop.location, context.result.redirects.len());
+    context.result.redirects.push(hop);
+
+    match refusal {
+        Some(reason) => {
+            context.pending_result = Some(Err(reason));
+            Cronet_UrlRequest_Cancel(request);
+        }
+        None => {
+            Cronet_UrlRequest_FollowRedirect(request);
+        }
+    }
 }
 
 unsafe extern "C" fn on_response_started(
@@ -304,10 +1397,34 @@ unsafe extern "C" fn on_response_started(
     let context_ptr = Cronet_UrlRequestCallback_GetClientContext(self_) as *mut RequestContext;
     let context = &mut *context_ptr;
 
-    context.status_code = Cronet_UrlResponseInfo_http_status_code_get(info);
+    read_response_info(&mut context.result, info);
+
+    if context.only_if_cached && !context.result.was_cached {
+        context.pending_result = Some(Err(RequestError::new(
+            ErrorKind::NotCached,
+            format!("Not cached: {}", context.result.url),
+        )));
+        Cronet_UrlRequest_Cancel(request);
+        return;
+    }
+
+    // Cached responses say nothing about the network path
+    if context.quic && !context.result.was_cached {
+        if is_http3(&context.result.negotiated_protocol) {
+            metrics::RESPONSES_HTTP3.inc();
+        } else {
+            metrics::RESPONSES_TCP.inc();
+        }
+    }
+
+    if let ResponseSink::Streaming(sink) = &mut context.sink {
+        if let Some(head) = sink.head.take() {
+            let _ = head.send(Ok(context.result.clone()));
+        }
+    }
 
     let buffer_ptr = Cronet_Buffer_Create();
-    Cronet_Buffer_InitWithAlloc(buffer_ptr, 32 * 1024);
+    Cronet_Buffer_InitWithAlloc(buffer_ptr, READ_BUFFER_SIZE);
 
     Cronet_UrlRequest_Read(request, buffer_ptr);
 }
@@ -325,22 +1442,67 @@ unsafe extern "C" fn on_read_completed(
 
     let data_ptr = Cronet_Buffer_GetData(buffer);
     let slice = std::slice::from_raw_parts(data_ptr as *const u8, bytes_read as usize);
-    context.response_buffer.extend_from_slice(slice);
 
-    Cronet_Buffer_Destroy(buffer);
+    let sink = match &mut context.sink {
+        ResponseSink::Buffered(_) => {
+            context.result.body.extend_from_slice(slice);
 
-    let new_buffer = Cronet_Buffer_Create();
-    Cronet_Buffer_InitWithAlloc(new_buffer, 32 * 1024);
+            Cronet_Buffer_Destroy(buffer);
 
-    Cronet_UrlRequest_Read(request, new_buffer);
+            let new_buffer = Cronet_Buffer_Create();
+            Cronet_Buffer_InitWithAlloc(new_buffer, READ_BUFFER_SIZE);
+
+            Cronet_UrlRequest_Read(request, new_buffer);
+            return;
+        }
+        ResponseSink::Streaming(sink) => sink,
+    };
+
+    // Streaming: the buffer is reused for the next read once the chunk is queued
+    let chunk = slice.to_vec();
+    match sink.body.try_reserve() {
+        Ok(permit) => {
+            permit.send(chunk);
+            Cronet_UrlRequest_Read(request, buffer);
+        }
+        Err(TrySendError::Full(())) => {
+            // Consumer is behind: read again once it makes room
+            let body = sink.body.clone();
+            let shared = context.shared.clone();
+            let buffer = SendPtr(buffer);
+            sink.runtime.spawn(async move {
+                match body.reserve().await {
+                    Ok(permit) => {
+                        permit.send(chunk);
+                        shared.resume_read(buffer.get());
+                    }
+                    Err(_) => {
+                        Cronet_Buffer_Destroy(buffer.get());
+                        shared.cancel();
+                    }
+                }
+            });
+        }
+        Err(TrySendError::Closed(())) => {
+            // Consumer went away
+            Cronet_Buffer_Destroy(buffer);
+            Cronet_UrlRequest_Cancel(request);
+        }
+    }
 }
 
 unsafe extern "C" fn on_succeeded(
     self_: Cronet_UrlRequestCallbackPtr,
     _request: Cronet_UrlRequestPtr,
-    _info: Cronet_UrlResponseInfoPtr,
+    info: Cronet_UrlResponseInfoPtr,
 ) {
     eprintln!("[DEBUG] on_succeeded");
+    let context_ptr = Cronet_UrlRequestCallback_GetClientContext(self_) as *mut RequestContext;
+    let context = &mut *context_ptr;
+
+    // The byte count keeps growing while the body is read
+    context.result.received_byte_count = Cronet_UrlResponseInfo_received_byte_count_get(info);
+
     complete_request(self_, Ok(()));
 }
 
@@ -354,7 +1516,13 @@ unsafe extern "C" fn on_failed(
     let msg = CStr::from_ptr(Cronet_Error_message_get(error))
         .to_string_lossy()
         .into_owned();
-    complete_request(self_, Err(msg));
+    let cronet = CronetError {
+        error_code: Cronet_Error_error_code_get(error) as i32,
+        internal_error_code: Cronet_Error_internal_error_code_get(error),
+        quic_detailed_error_code: Cronet_Error_quic_detailed_error_code_get(error),
+        immediately_retryable: Cronet_Error_immediately_retryable_get(error),
+    };
+    complete_request(self_, Err(RequestError::cronet(msg, cronet)));
 }
 
 unsafe extern "C" fn on_canceled(
@@ -363,31 +1531,108 @@ unsafe extern "C" fn on_canceled(
     _info: Cronet_UrlResponseInfoPtr,
 ) {
     eprintln!("[DEBUG] on_canceled");
-    complete_request(self_, Err("Canceled".to_string()));
+    let context_ptr = Cronet_UrlRequestCallback_GetClientContext(self_) as *mut RequestContext;
+    let context = &mut *context_ptr;
+
+    let result = if context.shared.timed_out.load(Ordering::SeqCst) {
+        Err(RequestError::new(
+            ErrorKind::Timeout,
+            format!("Timeout: request exceeded {} ms", context.timeout_ms),
+        ))
+    } else if let Some(pending) = context.pending_result.take() {
+        pending
+    } else {
+        Err(RequestError::new(ErrorKind::Canceled, "Canceled"))
+    };
+    complete_request(self_, result);
 }
 
-unsafe fn complete_request(callback_ptr: Cronet_UrlRequestCallbackPtr, result: Result<(), String>) {
+unsafe fn complete_request(
+    callback_ptr: Cronet_UrlRequestCallbackPtr,
+    result: Result<(), RequestError>,
+) {
     let context_ptr =
         Cronet_UrlRequestCallback_GetClientContext(callback_ptr) as *mut RequestContext;
+    eprintln!("[DEBUG] complete_request: {:?}", result);
+
+    (*context_ptr).outcome = Some(result);
+    release_context(context_ptr);
+}
+
+// Called once per pending event (terminal callback, finished listener). The
+// last one sends the result and frees the context, whichever order Cronet
+// delivers them in.
+unsafe fn release_context(context_ptr: *mut RequestContext) {
+    let context = &mut *context_ptr;
+    context.pending_events -= 1;
+    if context.pending_events > 0 {
+        return;
+    }
+
     // Take ownership back to drop it.
-    let context = Box::from_raw(context_ptr);
+    let context = *Box::from_raw(context_ptr);
+    let parked = {
+        let mut parked = context.shared.parked.lock().unwrap();
+        context.shared.finished.store(true, Ordering::SeqCst);
+        parked.take()
+    };
+    let result = context.outcome.unwrap_or_else(|| {
+        Err(RequestError::new(
+            ErrorKind::Internal,
+            "Request finished without a result",
+        ))
+    });
+
+    context.sink.finish(result, context.result);
+
+    if let Some(request) = parked {
+        // A request cannot be destroyed from its own callback
+        std::thread::spawn(move || drop(request));
+    }
+}
 
-    eprintln!("[DEBUG] complete_request: {:?}", result);
+// Reads an optional Cronet timestamp.
+unsafe fn date_time(ptr: Cronet_DateTimePtr) -> Option<i64> {
+    if ptr.is_null() {
+        None
+    } else {
+        Some(Cronet_DateTime_value_get(ptr))
+    }
+}
 
-    if let Some(tx) = context.tx {
-        match result {
-            Ok(_) => {
-                let res = RequestResult {
-                    status_code: context.status_code,
-                    body: context.response_buffer.clone(),
-                };
-                let _ = tx.send(Ok(res));
-            }
-            Err(e) => {
-                let _ = tx.send(Err(e));
-            }
-        }
+unsafe extern "C" fn on_request_finished(
+    self_: Cronet_RequestFinishedInfoListenerPtr,
+    request_info: Cronet_RequestFinishedInfoPtr,
+    _response_info: Cronet_UrlResponseInfoPtr,
+    _error: Cronet_ErrorPtr,
+) {
+    tracing::debug!("on_request_finished");
+    let context_ptr =
+        Cronet_RequestFinishedInfoListener_GetClientContext(self_) as *mut RequestContext;
+    let context = &mut *context_ptr;
+
+    let metrics = Cronet_RequestFinishedInfo_metrics_get(request_info);
+    if !metrics.is_null() {
+        let timing = RequestTiming {
+            request_start: date_time(Cronet_Metrics_request_start_get(metrics)),
+            dns_start: date_time(Cronet_Metrics_dns_start_get(metrics)),
+            dns_end: date_time(Cronet_Metrics_dns_end_get(metrics)),
+            connect_start: date_time(Cronet_Metrics_connect_start_get(metrics)),
+            connect_end: date_time(Cronet_Metrics_connect_end_get(metrics)),
+            ssl_start: date_time(Cronet_Metrics_ssl_start_get(metrics)),
+            ssl_end: date_time(Cronet_Metrics_ssl_end_get(metrics)),
+            sending_start: date_time(Cronet_Metrics_sending_start_get(metrics)),
+            sending_end: date_time(Cronet_Metrics_sending_end_get(metrics)),
+            response_start: date_time(Cronet_Metrics_response_start_get(metrics)),
+            request_end: date_time(Cronet_Metrics_request_end_get(metrics)),
+            socket_reused: Cronet_Metrics_socket_reused_get(metrics),
+            sent_byte_count: Cronet_Metrics_sent_byte_count_get(metrics),
+            received_byte_count: Cronet_Metrics_received_byte_count_get(metrics),
+        };
+        *context.shared.timing.lock().unwrap() = Some(timing);
     }
+
+    release_context(context_ptr);
 }
 
 // -----------------------------------------------------------------------------
@@ -395,14 +1640,89 @@ unsafe fn complete_request(callback_ptr: Cronet_UrlRequestCallbackPtr, result: R
 // -----------------------------------------------------------------------------
 
 struct UploadContext {
-    data: Vec<u8>,
-    position: u64,
+    source: UploadSource,
+    // -1 for chunked uploads
+    length: i64,
+    // Runs the reads of async sources
+    runtime: Option<tokio::runtime::Handle>,
+    shared: Arc<RequestShared>,
+    // Set by upload_close, which frees this context. A read in flight checks
+    // it under the lock before touching the context, the sink or Cronet's
+    // buffer, so close waits for a read that is completing.
+    closed: Arc<Mutex<bool>>,
+    // The read in flight, aborted on close
+    read_task: Option<tokio::task::AbortHandle>,
+}
+
+enum UploadSource {
+    Bytes { data: Vec<u8>, position: usize },
+    // None while a read is in flight
+    Async(Option<AsyncUpload>),
+}
+
+// Upload sources that must not be read on the network thread
+enum AsyncUpload {
+    Stream {
+        chunks: mpsc::Receiver<Result<Vec<u8>, String>>,
+        // Rest of a chunk larger than Cronet's buffer
+        pending: Vec<u8>,
+    },
+    File {
+        path: PathBuf,
+        file: Option<tokio::fs::File>,
+    },
+}
+
+impl AsyncUpload {
+    // Reads up to `max` bytes. Empty means the body has ended.
+    async fn read(&mut self, max: usize) -> Result<Vec<u8>, String> {
+        match self {
+            AsyncUpload::Stream { chunks, pending } => {
+                while pending.is_empty() {
+                    match chunks.recv().await {
+                        Some(Ok(chunk)) => *pending = chunk,
+                        Some(Err(e)) => return Err(e),
+                        None => return Ok(Vec::new()),
+                    }
+                }
+                let len = max.min(pending.len());
+                Ok(pending.drain(..len).collect())
+            }
+            AsyncUpload::File { path, file } => {
+                if file.is_none() {
+                    let opened = tokio::fs::File::open(&path)
+                        .await
+                        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+                    *file = Some(opened);
+                }
+                let mut data = vec![0; max];
+                let len = file
+                    .as_mut()
+                    .unwrap()
+                    .read(&mut data)
+                    .await
+                    .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+                data.truncate(len);
+                Ok(data)
+            }
+        }
+    }
+
+    fn rewind(&mut self) -> Result<(), String> {
+        match self {
+            AsyncUpload::Stream { .. } => Err("Upload stream cannot be rewound".to_string()),
+            AsyncUpload::File { file, .. } => {
+                *file = None;
+                Ok(())
+            }
+        }
+    }
 }
 
 unsafe extern "C" fn upload_get_length(self_: Cronet_UploadDataProviderPtr) -> i64 {
     let context_ptr = Cronet_UploadDataProvider_GetClientContext(self_) as *mut UploadContext;
     let context = &*context_ptr;
-    context.data.len() as i64
+    context.length
 }
 
 unsafe extern "C" fn upload_read(
@@ -416,19 +1736,74 @@ unsafe extern "C" fn upload_read(
     let buffer_size = Cronet_Buffer_GetSize(buffer);
     let buffer_data = Cronet_Buffer_GetData(buffer) as *mut u8;
 
-    let remaining = (context.data.len() as u64) - context.position;
-    let to_read = std::cmp::min(buffer_size, remaining);
-
-    if to_read > 0 {
-        ptr::copy_nonoverlapping(
-            context.data.as_ptr().add(context.position as usize),
-            buffer_data,
-            to_read as usize,
-        );
-        context.position += to_read;
-    }
+    let upload = match &mut context.source {
+        UploadSource::Bytes { data, position } => {
+            let to_read = std::cmp::min(buffer_size as usize, data.len() - *position);
+            if to_read > 0 {
+                ptr::copy_nonoverlapping(data.as_ptr().add(*position), buffer_data, to_read);
+                *position += to_read;
+            }
+            Cronet_UploadDataSink_OnReadSucceeded(sink, to_read as u64, false);
+            return;
+        }
+        UploadSource::Async(upload) => upload.take(),
+    };
+    let Some(mut upload) = upload else {
+        upload_read_error(sink, "Upload read already in progress");
+        return;
+    };
+
+    // Read on the runtime and complete the read from there. Cronet makes no
+    // other provider call until then, except upload_close if the request ends
+    // first: the task then finds `closed` set and leaves everything alone.
+    let chunked = context.length < 0;
+    let shared = context.shared.clone();
+    let closed = context.closed.clone();
+    let context_ptr = SendPtr(context_ptr);
+    let sink = SendPtr(sink);
+    let buffer_data = SendPtr(buffer_data);
+    let runtime = context
+        .runtime
+        .as_ref()
+        .expect("async upload without a runtime");
+    // Held until the task is recorded, so it cannot complete before that
+    let guard = context.closed.lock().unwrap();
+    let task = runtime.spawn(async move {
+        let result = upload.read(buffer_size as usize).await;
+
+        // Same lock order as request destruction, which may call upload_close
+        let request = shared.request.lock().unwrap();
+        let closed = closed.lock().unwrap();
+        if request.is_null() || *closed {
+            // Request destroyed or provider closed meanwhile, the sink,
+            // buffer and context are gone
+            return;
+        }
+        (*context_ptr.get()).read_task = None;
+        (*context_ptr.get()).source = UploadSource::Async(Some(upload));
+        match result {
+            Ok(data) if data.is_empty() && !chunked => {
+                upload_read_error(sink.get(), "Upload body ended before its declared length");
+            }
+            Ok(data) => {
+                ptr::copy_nonoverlapping(data.as_ptr(), buffer_data.get(), data.len());
+                // With a chunked upload, an empty read marks the end
+                Cronet_UploadDataSink_OnReadSucceeded(
+                    sink.get(),
+                    data.len() as u64,
+                    data.is_empty(),
+                );
+            }
+            Err(e) => upload_read_error(sink.get(), &e),
+        }
+    });
+    context.read_task = Some(task.abort_handle());
+    drop(guard);
+}
 
-    Cronet_UploadDataSink_OnReadSucceeded(sink, to_read, false);
+unsafe fn upload_read_error(sink: Cronet_UploadDataSinkPtr, message: &str) {
+    let c_message = CString::new(message.replace('\0', "")).unwrap();
+    Cronet_UploadDataSink_OnReadError(sink, c_message.as_ptr());
 }
 
 unsafe extern "C" fn upload_rewind(
@@ -437,12 +1812,30 @@ unsafe extern "C" fn upload_rewind(
 ) {
     let context_ptr = Cronet_UploadDataProvider_GetClientContext(self_) as *mut UploadContext;
     let context = &mut *context_ptr;
-    context.position = 0;
-    Cronet_UploadDataSink_OnRewindSucceeded(sink);
+    let result = match &mut context.source {
+        UploadSource::Bytes { position, .. } => {
+            *position = 0;
+            Ok(())
+        }
+        UploadSource::Async(Some(upload)) => upload.rewind(),
+        UploadSource::Async(None) => Err("Upload read in progress".to_string()),
+    };
+    match result {
+        Ok(()) => Cronet_UploadDataSink_OnRewindSucceeded(sink),
+        Err(e) => {
+            let c_message = CString::new(e).unwrap();
+            Cronet_UploadDataSink_OnRewindError(sink, c_message.as_ptr());
+        }
+    }
 }
 
 unsafe extern "C" fn upload_close(self_: Cronet_UploadDataProviderPtr) {
     let context_ptr = Cronet_UploadDataProvider_GetClientContext(self_) as *mut UploadContext;
     // Take ownership to drop
-    let _ = Box::from_raw(context_ptr);
+    let context = Box::from_raw(context_ptr);
+    // Waits for a read that is completing; any later one sees `closed`
+    *context.closed.lock().unwrap() = true;
+    if let Some(task) = &context.read_task {
+        task.abort();
+    }
 }
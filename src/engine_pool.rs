@@ -0,0 +1,168 @@
+//! Pool of proxy engines keyed by proxy configuration.
+//!
+//! Reusing an engine per proxy keeps its connections, TLS sessions and DNS
+//! cache warm across requests. Entries are reference counted: an engine
+//! evicted from the pool stays alive until its last in-flight request is done.
+
+use crate::cronet::{EngineConfig, EngineHandle};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Pooled engines kept by default.
+pub const DEFAULT_MAX_SIZE: usize = 16;
+/// Default time an unused engine is kept before it is shut down.
+pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+
+pub struct EnginePool {
+    entries: Mutex<HashMap<String, PoolEntry>>,
+    max_size: usize,
+    idle_timeout: Duration,
+}
+
+struct PoolEntry {
+    engine: Arc<EngineHandle>,
+    last_used: Instant,
+}
+
+impl PoolEntry {
+    // Only the pool holds the engine
+    fn is_unused(&self) -> bool {
+        Arc::strong_count(&self.engine) == 1
+    }
+}
+
+impl Default for EnginePool {
+    fn default() -> Self {
+        EnginePool::new(DEFAULT_MAX_SIZE, DEFAULT_IDLE_TIMEOUT)
+    }
+}
+
+impl EnginePool {
+    pub fn new(max_size: usize, idle_timeout: Duration) -> Self {
+        EnginePool {
+            entries: Mutex::new(HashMap::new()),
+            max_size,
+            idle_timeout,
+        }
+    }
+
+    /// Returns the engine for `proxy_rules`, starting one with `base`'s
+    /// settings if needed. When the pool is full of engines in use, the new
+    /// engine is not pooled and lives only as long as the request holding it.
+    pub async fn get(
+        &self,
+        proxy_rules: &str,
+        base: &EngineConfig,
+    ) -> Result<Arc<EngineHandle>, String> {
+        if let Some(entry) = self.entries.lock().unwrap().get_mut(proxy_rules) {
+            entry.last_used = Instant::now();
+            return Ok(entry.engine.clone());
+        }
+
+        // Starting an engine waits for its network thread, so it runs outside
+        // the lock and off the runtime. Concurrent misses may each start one;
+        // the first to be pooled wins.
+        let config = base.for_proxy(proxy_rules);
+        let engine = tokio::task::spawn_blocking(move || EngineHandle::start(&config))
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|started| started)
+            .map_err(|e| format!("Failed to start proxy engine: {}", e))?;
+        Ok(self.insert(proxy_rules, Arc::new(engine)))
+    }
+
+    // Pools `engine` under `proxy_rules`, unless another one got there first,
+    // and returns the pooled engine
+    fn insert(&self, proxy_rules: &str, engine: Arc<EngineHandle>) -> Arc<EngineHandle> {
+        let mut evicted = Vec::new();
+        let engine = {
+            let mut entries = self.entries.lock().unwrap();
+            let now = Instant::now();
+            if let Some(entry) = entries.get_mut(proxy_rules) {
+                entry.last_used = now;
+                shut_down(vec![engine]);
+                return entry.engine.clone();
+            }
+
+            evicted.extend(self.take_idle(&mut entries, now));
+            if entries.len() >= self.max_size {
+                // Make room by dropping the least recently used unused engine
+                let oldest = entries
+                    .iter()
+                    .filter(|(_, entry)| entry.is_unused())
+                    .min_by_key(|(_, entry)| entry.last_used)
+                    .map(|(key, _)| key.clone());
+                if let Some(key) = oldest {
+                    evicted.extend(entries.remove(&key));
+                }
+            }
+            if entries.len() < self.max_size {
+                entries.insert(
+                    proxy_rules.to_string(),
+                    PoolEntry {
+                        engine: engine.clone(),
+                        last_used: now,
+                    },
+                );
+            }
+            engine
+        };
+        // Shut evicted engines down outside the lock
+        shut_down(evicted);
+        engine
+    }
+
+    /// Shuts down engines that have been unused for longer than the idle
+    /// timeout. Returns how many were evicted.
+    pub fn evict_idle(&self) -> usize {
+        let evicted = {
+            let mut entries = self.entries.lock().unwrap();
+            self.take_idle(&mut entries, Instant::now())
+        };
+        let count = evicted.len();
+        shut_down(evicted);
+        count
+    }
+
+    /// Drops every pooled engine. Engines in use shut down once their
+    /// requests are done.
+    pub fn clear(&self) {
+        let entries = std::mem::take(&mut *self.entries.lock().unwrap());
+        shut_down(entries.into_values().collect());
+    }
+
+    /// Number of pooled engines.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn take_idle(&self, entries: &mut HashMap<String, PoolEntry>, now: Instant) -> Vec<PoolEntry> {
+        let idle: Vec<String> = entries
+            .iter()
+            .filter(|(_, entry)| {
+                entry.is_unused() && now.duration_since(entry.last_used) >= self.idle_timeout
+            })
+            .map(|(key, _)| key.clone())
+            .collect();
+        idle.iter().filter_map(|key| entries.remove(key)).collect()
+    }
+}
+
+// Drops engines on the blocking pool when called from a runtime, since
+// shutting an engine down waits for its network thread
+fn shut_down<T: Send + 'static>(engines: Vec<T>) {
+    if engines.is_empty() {
+        return;
+    }
+    match tokio::runtime::Handle::try_current() {
+        Ok(runtime) => {
+            runtime.spawn_blocking(move || drop(engines));
+        }
+        Err(_) => drop(engines),
+    }
+}
@@ -0,0 +1,178 @@
+//! Proto enum fields in JSON, by number or by name.
+//!
+//! A name is the proto enum name (`PRIORITY_HIGHEST`) or the same without
+//! the enum's prefix (`highest`), in any case and with `-` for `_`. Enum
+//! fields are `i32` in the generated code, so these take and give `i32`.
+
+use crate::cronet_pb::proxy_config::ProxyType;
+use crate::cronet_pb::{BodyEncoding, CachePolicy, ErrorKind, Idempotency, RequestPriority};
+use serde::de::Error as _;
+use serde::ser::SerializeSeq;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// A proto enum read from JSON by name.
+pub trait EnumName: Sized + Copy + Into<i32> + TryFrom<i32> + 'static {
+    /// Prefix of the proto names, left out of short names.
+    const PREFIX: &'static str;
+    /// What the enum is called in error messages.
+    const WHAT: &'static str;
+    /// Further names, normalized like the input: upper case, `_` for `-`.
+    const ALIASES: &'static [(&'static str, Self)] = &[];
+
+    fn from_str_name(name: &str) -> Option<Self>;
+    fn as_str_name(&self) -> &'static str;
+}
+
+/// Parses a proto name, short name or alias of `T`.
+pub fn parse<T: EnumName>(name: &str) -> Option<T> {
+    let name = name.to_ascii_uppercase().replace('-', "_");
+    T::from_str_name(&name)
+        .or_else(|| T::from_str_name(&format!("{}{}", T::PREFIX, name)))
+        .or_else(|| {
+            T::ALIASES
+                .iter()
+                .find(|(alias, _)| *alias == name)
+                .map(|(_, value)| *value)
+        })
+}
+
+/// Accepts an enum field as a number or a name.
+pub fn deserialize<'de, T: EnumName, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<i32, D::Error> {
+    Raw::deserialize(deserializer)?
+        .value::<T>()
+        .map_err(D::Error::custom)
+}
+
+/// `deserialize` for a repeated enum field.
+pub fn deserialize_list<'de, T: EnumName, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<i32>, D::Error> {
+    Vec::<Raw>::deserialize(deserializer)?
+        .into_iter()
+        .map(|raw| raw.value::<T>().map_err(D::Error::custom))
+        .collect()
+}
+
+/// Writes an enum field as its proto name, as proto3 JSON does. Values
+/// without a name are written as numbers.
+pub fn serialize<T: EnumName, S: Serializer>(
+    value: &i32,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match T::try_from(*value) {
+        Ok(value) => serializer.serialize_str(value.as_str_name()),
+        Err(_) => serializer.serialize_i32(*value),
+    }
+}
+
+/// `serialize` for a repeated enum field.
+pub fn serialize_list<T: EnumName, S: Serializer>(
+    values: &[i32],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    struct Named<T>(i32, std::marker::PhantomData<T>);
+    impl<T: EnumName> Serialize for Named<T> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serialize::<T, S>(&self.0, serializer)
+        }
+    }
+    let mut seq = serializer.serialize_seq(Some(values.len()))?;
+    for value in values {
+        seq.serialize_element(&Named::<T>(*value, std::marker::PhantomData))?;
+    }
+    seq.end()
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Raw {
+    Number(i32),
+    Name(String),
+}
+
+impl Raw {
+    fn value<T: EnumName>(self) -> Result<i32, String> {
+        match self {
+            Raw::Number(n) => Ok(n),
+            Raw::Name(name) => parse::<T>(&name)
+                .map(Into::into)
+                .ok_or_else(|| format!("unknown {}: {}", T::WHAT, name)),
+        }
+    }
+}
+
+impl EnumName for BodyEncoding {
+    const PREFIX: &'static str = "BODY_ENCODING_";
+    const WHAT: &'static str = "body encoding";
+    const ALIASES: &'static [(&'static str, Self)] =
+        &[("UTF_8", BodyEncoding::Utf8), ("TEXT", BodyEncoding::Utf8)];
+
+    fn from_str_name(name: &str) -> Option<Self> {
+        BodyEncoding::from_str_name(name)
+    }
+    fn as_str_name(&self) -> &'static str {
+        BodyEncoding::as_str_name(self)
+    }
+}
+
+impl EnumName for CachePolicy {
+    const PREFIX: &'static str = "CACHE_";
+    const WHAT: &'static str = "cache policy";
+
+    fn from_str_name(name: &str) -> Option<Self> {
+        CachePolicy::from_str_name(name)
+    }
+    fn as_str_name(&self) -> &'static str {
+        CachePolicy::as_str_name(self)
+    }
+}
+
+impl EnumName for RequestPriority {
+    const PREFIX: &'static str = "PRIORITY_";
+    const WHAT: &'static str = "priority";
+
+    fn from_str_name(name: &str) -> Option<Self> {
+        RequestPriority::from_str_name(name)
+    }
+    fn as_str_name(&self) -> &'static str {
+        RequestPriority::as_str_name(self)
+    }
+}
+
+impl EnumName for Idempotency {
+    const PREFIX: &'static str = "IDEMPOTENCY_";
+    const WHAT: &'static str = "idempotency";
+
+    fn from_str_name(name: &str) -> Option<Self> {
+        Idempotency::from_str_name(name)
+    }
+    fn as_str_name(&self) -> &'static str {
+        Idempotency::as_str_name(self)
+    }
+}
+
+impl EnumName for ErrorKind {
+    const PREFIX: &'static str = "ERROR_KIND_";
+    const WHAT: &'static str = "error kind";
+
+    fn from_str_name(name: &str) -> Option<Self> {
+        ErrorKind::from_str_name(name)
+    }
+    fn as_str_name(&self) -> &'static str {
+        ErrorKind::as_str_name(self)
+    }
+}
+
+impl EnumName for ProxyType {
+    const PREFIX: &'static str = "";
+    const WHAT: &'static str = "proxy type";
+
+    fn from_str_name(name: &str) -> Option<Self> {
+        ProxyType::from_str_name(name)
+    }
+    fn as_str_name(&self) -> &'static str {
+        ProxyType::as_str_name(self)
+    }
+}
@@ -0,0 +1,179 @@
+//! Classification of failed requests into `ExecuteResponse.error`.
+//!
+//! Failures reported by Cronet are classified by their net error number,
+//! falling back to Cronet's error category. Failures raised by the service
+//! itself get their kind where they are raised.
+
+use crate::cronet::CronetError;
+use crate::cronet_pb::{Error, ErrorKind};
+use std::fmt;
+
+/// Fully qualified name of `Error`, for error details.
+pub const ERROR_TYPE: &str = "cronet.engine.v1.Error";
+
+/// Why a request failed: the message returned to the caller, its kind and,
+/// when the network stack reported the failure, what Cronet said.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestError {
+    pub kind: ErrorKind,
+    pub message: String,
+    pub cronet: Option<CronetError>,
+}
+
+impl RequestError {
+    /// A failure raised by the service itself.
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        RequestError {
+            kind,
+            message: message.into(),
+            cronet: None,
+        }
+    }
+
+    /// A request the service refuses as it stands.
+    pub fn invalid(message: impl Into<String>) -> Self {
+        RequestError::new(ErrorKind::InvalidRequest, message)
+    }
+
+    /// A failure reported by Cronet's `on_failed`.
+    pub fn cronet(message: impl Into<String>, cronet: CronetError) -> Self {
+        RequestError {
+            kind: cronet_kind(&cronet),
+            message: message.into(),
+            cronet: Some(cronet),
+        }
+    }
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for RequestError {}
+
+/// The structured error reported for `error`. Codes are only set for
+/// failures from the network stack.
+pub fn describe(error: &RequestError) -> Error {
+    match &error.cronet {
+        Some(cronet) => Error {
+            kind: error.kind as i32,
+            error_code: cronet.error_code,
+            internal_error_code: cronet.internal_error_code,
+            quic_detailed_error_code: cronet.quic_detailed_error_code,
+            immediately_retryable: cronet.immediately_retryable,
+            quic_error: quic_error_name(cronet.quic_detailed_error_code),
+        },
+        None => Error {
+            kind: error.kind as i32,
+            ..Default::default()
+        },
+    }
+}
+
+/// Kind of a failure reported by Cronet.
+pub fn cronet_kind(error: &CronetError) -> ErrorKind {
+    net_error_kind(error.internal_error_code).unwrap_or(match error.error_code {
+        1 => ErrorKind::Dns,
+        2 | 5..=9 => ErrorKind::Connection,
+        3 => ErrorKind::NetworkChanged,
+        4 => ErrorKind::Timeout,
+        10 => ErrorKind::Quic,
+        _ if error.quic_detailed_error_code != 0 => ErrorKind::Quic,
+        _ => ErrorKind::Other,
+    })
+}
+
+/// Kind of a Chromium net error number, `None` for those not classified.
+pub fn net_error_kind(code: i32) -> Option<ErrorKind> {
+    let kind = match code {
+        -3 => ErrorKind::Canceled,
+        -7 => ErrorKind::Timeout,
+        -21 => ErrorKind::NetworkChanged,
+        // NAME_NOT_RESOLVED, NAME_RESOLUTION_FAILED and the DNS range
+        -105 | -137 | -899..=-800 => ErrorKind::Dns,
+        // CONNECTION_CLOSED .. CONNECTION_FAILED, INTERNET_DISCONNECTED,
+        // ADDRESS_INVALID, ADDRESS_UNREACHABLE, CONNECTION_TIMED_OUT,
+        // NETWORK_ACCESS_DENIED
+        -104..=-100 | -106 | -108 | -109 | -118 | -138 => ErrorKind::Connection,
+        // TUNNEL_CONNECTION_FAILED, PROXY_AUTH_UNSUPPORTED, SOCKS errors,
+        // PROXY_AUTH_REQUESTED, PROXY_CONNECTION_FAILED, proxy configuration
+        // and certificate errors, UNABLE_TO_REUSE_CONNECTION_FOR_PROXY_AUTH,
+        // UNEXPECTED_PROXY_AUTH
+        -111 | -115 | -120 | -121 | -127 | -130 | -131 | -136 | -140 | -170 | -323 => {
+            ErrorKind::Proxy
+        }
+        // Certificate errors
+        -299..=-200 => ErrorKind::Tls,
+        _ if TLS_ERRORS.contains(&code) => ErrorKind::Tls,
+        // SSL_PINNED_KEY_NOT_IN_CERT_CHAIN
+        -150 => ErrorKind::PinViolation,
+        // QUIC_PROTOCOL_ERROR, QUIC_HANDSHAKE_FAILED, QUIC_CERT_ROOT_NOT_KNOWN,
+        // QUIC_GOAWAY_REQUEST_CAN_BE_RETRIED
+        -356 | -358 | -380 | -381 => ErrorKind::Quic,
+        // INVALID_URL, DISALLOWED_URL_SCHEME, UNKNOWN_URL_SCHEME, UNSAFE_PORT
+        -302..=-300 | -312 => ErrorKind::InvalidRequest,
+        // INVALID_REDIRECT, TOO_MANY_REDIRECTS, UNSAFE_REDIRECT
+        -303 | -310 | -311 => ErrorKind::Redirect,
+        // CACHE_MISS
+        -400 => ErrorKind::NotCached,
+        _ => return None,
+    };
+    Some(kind)
+}
+
+// TLS errors among the connection errors. Listed one by one: other errors
+// are interleaved with them.
+const TLS_ERRORS: &[i32] = &[
+    -107, // SSL_PROTOCOL_ERROR
+    -110, // SSL_CLIENT_AUTH_CERT_NEEDED
+    -113, // SSL_VERSION_OR_CIPHER_MISMATCH
+    -114, // SSL_RENEGOTIATION_REQUESTED
+    -117, // BAD_SSL_CLIENT_AUTH_CERT
+    -122, // ALPN_NEGOTIATION_FAILED
+    -123, // SSL_NO_RENEGOTIATION
+    -125, // SSL_DECOMPRESSION_FAILURE_ALERT
+    -126, // SSL_BAD_RECORD_MAC_ALERT
+    -134, // SSL_CLIENT_AUTH_PRIVATE_KEY_ACCESS_DENIED
+    -135, // SSL_CLIENT_AUTH_CERT_NO_PRIVATE_KEY
+    -141, // SSL_CLIENT_AUTH_SIGNATURE_FAILED
+    -148, // SSL_HANDSHAKE_NOT_COMPLETED
+    -149, // SSL_BAD_PEER_PUBLIC_KEY
+    -151, // CLIENT_AUTH_CERT_TYPE_UNSUPPORTED
+    -153, // SSL_DECRYPT_ERROR_ALERT
+    -156, // SSL_SERVER_CERT_CHANGED
+    -159, // SSL_UNRECOGNIZED_NAME_ALERT
+    -164, // SSL_CLIENT_AUTH_CERT_BAD_FORMAT
+    -167, // SSL_SERVER_CERT_BAD_FORMAT
+    -172, // SSL_OBSOLETE_CIPHER
+    -177, // SSL_CLIENT_AUTH_NO_COMMON_ALGORITHMS
+    -178, // EARLY_DATA_REJECTED
+    -179, // WRONG_VERSION_ON_EARLY_DATA
+    -180, // TLS13_DOWNGRADE_DETECTED
+    -181, // SSL_KEY_USAGE_INCOMPATIBLE
+    -182, // INVALID_ECH_CONFIG_LIST
+    -183, // ECH_NOT_NEGOTIATED
+    -184, // ECH_FALLBACK_CERTIFICATE_INVALID
+];
+
+/// Name of a QUIC error code (`QuicErrorCode` in Chromium), empty for 0.
+pub fn quic_error_name(code: i32) -> String {
+    let name = match code {
+        0 => return String::new(),
+        1 => "QUIC_INTERNAL_ERROR",
+        16 => "QUIC_PEER_GOING_AWAY",
+        19 => "QUIC_PUBLIC_RESET",
+        20 => "QUIC_INVALID_VERSION",
+        25 => "QUIC_NETWORK_IDLE_TIMEOUT",
+        27 => "QUIC_PACKET_WRITE_ERROR",
+        28 => "QUIC_HANDSHAKE_FAILED",
+        42 => "QUIC_PROOF_INVALID",
+        51 => "QUIC_PACKET_READ_ERROR",
+        67 => "QUIC_HANDSHAKE_TIMEOUT",
+        70 => "QUIC_CONNECTION_CANCELLED",
+        85 => "QUIC_TOO_MANY_RTOS",
+        _ => return format!("QUIC_ERROR_{}", code),
+    };
+    name.to_string()
+}
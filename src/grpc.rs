@@ -0,0 +1,305 @@
+//! Native gRPC transport for `EngineService`, plus gRPC-Web.
+//!
+//! gRPC shares the HTTP port with the JSON API: `dispatch` sends requests
+//! with a gRPC content type to the Tonic router and everything else on.
+//! gRPC-Web requests are translated to gRPC and their responses back, with
+//! the trailers moved into the body.
+
+use crate::connect;
+use crate::cronet_pb::engine_service_server::{EngineService, EngineServiceServer};
+use crate::cronet_pb::{
+    Error, ExecuteBatchRequest, ExecuteRequest, ExecuteResponse, ExecuteStreamResponse,
+};
+use crate::errors::{self, RequestError};
+use crate::service::{self, AppState};
+use axum::body::{Body, Bytes};
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderMap, HeaderValue};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::Router;
+use base64::Engine as _;
+use futures::{stream, Stream, StreamExt};
+use http_body_util::BodyExt;
+use prost::Message;
+use std::pin::Pin;
+use tonic::{Code, Status};
+use tower::ServiceExt;
+
+pub struct EngineGrpc {
+    state: AppState,
+}
+
+/// Router serving `EngineService` over gRPC.
+pub fn router(state: AppState) -> Router {
+    tonic::service::Routes::new(EngineServiceServer::new(EngineGrpc { state })).into_axum_router()
+}
+
+// `google.rpc.Status`, the message gRPC carries in `grpc-status-details-bin`
+#[derive(Clone, PartialEq, Message)]
+struct RpcStatus {
+    #[prost(int32, tag = "1")]
+    code: i32,
+    #[prost(string, tag = "2")]
+    message: String,
+    #[prost(message, repeated, tag = "3")]
+    details: Vec<prost_types::Any>,
+}
+
+/// Maps a failed request to a gRPC status, with `error` in its details.
+pub fn status_from_error(message: &str, error: &Error) -> Status {
+    let code = match connect::error_code(error.kind()) {
+        "deadline_exceeded" => Code::DeadlineExceeded,
+        "canceled" => Code::Cancelled,
+        "invalid_argument" => Code::InvalidArgument,
+        "not_found" => Code::NotFound,
+        _ => Code::Unavailable,
+    };
+    let details = RpcStatus {
+        code: code as i32,
+        message: message.to_string(),
+        details: vec![prost_types::Any {
+            type_url: format!("type.googleapis.com/{}", errors::ERROR_TYPE),
+            value: error.encode_to_vec(),
+        }],
+    };
+    Status::with_details(code, message, details.encode_to_vec().into())
+}
+
+fn status_from_request_error(error: &RequestError) -> Status {
+    status_from_error(&error.message, &errors::describe(error))
+}
+
+type ExecuteStreamStream =
+    Pin<Box<dyn Stream<Item = Result<ExecuteStreamResponse, Status>> + Send>>;
+
+type ExecuteBatchStream = Pin<Box<dyn Stream<Item = Result<ExecuteResponse, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl EngineService for EngineGrpc {
+    async fn execute(
+        &self,
+        request: tonic::Request<ExecuteRequest>,
+    ) -> Result<tonic::Response<ExecuteResponse>, Status> {
+        let headers = request.metadata().clone().into_headers();
+        let mut request = request.into_inner();
+        service::apply_header_deadline(&headers, &mut request);
+
+        let response = service::execute(&self.state, request).await;
+        if response.success {
+            Ok(tonic::Response::new(response))
+        } else {
+            let error = response.error.unwrap_or_default();
+            Err(status_from_error(&response.error_message, &error))
+        }
+    }
+
+    type ExecuteStreamStream = ExecuteStreamStream;
+
+    async fn execute_stream(
+        &self,
+        request: tonic::Request<ExecuteRequest>,
+    ) -> Result<tonic::Response<Self::ExecuteStreamStream>, Status> {
+        let headers = request.metadata().clone().into_headers();
+        let mut request = request.into_inner();
+        service::apply_header_deadline(&headers, &mut request);
+
+        let target = request
+            .target
+            .as_ref()
+            .ok_or_else(|| Status::invalid_argument("Missing target configuration"))?;
+        let config = request.config.clone().unwrap_or_default();
+        let (head, stream) = service::start_stream(&self.state, target, &config)
+            .await
+            .map_err(|e| status_from_request_error(&e))?;
+
+        let request_id = request.request_id;
+        let first = ExecuteStreamResponse {
+            request_id: request_id.clone(),
+            head: Some(service::target_response(head)),
+            chunk: Vec::new(),
+        };
+        let rest = stream::unfold(Some(stream), move |stream| {
+            let request_id = request_id.clone();
+            async move {
+                let mut stream = stream?;
+                match stream.next_chunk().await? {
+                    Ok(chunk) => {
+                        let message = ExecuteStreamResponse {
+                            request_id,
+                            head: None,
+                            chunk,
+                        };
+                        Some((Ok(message), Some(stream)))
+                    }
+                    Err(e) => Some((Err(status_from_request_error(&e)), None)),
+                }
+            }
+        });
+        let messages: Self::ExecuteStreamStream = Box::pin(stream::iter([Ok(first)]).chain(rest));
+        Ok(tonic::Response::new(messages))
+    }
+
+    type ExecuteBatchStream = ExecuteBatchStream;
+
+    /// Failed requests are reported in their `ExecuteResponse`, not as a
+    /// status, so one failure does not hide the other results.
+    async fn execute_batch(
+        &self,
+        request: tonic::Request<ExecuteBatchRequest>,
+    ) -> Result<tonic::Response<Self::ExecuteBatchStream>, Status> {
+        let headers = request.metadata().clone().into_headers();
+        let mut batch = request.into_inner();
+        self.state
+            .check_batch_size(&batch)
+            .map_err(|e| status_from_request_error(&e))?;
+        for request in &mut batch.requests {
+            service::apply_header_deadline(&headers, request);
+        }
+
+        let responses = service::execute_batch(&self.state, batch)
+            .map(|(_, response)| response)
+            .map(Ok);
+        Ok(tonic::Response::new(Box::pin(responses)))
+    }
+}
+
+enum GrpcKind {
+    Grpc,
+    Web { text: bool },
+}
+
+impl GrpcKind {
+    fn of(headers: &HeaderMap) -> Option<GrpcKind> {
+        let content_type = headers.get(header::CONTENT_TYPE)?.to_str().ok()?;
+        let content_type = content_type.split(';').next()?.trim();
+        match content_type {
+            "application/grpc" | "application/grpc+proto" => Some(GrpcKind::Grpc),
+            "application/grpc-web" | "application/grpc-web+proto" => {
+                Some(GrpcKind::Web { text: false })
+            }
+            "application/grpc-web-text" | "application/grpc-web-text+proto" => {
+                Some(GrpcKind::Web { text: true })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Middleware sending gRPC and gRPC-Web requests to `grpc`.
+pub async fn dispatch(State(grpc): State<Router>, request: Request, next: Next) -> Response {
+    match GrpcKind::of(request.headers()) {
+        None => next.run(request).await,
+        Some(GrpcKind::Grpc) => grpc.oneshot(request).await.into_response(),
+        Some(GrpcKind::Web { text }) => grpc_web(grpc, request, text).await,
+    }
+}
+
+async fn grpc_web(grpc: Router, request: Request, text: bool) -> Response {
+    let (mut parts, body) = request.into_parts();
+    let body = if text {
+        let data = match body.collect().await {
+            Ok(collected) => collected.to_bytes(),
+            Err(e) => return web_error(text, Code::Internal, &e.to_string()),
+        };
+        match base64::engine::general_purpose::STANDARD.decode(&data) {
+            Ok(data) => Body::from(data),
+            Err(e) => return web_error(text, Code::InvalidArgument, &e.to_string()),
+        }
+    } else {
+        body
+    };
+    parts.headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/grpc"),
+    );
+    parts
+        .headers
+        .insert(header::TE, HeaderValue::from_static("trailers"));
+
+    let response = grpc
+        .oneshot(Request::from_parts(parts, body))
+        .await
+        .into_response();
+    let (mut parts, body) = response.into_parts();
+
+    // Trailers-only responses carry the status in the headers
+    let mut header_trailers = HeaderMap::new();
+    for name in ["grpc-status", "grpc-message", "grpc-status-details-bin"] {
+        if let Some(value) = parts.headers.remove(name) {
+            header_trailers.insert(name, value);
+        }
+    }
+    parts
+        .headers
+        .insert(header::CONTENT_TYPE, web_content_type(text));
+
+    let frames = stream::unfold(Some((body, header_trailers)), move |state| async move {
+        let (mut body, header_trailers) = state?;
+        let frame = match body.frame().await {
+            Some(Ok(frame)) => frame,
+            Some(Err(e)) => {
+                let mut trailers = HeaderMap::new();
+                status_trailers(&mut trailers, Code::Internal, &e.to_string());
+                return Some((web_chunk(trailer_frame(&trailers), text), None));
+            }
+            None if header_trailers.is_empty() => return None,
+            None => return Some((web_chunk(trailer_frame(&header_trailers), text), None)),
+        };
+        let chunk = match frame.into_data() {
+            Ok(data) => data,
+            Err(frame) => match frame.into_trailers() {
+                Ok(trailers) => trailer_frame(&trailers),
+                Err(_) => Bytes::new(),
+            },
+        };
+        Some((web_chunk(chunk, text), Some((body, header_trailers))))
+    });
+    Response::from_parts(
+        parts,
+        Body::from_stream(frames.map(Ok::<_, std::convert::Infallible>)),
+    )
+}
+
+fn web_content_type(text: bool) -> HeaderValue {
+    HeaderValue::from_static(if text {
+        "application/grpc-web-text+proto"
+    } else {
+        "application/grpc-web+proto"
+    })
+}
+
+fn web_chunk(chunk: Bytes, text: bool) -> Bytes {
+    if text {
+        Bytes::from(base64::engine::general_purpose::STANDARD.encode(&chunk))
+    } else {
+        chunk
+    }
+}
+
+// gRPC-Web sends trailers as a body frame with the high bit of the flags set
+fn trailer_frame(trailers: &HeaderMap) -> Bytes {
+    let mut block = Vec::new();
+    for (name, value) in trailers {
+        block.extend_from_slice(name.as_str().as_bytes());
+        block.extend_from_slice(b": ");
+        block.extend_from_slice(value.as_bytes());
+        block.extend_from_slice(b"\r\n");
+    }
+    connect::envelope(0x80, &block)
+}
+
+fn status_trailers(trailers: &mut HeaderMap, code: Code, message: &str) {
+    let _ = Status::new(code, message).add_header(trailers);
+}
+
+fn web_error(text: bool, code: Code, message: &str) -> Response {
+    let mut trailers = HeaderMap::new();
+    status_trailers(&mut trailers, code, message);
+    let mut response = Response::new(Body::empty());
+    response
+        .headers_mut()
+        .insert(header::CONTENT_TYPE, web_content_type(text));
+    response.headers_mut().extend(trailers);
+    response
+}
@@ -3,8 +3,23 @@
 #![allow(non_camel_case_types)]
 #![allow(non_snake_case)]
 
+pub mod body_encoding;
+pub mod cache;
+pub mod config;
+pub mod connect;
+pub mod cookies;
 pub mod cronet;
+pub mod engine_pool;
+pub mod enum_name;
+pub mod errors;
+pub mod grpc;
+pub mod metrics;
+pub mod netlog;
+pub mod random;
+pub mod retry;
+pub mod scheduler;
 pub mod service;
+pub mod sessions;
 
 // Include generated bindings
 pub mod cronet_c {
@@ -1,16 +1,99 @@
-use axum::{routing::post, Router};
+use axum::{extract::DefaultBodyLimit, middleware, routing::post, Router};
+use clap::Parser;
+use cronet_cloak::config::{Cli, Command, Config, EngineSettings};
 use cronet_cloak::cronet;
+use cronet_cloak::grpc;
 use cronet_cloak::service;
-use cronet_cloak::service::AppState;
+use cronet_cloak::service::{AppState, Profile};
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
+use tracing_subscriber::EnvFilter;
 
 #[tokio::main]
 async fn main() {
-    tracing_subscriber::fmt::init();
+    let cli = Cli::parse();
+    let config = match Config::from_overrides(&cli.overrides) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Invalid configuration:\n{}", e);
+            std::process::exit(2);
+        }
+    };
+    if let Some(Command::CheckConfig) = cli.command {
+        println!("Configuration OK\n");
+        print!("{}", toml::to_string(&config).unwrap_or_default());
+        return;
+    }
+
+    // RUST_LOG directives still apply when no level is given on the command
+    // line or in CRONET_CLOAK_LOG_LEVEL
+    let level = || EnvFilter::default().add_directive(config.log_level().into());
+    let mut invalid_rust_log = None;
+    let filter = match (&cli.overrides.log_level, EnvFilter::try_from_default_env()) {
+        (None, Ok(filter)) => filter,
+        (None, Err(e)) if std::env::var_os("RUST_LOG").is_some() => {
+            invalid_rust_log = Some(e);
+            level()
+        }
+        _ => level(),
+    };
+    tracing_subscriber::fmt().with_env_filter(filter).init();
+    if let Some(e) = invalid_rust_log {
+        tracing::warn!("Ignoring RUST_LOG: {}", e);
+    }
 
     // Initialize Cronet Engine
-    let engine = Arc::new(cronet::CronetEngine::new("CronetCloak/1.0"));
-    let state = AppState { engine };
+    let limits = &config.limits;
+    let start_engine = |settings: &EngineSettings| match cronet::CronetEngine::with_config(
+        settings.engine_config(),
+    ) {
+        Ok(engine) => Arc::new(engine.with_proxy_pool(
+            limits.proxy_pool_size,
+            Duration::from_secs(limits.proxy_idle_timeout_secs),
+        )),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let profiles: HashMap<String, Profile> = config
+        .profiles
+        .iter()
+        .map(|(name, profile)| {
+            let profile = Profile {
+                engine: start_engine(&profile.engine),
+                headers: profile.headers.clone().into_iter().collect(),
+            };
+            (name.clone(), profile)
+        })
+        .collect();
+    let state = AppState::new(start_engine(&config.engine), limits)
+        .with_profiles(profiles)
+        .with_netlog(&config.netlog);
+
+    // Shut down proxy engines and sessions nobody has used for a while
+    let pool_state = state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(60));
+        loop {
+            interval.tick().await;
+            for engine in pool_state.engines() {
+                engine.evict_idle_proxy_engines();
+            }
+            // Expired sessions shut their engines down
+            let state = pool_state.clone();
+            let expired = tokio::task::spawn_blocking(move || state.evict_expired_sessions())
+                .await
+                .unwrap_or(0);
+            if expired > 0 {
+                tracing::info!("{} sessions expired", expired);
+            }
+        }
+    });
+
+    // Native gRPC (and gRPC-Web) for EngineService
+    let grpc_router = grpc::router(state.clone());
 
     // Build Router
     // Connect-style path: /<package>.<Service>/<Method>
@@ -21,17 +104,83 @@ async fn main() {
         // Connect-RPC compatible path
         .route(
             "/cronet.engine.v1.EngineService/Execute",
-            post(service::execute_request),
+            post(service::execute_connect).get(service::execute_connect_get),
+        )
+        .route(
+            "/cronet.engine.v1.EngineService/ExecuteStream",
+            post(service::execute_stream_connect),
+        )
+        .route(
+            "/cronet.engine.v1.EngineService/ExecuteBatch",
+            post(service::execute_batch_connect),
         )
         // Simple REST path alias
         .route("/api/execute", post(service::execute_request))
         .route("/api/v1/execute", post(service::execute_request))
+        .route("/api/v1/execute/stream", post(service::execute_stream))
+        .route("/api/v1/execute/upload", post(service::execute_upload))
+        .route(
+            "/api/v1/execute/batch",
+            post(service::execute_batch_request),
+        )
         // Version endpoint
         .route("/version", axum::routing::get(service::get_version))
         .route("/api/version", axum::routing::get(service::get_version))
-        .with_state(state);
+        // Prometheus metrics
+        .route("/metrics", axum::routing::get(service::get_metrics))
+        .route(
+            "/api/v1/sessions",
+            axum::routing::get(service::list_sessions).post(service::create_session),
+        )
+        .route(
+            "/api/v1/sessions/:session_id",
+            axum::routing::get(service::get_session).delete(service::delete_session),
+        )
+        .route(
+            "/api/v1/sessions/:session_id/cookies",
+            axum::routing::get(service::list_cookies)
+                .post(service::set_cookies)
+                .delete(service::delete_cookies),
+        )
+        .route(
+            "/api/v1/sessions/:session_id/cookies/export",
+            axum::routing::get(service::export_cookies),
+        )
+        .route(
+            "/api/v1/sessions/:session_id/cookies/import",
+            post(service::import_cookies),
+        )
+        .route("/admin/netlog", axum::routing::get(service::list_netlogs))
+        .route("/admin/netlog/start", post(service::start_netlog))
+        .route("/admin/netlog/stop", post(service::stop_netlog))
+        .route(
+            "/admin/netlog/:name",
+            axum::routing::get(service::get_netlog).delete(service::delete_netlog),
+        )
+        .route(
+            "/admin/cache",
+            axum::routing::get(service::get_cache).delete(service::clear_cache),
+        )
+        .with_state(state)
+        .layer(DefaultBodyLimit::max(limits.max_body_size))
+        // gRPC shares the port, told apart by content type
+        .layer(middleware::from_fn_with_state(
+            grpc_router.clone(),
+            grpc::dispatch,
+        ));
+
+    // Optionally serve gRPC on a port of its own as well
+    if let Some(addr) = config.grpc_listen() {
+        let grpc_app =
+            Router::new().layer(middleware::from_fn_with_state(grpc_router, grpc::dispatch));
+        let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
+        println!("gRPC listening on {}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            axum::serve(listener, grpc_app).await.unwrap();
+        });
+    }
 
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await.unwrap();
+    let listener = tokio::net::TcpListener::bind(&config.listen).await.unwrap();
     println!("Listening on {}", listener.local_addr().unwrap());
     axum::serve(listener, app).await.unwrap();
 }
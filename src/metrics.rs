@@ -0,0 +1,66 @@
+//! Process-wide counters, served in the Prometheus text format at `/metrics`.
+
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+pub struct Counter {
+    name: &'static str,
+    help: &'static str,
+    value: AtomicU64,
+}
+
+impl Counter {
+    pub const fn new(name: &'static str, help: &'static str) -> Self {
+        Counter {
+            name,
+            help,
+            value: AtomicU64::new(0),
+        }
+    }
+
+    pub fn inc(&self) {
+        self.value.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.value.load(Ordering::Relaxed)
+    }
+}
+
+pub static REQUESTS_ABANDONED: Counter = Counter::new(
+    "cronet_cloak_requests_abandoned_total",
+    "Cronet requests cancelled because their caller went away before they finished.",
+);
+
+pub static REQUEST_RETRIES: Counter = Counter::new(
+    "cronet_cloak_request_retries_total",
+    "Attempts started again under a request's retry policy.",
+);
+
+pub static RESPONSES_HTTP3: Counter = Counter::new(
+    "cronet_cloak_responses_http3_total",
+    "Responses from QUIC-enabled engines that negotiated HTTP/3.",
+);
+
+pub static RESPONSES_TCP: Counter = Counter::new(
+    "cronet_cloak_responses_tcp_total",
+    "Responses from QUIC-enabled engines that did not negotiate HTTP/3.",
+);
+
+static COUNTERS: &[&Counter] = &[
+    &REQUESTS_ABANDONED,
+    &REQUEST_RETRIES,
+    &RESPONSES_HTTP3,
+    &RESPONSES_TCP,
+];
+
+/// Renders all counters in the Prometheus text exposition format.
+pub fn render() -> String {
+    let mut out = String::new();
+    for counter in COUNTERS {
+        let _ = writeln!(out, "# HELP {} {}", counter.name, counter.help);
+        let _ = writeln!(out, "# TYPE {} counter", counter.name);
+        let _ = writeln!(out, "{} {}", counter.name, counter.get());
+    }
+    out
+}
@@ -0,0 +1,366 @@
+//! NetLog captures for debugging network failures.
+//!
+//! A capture started through the admin API logs the direct requests of one
+//! engine until it is stopped or its file reaches the size limit. Cronet's C
+//! API has no size limit of its own, so the file is checked once a second
+//! and may overshoot a little.
+//!
+//! A request with `config.capture_netlog` runs on an engine of its own, so
+//! that its log holds nothing else, and gets the log back in the response.
+
+use crate::config::NetLogSettings;
+use crate::cronet::{CronetEngine, NetLog};
+use crate::cronet_pb::ProxyConfig;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// How often running captures are checked against their size limit
+const SIZE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+// Subdirectory for the logs of single requests, removed once read
+const REQUEST_DIR: &str = "requests";
+
+/// A capture file, finished or still being written.
+#[derive(Debug, Serialize)]
+pub struct NetLogFile {
+    pub name: String,
+    pub size: u64,
+    /// Seconds since the Unix epoch.
+    pub modified: u64,
+    /// Profile of the engine still writing the file; `None` once stopped.
+    pub capturing: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub log_all: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_size: Option<u64>,
+}
+
+// A capture started through the admin API
+struct Capture {
+    id: u64,
+    netlog: NetLog,
+    log_all: bool,
+    max_size: u64,
+}
+
+/// Captures started through the admin API, one per engine at most.
+pub struct NetLogs {
+    dir: PathBuf,
+    max_size: u64,
+    // By profile name, "" for the default engine; `None` while starting
+    active: Mutex<HashMap<String, Option<Capture>>>,
+    next_id: AtomicU64,
+}
+
+impl NetLogs {
+    pub fn new(settings: &NetLogSettings) -> Self {
+        NetLogs {
+            dir: settings.dir.clone(),
+            max_size: settings.max_size,
+            active: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(0),
+        }
+    }
+
+    /// Starts logging `engine`, the engine of `profile`, to a new file.
+    pub async fn start(
+        self: &Arc<Self>,
+        profile: &str,
+        engine: Arc<CronetEngine>,
+        log_all: bool,
+        max_size: Option<u64>,
+    ) -> Result<NetLogFile, String> {
+        let reservation = self.reserve(profile)?;
+        let dir = self.dir.clone();
+        let path = dir.join(format!("{}-{}.json", label(profile), unix_millis()));
+        // Starting a NetLog waits on the engine's network thread
+        let netlog = tokio::task::spawn_blocking({
+            let path = path.clone();
+            move || {
+                create_private_dir(&dir)?;
+                engine.start_netlog(&path, log_all)
+            }
+        })
+        .await
+        .unwrap_or_else(|e| Err(e.to_string()))?;
+        let capture = Capture {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            netlog,
+            log_all,
+            max_size: max_size.unwrap_or(self.max_size),
+        };
+        tracing::info!("NetLog of {} started: {}", label(profile), path.display());
+        let (id, info) = (capture.id, file_info(&path, Some((profile, &capture))));
+        reservation.fill(capture);
+        tokio::spawn(self.clone().enforce_limit(profile.to_string(), id));
+        Ok(info)
+    }
+
+    // Takes the slot of `profile` for a capture about to start
+    fn reserve<'a>(&'a self, profile: &'a str) -> Result<Reservation<'a>, String> {
+        let mut active = self.active.lock().unwrap();
+        if active.contains_key(profile) {
+            return Err(format!("NetLog already running: {}", label(profile)));
+        }
+        active.insert(profile.to_string(), None);
+        Ok(Reservation {
+            netlogs: self,
+            profile,
+            filled: false,
+        })
+    }
+
+    /// Stops the capture running on the engine of `profile`, if any, and
+    /// returns its file. Blocks until the file is written out.
+    pub fn stop(&self, profile: &str) -> Option<NetLogFile> {
+        let capture = {
+            let mut active = self.active.lock().unwrap();
+            // A capture still starting is left alone
+            active.get(profile)?.as_ref()?;
+            active.remove(profile)??
+        };
+        let path = capture.netlog.path().to_path_buf();
+        // Dropping the capture completes the file
+        drop(capture);
+        tracing::info!("NetLog of {} stopped", label(profile));
+        Some(file_info(&path, None))
+    }
+
+    // Stops the capture `id` once its file reaches the size limit
+    async fn enforce_limit(self: Arc<Self>, profile: String, id: u64) {
+        loop {
+            tokio::time::sleep(SIZE_CHECK_INTERVAL).await;
+            let capture = {
+                let mut active = self.active.lock().unwrap();
+                let capture = active.get(&profile).and_then(Option::as_ref);
+                let Some(capture) = capture.filter(|capture| capture.id == id) else {
+                    return;
+                };
+                let size = std::fs::metadata(capture.netlog.path()).map_or(0, |m| m.len());
+                if size < capture.max_size {
+                    continue;
+                }
+                active.remove(&profile).flatten()
+            };
+            // Stopping waits for the file to be written out
+            let _ = tokio::task::spawn_blocking(move || drop(capture)).await;
+            tracing::info!("NetLog of {} stopped at its size limit", label(&profile));
+            return;
+        }
+    }
+
+    /// Capture files, by name.
+    pub fn list(&self) -> Vec<NetLogFile> {
+        let active = self.active.lock().unwrap();
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        let mut files: Vec<NetLogFile> = entries
+            .flatten()
+            .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_file()))
+            .map(|entry| {
+                let path = entry.path();
+                let capture = active
+                    .iter()
+                    .filter_map(|(profile, capture)| Some((profile.as_str(), capture.as_ref()?)))
+                    .find(|(_, capture)| capture.netlog.path() == path);
+                file_info(&path, capture)
+            })
+            .collect();
+        files.sort_by(|a, b| a.name.cmp(&b.name));
+        files
+    }
+
+    /// Path of the capture file `name`. Only plain file names are accepted.
+    pub fn path(&self, name: &str) -> Result<PathBuf, String> {
+        if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+            return Err(format!("Unknown NetLog: {}", name));
+        }
+        let path = self.dir.join(name);
+        if !path.is_file() {
+            return Err(format!("Unknown NetLog: {}", name));
+        }
+        Ok(path)
+    }
+
+    /// Deletes the capture file `name`, unless it is still being written.
+    pub fn remove(&self, name: &str) -> Result<(), String> {
+        let path = self.path(name)?;
+        let active = self.active.lock().unwrap();
+        if active
+            .values()
+            .flatten()
+            .any(|capture| capture.netlog.path() == path)
+        {
+            return Err(format!("NetLog still running: {}", name));
+        }
+        std::fs::remove_file(&path).map_err(|e| format!("Cannot remove {}: {}", name, e))
+    }
+
+    /// Starts an engine with the settings of `base`, sending requests through
+    /// `proxy` when given, that logs everything it does.
+    pub async fn capture_request(
+        &self,
+        base: &CronetEngine,
+        proxy: Option<&ProxyConfig>,
+    ) -> Result<RequestCapture, String> {
+        let dir = self.dir.join(REQUEST_DIR);
+        let path = dir.join(format!(
+            "{}-{}.json",
+            unix_millis(),
+            self.next_id.fetch_add(1, Ordering::Relaxed)
+        ));
+        let config = base.standalone_config(proxy);
+        // Starting the engine waits on its network thread
+        tokio::task::spawn_blocking(move || {
+            create_private_dir(&dir)?;
+            let engine = CronetEngine::with_config(config)?;
+            let netlog = engine.start_netlog(&path, false)?;
+            Ok(RequestCapture {
+                running: Some((netlog, engine)),
+            })
+        })
+        .await
+        .unwrap_or_else(|e| Err(e.to_string()))
+    }
+}
+
+// The slot of a capture being started, freed again unless filled, e.g. when
+// the start fails or the caller goes away
+struct Reservation<'a> {
+    netlogs: &'a NetLogs,
+    profile: &'a str,
+    filled: bool,
+}
+
+impl Reservation<'_> {
+    fn fill(mut self, capture: Capture) {
+        let mut active = self.netlogs.active.lock().unwrap();
+        active.insert(self.profile.to_string(), Some(capture));
+        self.filled = true;
+    }
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        if !self.filled {
+            self.netlogs.active.lock().unwrap().remove(self.profile);
+        }
+    }
+}
+
+/// The NetLog of a single request, on an engine of its own. The file is
+/// removed once read, or when the capture is dropped.
+pub struct RequestCapture {
+    // Taken when the capture stops
+    running: Option<(NetLog, CronetEngine)>,
+}
+
+impl RequestCapture {
+    /// The engine to run the request on. Any proxy is built in, so the
+    /// request's own config should not set one.
+    pub fn engine(&self) -> &CronetEngine {
+        let (_, engine) = self.running.as_ref().expect("capture already stopped");
+        engine
+    }
+
+    /// Stops the capture and returns the log.
+    pub async fn finish(mut self) -> Result<String, String> {
+        let running = self.running.take().expect("capture finished twice");
+        tokio::task::spawn_blocking(move || {
+            let path = stop(running);
+            let log = std::fs::read_to_string(&path)
+                .map_err(|e| format!("Cannot read {}: {}", path.display(), e));
+            let _ = std::fs::remove_file(&path);
+            log
+        })
+        .await
+        .unwrap_or_else(|e| Err(e.to_string()))
+    }
+}
+
+impl Drop for RequestCapture {
+    fn drop(&mut self) {
+        let Some(running) = self.running.take() else {
+            return;
+        };
+        let discard = move || {
+            let _ = std::fs::remove_file(stop(running));
+        };
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => {
+                runtime.spawn_blocking(discard);
+            }
+            Err(_) => discard(),
+        }
+    }
+}
+
+// Stops the log and shuts its engine down, which waits for the file to be
+// written out and for the network thread. Returns the file.
+fn stop((netlog, engine): (NetLog, CronetEngine)) -> PathBuf {
+    let path = netlog.path().to_path_buf();
+    drop(netlog);
+    drop(engine);
+    path
+}
+
+// Logs hold URLs, headers and, with `log_all`, cookies and bodies, so the
+// directories are created readable by the owner only
+fn create_private_dir(dir: &Path) -> Result<(), String> {
+    let mut builder = std::fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder
+        .create(dir)
+        .map_err(|e| format!("Cannot create {}: {}", dir.display(), e))
+}
+
+// Name of the engine in file names and messages
+fn label(profile: &str) -> String {
+    if profile.is_empty() {
+        return "default".to_string();
+    }
+    profile
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn unix_millis() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis())
+}
+
+// Size and modification time are 0 if the file cannot be read
+fn file_info(path: &Path, capture: Option<(&str, &Capture)>) -> NetLogFile {
+    let metadata = std::fs::metadata(path).ok();
+    let modified = metadata
+        .as_ref()
+        .and_then(|metadata| metadata.modified().ok())
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_secs());
+    NetLogFile {
+        name: path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        size: metadata.map_or(0, |metadata| metadata.len()),
+        modified,
+        capturing: capture.map(|(profile, _)| profile.to_string()),
+        log_all: capture.map(|(_, capture)| capture.log_all),
+        max_size: capture.map(|(_, capture)| capture.max_size),
+    }
+}
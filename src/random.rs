@@ -0,0 +1,13 @@
+//! Randomness from the thread-local CSPRNG of `rand`.
+
+use rand::Rng;
+
+/// 128 random bits as 32 hex digits, for ids that must not be guessed.
+pub fn token() -> String {
+    format!("{:032x}", rand::thread_rng().gen::<u128>())
+}
+
+/// Uniform in [0, 1), for jitter.
+pub fn fraction() -> f64 {
+    rand::thread_rng().gen::<f64>()
+}
@@ -0,0 +1,131 @@
+//! Retry policy of a request, from `config.retry`.
+//!
+//! A request is retried when Cronet reports a failure it considers
+//! immediately retryable, when the failure is of a kind the policy lists, or
+//! when the response has a status the policy lists. Requests that are not
+//! idempotent, by `config.idempotency` or else by their method, are only
+//! retried when the policy says so.
+
+use crate::cookies::parse_cookie_date;
+use crate::cronet::CronetError;
+use crate::cronet_pb::{ErrorKind, ExecutionConfig, Idempotency};
+use crate::errors::cronet_kind;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(10);
+const DEFAULT_STATUSES: [i32; 4] = [429, 502, 503, 504];
+const DEFAULT_KINDS: [ErrorKind; 2] = [ErrorKind::Connection, ErrorKind::NetworkChanged];
+/// Most attempts a policy allows, whatever the client asks for.
+pub const MAX_ATTEMPTS: u32 = 10;
+/// Longest delay before a retry, whatever the client asks for.
+pub const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    statuses: Vec<i32>,
+    kinds: Vec<i32>,
+    honor_retry_after: bool,
+}
+
+impl RetryPolicy {
+    /// The policy of `config` for a request with `method`, or `None` when
+    /// the request is not retried.
+    pub fn from_config(config: &ExecutionConfig, method: &str) -> Option<Self> {
+        let retry = config.retry.as_ref()?;
+        let idempotent = match config.idempotency() {
+            Idempotency::Default => is_idempotent(method),
+            Idempotency::Idempotent => true,
+            Idempotency::NotIdempotent => false,
+        };
+        if retry.max_attempts <= 1 || !(retry.retry_non_idempotent || idempotent) {
+            return None;
+        }
+        let initial_backoff = match retry.initial_backoff_ms {
+            0 => DEFAULT_INITIAL_BACKOFF,
+            ms => Duration::from_millis(ms as u64).min(MAX_BACKOFF),
+        };
+        let max_backoff = match retry.max_backoff_ms {
+            0 => DEFAULT_MAX_BACKOFF,
+            ms => Duration::from_millis(ms as u64).min(MAX_BACKOFF),
+        };
+        Some(RetryPolicy {
+            max_attempts: retry.max_attempts.min(MAX_ATTEMPTS),
+            initial_backoff,
+            max_backoff: max_backoff.max(initial_backoff),
+            statuses: if retry.retry_on_status.is_empty() {
+                DEFAULT_STATUSES.to_vec()
+            } else {
+                retry.retry_on_status.clone()
+            },
+            kinds: if retry.retry_on_errors.is_empty() {
+                DEFAULT_KINDS.iter().map(|kind| *kind as i32).collect()
+            } else {
+                retry.retry_on_errors.clone()
+            },
+            honor_retry_after: !retry.ignore_retry_after,
+        })
+    }
+
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// Whether a response with `status` is retried.
+    pub fn retries_status(&self, status: i32) -> bool {
+        self.statuses.contains(&status)
+    }
+
+    /// Whether a failure Cronet reported as `error` is retried.
+    pub fn retries_error(&self, error: &CronetError) -> bool {
+        error.immediately_retryable || self.kinds.contains(&(cronet_kind(error) as i32))
+    }
+
+    /// Delay before retry number `retry`, 1 for the first, after a response
+    /// with `headers`. `None` when the response's Retry-After asks for
+    /// longer than the policy waits.
+    pub fn delay(&self, retry: u32, headers: &[(String, String)]) -> Option<Duration> {
+        if self.honor_retry_after {
+            let retry_after = headers
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case("retry-after"))
+                .and_then(|(_, value)| retry_after(value, unix_now()));
+            if let Some(retry_after) = retry_after {
+                return (retry_after <= self.max_backoff).then_some(retry_after);
+            }
+        }
+        let backoff = self
+            .initial_backoff
+            .saturating_mul(1 << retry.saturating_sub(1).min(16))
+            .min(self.max_backoff);
+        Some(backoff - backoff.mul_f64(crate::random::fraction() / 2.0))
+    }
+}
+
+/// Whether `method` may be sent twice with the effect of once (RFC 9110
+/// section 9.2.2).
+pub fn is_idempotent(method: &str) -> bool {
+    ["GET", "HEAD", "OPTIONS", "TRACE", "PUT", "DELETE"]
+        .iter()
+        .any(|m| m.eq_ignore_ascii_case(method))
+}
+
+/// Parses a Retry-After value, delay seconds or an HTTP date, into the time
+/// to wait from `now` (seconds since the Unix epoch).
+pub fn retry_after(value: &str, now: i64) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = parse_cookie_date(value)?;
+    Some(Duration::from_secs(date.saturating_sub(now).max(0) as u64))
+}
+
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64)
+}
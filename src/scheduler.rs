@@ -0,0 +1,152 @@
+//! Bounds upstream requests in flight (`limits.max_in_flight`), handing
+//! free slots to waiting requests by `config.priority`, and in arrival order
+//! within a priority.
+
+use crate::cronet_pb::RequestPriority;
+use std::collections::BinaryHeap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+
+/// Order of `priority` among the others, lowest first.
+pub fn rank(priority: RequestPriority) -> u8 {
+    match priority {
+        RequestPriority::PriorityIdle => 0,
+        RequestPriority::PriorityLowest => 1,
+        RequestPriority::PriorityLow => 2,
+        RequestPriority::PriorityDefault | RequestPriority::PriorityMedium => 3,
+        RequestPriority::PriorityHighest => 4,
+    }
+}
+
+/// A fixed number of slots, handed out by priority.
+pub struct Scheduler {
+    state: Mutex<State>,
+}
+
+struct State {
+    available: usize,
+    waiting: BinaryHeap<Waiter>,
+    // Arrival order of waiters
+    next_seq: u64,
+}
+
+struct Waiter {
+    rank: u8,
+    seq: u64,
+    ready: oneshot::Sender<()>,
+}
+
+impl Ord for Waiter {
+    // Higher priority first, then earlier arrival
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.rank
+            .cmp(&other.rank)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+impl PartialOrd for Waiter {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Waiter {
+    fn eq(&self, other: &Self) -> bool {
+        self.seq == other.seq
+    }
+}
+
+impl Eq for Waiter {}
+
+/// A slot, given back when dropped.
+pub struct Permit {
+    scheduler: Arc<Scheduler>,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        self.scheduler.release();
+    }
+}
+
+// A request waiting for a slot. If it goes away after a slot was handed to
+// it, the slot is passed on.
+struct Pending {
+    ready: oneshot::Receiver<()>,
+    scheduler: Arc<Scheduler>,
+    granted: bool,
+}
+
+impl Drop for Pending {
+    fn drop(&mut self) {
+        if !self.granted {
+            self.ready.close();
+            if self.ready.try_recv().is_ok() {
+                self.scheduler.release();
+            }
+        }
+    }
+}
+
+impl Scheduler {
+    pub fn new(slots: usize) -> Arc<Self> {
+        Arc::new(Scheduler {
+            state: Mutex::new(State {
+                available: slots,
+                waiting: BinaryHeap::new(),
+                next_seq: 0,
+            }),
+        })
+    }
+
+    /// Waits for a free slot. Requests of a higher priority that arrive
+    /// meanwhile get theirs first.
+    pub async fn acquire(self: &Arc<Self>, priority: RequestPriority) -> Permit {
+        let ready = {
+            let mut state = self.state.lock().unwrap();
+            if state.available > 0 {
+                state.available -= 1;
+                return Permit {
+                    scheduler: self.clone(),
+                };
+            }
+            let (tx, rx) = oneshot::channel();
+            let seq = state.next_seq;
+            state.next_seq += 1;
+            state.waiting.push(Waiter {
+                rank: rank(priority),
+                seq,
+                ready: tx,
+            });
+            rx
+        };
+        let mut pending = Pending {
+            ready,
+            scheduler: self.clone(),
+            granted: false,
+        };
+        // The sender is only dropped after sending
+        let _ = (&mut pending.ready).await;
+        pending.granted = true;
+        Permit {
+            scheduler: self.clone(),
+        }
+    }
+
+    /// Requests waiting for a slot.
+    pub fn waiting(&self) -> usize {
+        self.state.lock().unwrap().waiting.len()
+    }
+
+    // Hands a freed slot to the first waiter still there
+    fn release(&self) {
+        let mut state = self.state.lock().unwrap();
+        while let Some(waiter) = state.waiting.pop() {
+            if waiter.ready.send(()).is_ok() {
+                return;
+            }
+        }
+        state.available += 1;
+    }
+}
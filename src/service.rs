@@ -1,91 +1,1819 @@
-use crate::cronet::CronetEngine;
-use crate::cronet_pb::{ExecuteRequest, ExecuteResponse};
+use crate::body_encoding::{self, EncodedJson};
+use crate::config::{Limits, NetLogSettings};
+use crate::connect;
+use crate::cookies::{Cookie, CookieJars};
+use crate::cronet::{
+    CronetEngine, CronetRequest, RedirectHop, RedirectPolicy, RequestResult, RequestTiming,
+    UploadBody,
+};
+use crate::cronet_pb::{
+    AttemptError, BodyEncoding, ErrorKind, ExecuteBatchRequest, ExecuteRequest, ExecuteResponse,
+    ExecuteStreamResponse, ExecutionConfig, Header, HeaderValues, TargetRequest, TargetResponse,
+    Timing,
+};
+use crate::errors::{self, RequestError};
+use crate::netlog::{NetLogFile, NetLogs, RequestCapture};
+use crate::retry::RetryPolicy;
+use crate::scheduler::{Permit, Scheduler};
+use crate::sessions::{Session, SessionInfo, SessionOptions, Sessions};
 use axum::{
-    extract::{Json, State},
-    response::IntoResponse,
+    body::{Body, Bytes},
+    extract::{Json, Path, Query, State},
+    http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
 };
+use futures::{future, stream, Stream, StreamExt};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::convert::Infallible;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncReadExt;
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::Instant;
+
+/// Upload chunks buffered between the incoming body and Cronet.
+const UPLOAD_CHANNEL_CAPACITY: usize = 8;
+
+/// Requests of a batch in flight at once when `max_concurrency` is unset.
+const DEFAULT_BATCH_CONCURRENCY: usize = 8;
+
+/// Upper bound for `max_concurrency`.
+const MAX_BATCH_CONCURRENCY: usize = 64;
 
 // Service State
 #[derive(Clone)]
 pub struct AppState {
     pub engine: Arc<CronetEngine>,
+    // Engines selected with `config.profile`, by name
+    profiles: Arc<HashMap<String, Profile>>,
+    // Bounds upstream requests in flight when `limits.max_in_flight` is set
+    in_flight: Option<Arc<Scheduler>>,
+    max_batch_size: usize,
+    // Cookie jars by session id or `config.cookie_jar`
+    cookies: Arc<CookieJars>,
+    // Sessions created through the API, by id
+    sessions: Arc<Sessions>,
+    netlogs: Arc<NetLogs>,
+}
+
+/// A named engine and the default headers of the requests using it.
+pub struct Profile {
+    pub engine: Arc<CronetEngine>,
+    pub headers: Vec<(String, String)>,
+}
+
+// Adds the default headers the request does not set itself
+fn apply_default_headers(target: &mut TargetRequest, headers: &[(String, String)]) {
+    for (name, value) in headers {
+        if !target
+            .headers
+            .keys()
+            .any(|existing| existing.eq_ignore_ascii_case(name))
+        {
+            target.headers.insert(
+                name.clone(),
+                HeaderValues {
+                    values: vec![value.clone()],
+                },
+            );
+        }
+    }
+}
+
+/// Engine and default headers a request runs with: those of its session,
+/// of its profile, or the default engine.
+struct Route<'a> {
+    default: &'a CronetEngine,
+    profile: Option<&'a Profile>,
+    session: Option<Arc<Session>>,
+}
+
+impl Route<'_> {
+    fn engine(&self) -> &CronetEngine {
+        match (&self.session, self.profile) {
+            (Some(session), _) => &session.engine,
+            (None, Some(profile)) => &profile.engine,
+            (None, None) => self.default,
+        }
+    }
+
+    fn has_headers(&self) -> bool {
+        self.session
+            .as_ref()
+            .is_some_and(|session| !session.headers.is_empty())
+            || self
+                .profile
+                .is_some_and(|profile| !profile.headers.is_empty())
+    }
+
+    // Session headers win over profile headers
+    fn apply_headers(&self, target: &mut TargetRequest) {
+        if let Some(session) = &self.session {
+            apply_default_headers(target, &session.headers);
+        }
+        if let Some(profile) = self.profile {
+            apply_default_headers(target, &profile.headers);
+        }
+    }
+}
+
+impl AppState {
+    pub fn new(engine: Arc<CronetEngine>, limits: &Limits) -> Self {
+        AppState {
+            engine,
+            profiles: Arc::new(HashMap::new()),
+            in_flight: (limits.max_in_flight > 0).then(|| Scheduler::new(limits.max_in_flight)),
+            max_batch_size: limits.max_batch_size,
+            cookies: Arc::new(CookieJars::new(
+                limits.max_cookie_jars,
+                Duration::from_secs(limits.cookie_jar_ttl_secs),
+            )),
+            sessions: Arc::new(Sessions::new(limits)),
+            netlogs: Arc::new(NetLogs::new(&NetLogSettings::default())),
+        }
+    }
+
+    pub fn with_netlog(mut self, settings: &NetLogSettings) -> Self {
+        self.netlogs = Arc::new(NetLogs::new(settings));
+        self
+    }
+
+    pub fn with_profiles(mut self, profiles: HashMap<String, Profile>) -> Self {
+        self.profiles = Arc::new(profiles);
+        self
+    }
+
+    /// The default engine followed by the profile engines.
+    pub fn engines(&self) -> impl Iterator<Item = &Arc<CronetEngine>> {
+        std::iter::once(&self.engine).chain(self.profiles.values().map(|profile| &profile.engine))
+    }
+
+    /// The engine of profile `name`, "default" or "" for the default engine,
+    /// with its name.
+    fn named_engine(&self, name: &str) -> Result<(&str, &Arc<CronetEngine>), String> {
+        let name = if name == "default" { "" } else { name };
+        self.named_engines()
+            .into_iter()
+            .find(|(profile, _)| *profile == name)
+            .ok_or_else(|| format!("Unknown profile: {}", name))
+    }
+
+    /// Engines by profile name, the default engine first under "".
+    fn named_engines(&self) -> Vec<(&str, &Arc<CronetEngine>)> {
+        let mut profiles: Vec<_> = self
+            .profiles
+            .iter()
+            .map(|(name, profile)| (name.as_str(), &profile.engine))
+            .collect();
+        profiles.sort_by_key(|(name, _)| *name);
+        std::iter::once(("", &self.engine))
+            .chain(profiles)
+            .collect()
+    }
+
+    /// The profile called `name`, `None` for the default engine.
+    fn profile(&self, name: &str) -> Result<Option<&Profile>, String> {
+        if name.is_empty() {
+            return Ok(None);
+        }
+        match self.profiles.get(name) {
+            Some(profile) => Ok(Some(profile)),
+            None => Err(format!("Unknown profile: {}", name)),
+        }
+    }
+
+    /// Where a request runs: the session named by `config.session_id`, else
+    /// the profile named by `config.profile`.
+    fn route(&self, config: &ExecutionConfig) -> Result<Route<'_>, RequestError> {
+        let session = match config.session_id.as_str() {
+            "" => None,
+            _ if !config.cookie_jar.is_empty() => {
+                return Err(RequestError::invalid(
+                    "Invalid config: set session_id or cookie_jar, not both",
+                ))
+            }
+            id => Some(
+                self.sessions
+                    .get(id)
+                    .ok_or_else(|| RequestError::invalid(format!("Unknown session: {}", id)))?,
+            ),
+        };
+        let profile_name = match &session {
+            Some(session) => {
+                if !config.profile.is_empty() && config.profile != session.profile {
+                    return Err(RequestError::invalid(format!(
+                        "Profile mismatch: session {} uses profile {:?}",
+                        session.id, session.profile
+                    )));
+                }
+                session.profile.as_str()
+            }
+            None => config.profile.as_str(),
+        };
+        Ok(Route {
+            default: &self.engine,
+            profile: self.profile(profile_name).map_err(RequestError::invalid)?,
+            session,
+        })
+    }
+
+    /// Ends sessions unused for longer than their TTL, along with their
+    /// cookie jars, drops other idle cookie jars, and shuts down idle proxy
+    /// engines of the remaining sessions. Returns how many sessions expired.
+    pub fn evict_expired_sessions(&self) -> usize {
+        let expired = self.sessions.evict_expired();
+        for session in &expired {
+            self.cookies.remove(&session.id);
+        }
+        self.cookies.evict_idle();
+        for session in self.sessions.list() {
+            session.engine.evict_idle_proxy_engines();
+        }
+        expired.len()
+    }
+
+    /// Waits for a free upstream request slot, if the number is limited.
+    /// Slots go to waiting requests by `config.priority`.
+    async fn in_flight_permit(&self, config: &ExecutionConfig) -> Option<Permit> {
+        let in_flight = self.in_flight.as_ref()?;
+        Some(in_flight.acquire(config.priority()).await)
+    }
+
+    pub(crate) fn check_batch_size(&self, batch: &ExecuteBatchRequest) -> Result<(), RequestError> {
+        if batch.requests.len() > self.max_batch_size {
+            return Err(RequestError::invalid(format!(
+                "Batch too large: {} requests, limit is {}",
+                batch.requests.len(),
+                self.max_batch_size
+            )));
+        }
+        Ok(())
+    }
 }
 
 // Handlers
 pub async fn execute_request(
     State(state): State<AppState>,
-    Json(request): Json<ExecuteRequest>,
+    EncodedJson(request, encoding): EncodedJson<ExecuteRequest>,
 ) -> impl IntoResponse {
+    EncodedJson(execute(&state, request).await, encoding)
+}
+
+/// Connect unary `Execute` over POST, with a JSON or binary protobuf body.
+/// Also honors the `Connect-Timeout-Ms` and `grpc-timeout` deadline headers.
+pub async fn execute_connect(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let codec = match connect::Codec::from_unary_headers(&headers) {
+        Some(codec) => codec,
+        None => {
+            return (
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                [("accept-post", "application/json, application/proto")],
+            )
+                .into_response()
+        }
+    };
+    connect_unary(&state, &headers, codec, &body).await
+}
+
+/// Connect unary `Execute` over GET, with the request in the query string.
+pub async fn execute_connect_get(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+) -> Response {
+    match connect::read_get_message(&params) {
+        Ok((codec, message)) => connect_unary(&state, &headers, codec, &message).await,
+        Err(e) => connect_invalid_argument(&e),
+    }
+}
+
+async fn connect_unary(
+    state: &AppState,
+    headers: &HeaderMap,
+    codec: connect::Codec,
+    message: &[u8],
+) -> Response {
+    let (mut request, encoding): (ExecuteRequest, _) = match codec.decode(message) {
+        Ok(decoded) => decoded,
+        Err(e) => return connect_invalid_argument(&e),
+    };
+    apply_header_deadline(headers, &mut request);
+
+    let response = execute(state, request).await;
+    if !response.success {
+        let error = response.error.unwrap_or_default();
+        return connect::error_response(&response.error_message, &error);
+    }
+    (
+        [(header::CONTENT_TYPE, codec.unary_content_type())],
+        codec.encode(&response, encoding),
+    )
+        .into_response()
+}
+
+// Unary response for a request that cannot be decoded
+fn connect_invalid_argument(message: &str) -> Response {
+    let error = RequestError::invalid(message);
+    connect::error_response(message, &errors::describe(&error))
+}
+
+#[derive(serde::Deserialize)]
+pub struct UploadParams {
+    /// The `ExecuteRequest` as JSON.
+    pub request: Option<String>,
+}
+
+/// Streaming upload entry point. The `ExecuteRequest` travels as JSON in the
+/// `request` query parameter, which is not subject to the header size limits
+/// of proxies, and the HTTP request body is uploaded to the target as it
+/// arrives, chunked unless `Content-Length` is set.
+pub async fn execute_upload(
+    State(state): State<AppState>,
+    Query(params): Query<UploadParams>,
+    headers: HeaderMap,
+    body: Body,
+) -> Response {
+    let request = params
+        .request
+        .ok_or_else(|| "Missing request query parameter".to_string())
+        .and_then(|value| {
+            body_encoding::from_json_slice::<ExecuteRequest>(value.as_bytes())
+                .map_err(|e| format!("Invalid request query parameter: {}", e))
+        });
+    let (request, encoding) = match request {
+        Ok(decoded) => decoded,
+        Err(error_message) => {
+            let response = failed_response(String::new(), RequestError::invalid(error_message));
+            return (
+                StatusCode::BAD_REQUEST,
+                EncodedJson(response, BodyEncoding::Base64),
+            )
+                .into_response();
+        }
+    };
+
+    let length = headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok()?.parse::<u64>().ok());
+    let (tx, chunks) = mpsc::channel(UPLOAD_CHANNEL_CAPACITY);
+    tokio::spawn(async move {
+        let mut body = body.into_data_stream();
+        while let Some(chunk) = body.next().await {
+            let chunk = chunk.map(|data| data.to_vec()).map_err(|e| e.to_string());
+            if tx.send(chunk).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let upload = UploadBody::Stream { chunks, length };
+    EncodedJson(
+        execute_with_upload(&state, request, Some(upload)).await,
+        encoding,
+    )
+    .into_response()
+}
+
+/// Streaming REST entry point. Responds with the target's status and headers,
+/// then forwards the body as it arrives. Failures before the response starts
+/// are reported as a JSON `ExecuteResponse` with a 4xx/5xx status; failures
+/// mid-body abort the connection.
+pub async fn execute_stream(
+    State(state): State<AppState>,
+    EncodedJson(request, encoding): EncodedJson<ExecuteRequest>,
+) -> Response {
+    let config = request.config.clone().unwrap_or_default();
+    let started = match request.target.as_ref() {
+        Some(target) => start_stream(&state, target, &config).await,
+        None => Err(RequestError::invalid("Missing target configuration")),
+    };
+    let (head, stream) = match started {
+        Ok(started) => started,
+        Err(error) => {
+            let status = match connect::error_code(error.kind) {
+                "invalid_argument" => StatusCode::BAD_REQUEST,
+                "not_found" => StatusCode::NOT_FOUND,
+                "deadline_exceeded" => StatusCode::GATEWAY_TIMEOUT,
+                _ => StatusCode::BAD_GATEWAY,
+            };
+            let response = failed_response(request.request_id, error);
+            return (status, EncodedJson(response, encoding)).into_response();
+        }
+    };
+
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() =
+        StatusCode::from_u16(head.status_code as u16).unwrap_or(StatusCode::BAD_GATEWAY);
+    for (name, value) in &head.headers {
+        // Cronet has already decoded and de-chunked the body
+        let name = match HeaderName::from_bytes(name.as_bytes()) {
+            Ok(name) => name,
+            Err(_) => continue,
+        };
+        if name == header::CONTENT_LENGTH
+            || name == header::CONTENT_ENCODING
+            || name == header::TRANSFER_ENCODING
+            || name == header::CONNECTION
+        {
+            continue;
+        }
+        if let Ok(value) = HeaderValue::from_str(value) {
+            response.headers_mut().append(name, value);
+        }
+    }
+
+    let body = stream::unfold(Some(stream), |stream| async move {
+        let mut stream = stream?;
+        match stream.next_chunk().await? {
+            Ok(chunk) => Some((Ok(Bytes::from(chunk)), Some(stream))),
+            Err(e) => Some((Err(std::io::Error::other(e)), None)),
+        }
+    });
+    *response.body_mut() = Body::from_stream(body);
+    response
+}
+
+/// Connect server-streaming `ExecuteStream`. The first message carries the
+/// response head, the following ones body chunks; errors end the stream.
+pub async fn execute_stream_connect(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let codec = match connect::Codec::from_headers(&headers) {
+        Some(codec) => codec,
+        None => return StatusCode::UNSUPPORTED_MEDIA_TYPE.into_response(),
+    };
+    let (mut request, encoding): (ExecuteRequest, _) =
+        match connect::read_envelope(&body).and_then(|message| codec.decode(message)) {
+            Ok(decoded) => decoded,
+            Err(e) => {
+                return connect_stream_response(codec, vec![error_frame(&RequestError::invalid(e))])
+            }
+        };
+    apply_header_deadline(&headers, &mut request);
+
+    let config = request.config.clone().unwrap_or_default();
+    let started = match request.target.as_ref() {
+        Some(target) => start_stream(&state, target, &config).await,
+        None => Err(RequestError::invalid("Missing target configuration")),
+    };
+    let (head, stream) = match started {
+        Ok(started) => started,
+        Err(e) => return connect_stream_response(codec, vec![error_frame(&e)]),
+    };
+
+    let request_id = request.request_id;
+    let first = ExecuteStreamResponse {
+        request_id: request_id.clone(),
+        head: Some(target_response(head)),
+        chunk: Vec::new(),
+    };
+    let first = connect::envelope(0, &codec.encode(&first, encoding));
+
+    let rest = stream::unfold(Some(stream), move |stream| {
+        let request_id = request_id.clone();
+        async move {
+            let mut stream = stream?;
+            let frame = match stream.next_chunk().await {
+                Some(Ok(chunk)) => {
+                    let message = ExecuteStreamResponse {
+                        request_id,
+                        head: None,
+                        chunk,
+                    };
+                    let frame = connect::envelope(0, &codec.encode(&message, encoding));
+                    return Some((frame, Some(stream)));
+                }
+                Some(Err(e)) => error_frame(&e),
+                None => connect::end_stream(None),
+            };
+            Some((frame, None))
+        }
+    });
+    let frames = stream::iter([first]).chain(rest).map(Ok::<_, Infallible>);
+    connect_stream_body(codec, Body::from_stream(frames))
+}
+
+/// Batch REST entry point. Responds with NDJSON: one `ExecuteResponse` per
+/// line, in completion order.
+pub async fn execute_batch_request(State(state): State<AppState>, body: Bytes) -> Response {
+    let (batch, encodings) = match body_encoding::batch_from_json_slice(&body) {
+        Ok(decoded) => decoded,
+        Err(e) => {
+            return (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!("Failed to deserialize the JSON body: {}", e),
+            )
+                .into_response()
+        }
+    };
+    if let Err(e) = state.check_batch_size(&batch) {
+        return (StatusCode::PAYLOAD_TOO_LARGE, e.message).into_response();
+    }
+
+    let lines = execute_batch(&state, batch).map(move |(index, response)| {
+        let encoding = encodings.get(index).copied().unwrap_or_default();
+        let mut line = body_encoding::to_json_vec(&response, encoding);
+        line.push(b'\n');
+        Ok::<_, Infallible>(Bytes::from(line))
+    });
+    (
+        [(header::CONTENT_TYPE, "application/x-ndjson")],
+        Body::from_stream(lines),
+    )
+        .into_response()
+}
+
+/// Connect server-streaming `ExecuteBatch`: one message per response, in
+/// completion order. Deadline headers apply to each request.
+pub async fn execute_batch_connect(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let codec = match connect::Codec::from_headers(&headers) {
+        Some(codec) => codec,
+        None => return StatusCode::UNSUPPORTED_MEDIA_TYPE.into_response(),
+    };
+    let (mut batch, encodings) =
+        match connect::read_envelope(&body).and_then(|message| decode_batch(codec, message)) {
+            Ok(decoded) => decoded,
+            Err(e) => {
+                return connect_stream_response(codec, vec![error_frame(&RequestError::invalid(e))])
+            }
+        };
+    if let Err(e) = state.check_batch_size(&batch) {
+        return connect_stream_response(codec, vec![error_frame(&e)]);
+    }
+    for request in &mut batch.requests {
+        apply_header_deadline(&headers, request);
+    }
+
+    let frames = execute_batch(&state, batch)
+        .map(move |(index, response)| {
+            let encoding = encodings.get(index).copied().unwrap_or_default();
+            connect::envelope(0, &codec.encode(&response, encoding))
+        })
+        .chain(stream::once(async { connect::end_stream(None) }))
+        .map(Ok::<_, Infallible>);
+    connect_stream_body(codec, Body::from_stream(frames))
+}
+
+fn decode_batch(
+    codec: connect::Codec,
+    data: &[u8],
+) -> Result<(ExecuteBatchRequest, Vec<BodyEncoding>), String> {
+    match codec {
+        connect::Codec::Json => {
+            body_encoding::batch_from_json_slice(data).map_err(|e| e.to_string())
+        }
+        connect::Codec::Proto => codec.decode(data).map(|(batch, _)| (batch, Vec::new())),
+    }
+}
+
+// Connect streaming responses are always 200; errors travel in the stream.
+fn connect_stream_response(codec: connect::Codec, frames: Vec<Bytes>) -> Response {
+    let frames = stream::iter(frames.into_iter().map(Ok::<_, Infallible>));
+    connect_stream_body(codec, Body::from_stream(frames))
+}
+
+fn connect_stream_body(codec: connect::Codec, body: Body) -> Response {
+    ([(header::CONTENT_TYPE, codec.content_type())], body).into_response()
+}
+
+fn error_frame(error: &RequestError) -> Bytes {
+    connect::end_stream(Some((&error.message, &errors::describe(error))))
+}
+
+/// Lowers `config.timeout_ms` to the deadline sent in Connect/gRPC headers.
+pub(crate) fn apply_header_deadline(headers: &HeaderMap, request: &mut ExecuteRequest) {
+    if let Some(deadline_ms) = deadline_from_headers(headers) {
+        let config = request.config.get_or_insert_with(Default::default);
+        // The tighter of the two deadlines wins
+        if config.timeout_ms == 0 || deadline_ms < config.timeout_ms {
+            config.timeout_ms = deadline_ms;
+        }
+    }
+}
+
+pub async fn execute(state: &AppState, request: ExecuteRequest) -> ExecuteResponse {
+    execute_with_upload(state, request, None).await
+}
+
+/// Runs a batch on the shared engine and yields each response, with its index
+/// in the batch, as it completes. With `fail_fast` the stream ends after the
+/// first failure; dropping the stream cancels the requests still in flight.
+pub(crate) fn execute_batch(
+    state: &AppState,
+    batch: ExecuteBatchRequest,
+) -> impl Stream<Item = (usize, ExecuteResponse)> + Send + 'static {
+    let concurrency = match batch.max_concurrency as usize {
+        0 => DEFAULT_BATCH_CONCURRENCY,
+        n => n.min(MAX_BATCH_CONCURRENCY),
+    };
+    tracing::debug!(
+        "execute_batch: {} requests, concurrency {}",
+        batch.requests.len(),
+        concurrency
+    );
+
+    let state = state.clone();
+    let fail_fast = batch.fail_fast;
+    stream::iter(batch.requests.into_iter().enumerate())
+        .map(move |(index, request)| {
+            let state = state.clone();
+            async move { (index, execute(&state, request).await) }
+        })
+        .buffer_unordered(concurrency)
+        .scan(false, move |failed, (index, response)| {
+            if *failed {
+                return future::ready(None);
+            }
+            *failed = fail_fast && !response.success;
+            future::ready(Some((index, response)))
+        })
+}
+
+/// Runs a request to completion. `upload`, when given, replaces `target.body`.
+pub async fn execute_with_upload(
+    state: &AppState,
+    request: ExecuteRequest,
+    upload: Option<UploadBody>,
+) -> ExecuteResponse {
     eprintln!(
         "[DEBUG] execute_request handler entered. Request ID: {}",
         request.request_id
     );
     // Validate Target
-    let target = match request.target {
+    let mut target = match request.target {
         Some(t) => t,
         None => {
-            return Json(ExecuteResponse {
-                request_id: request.request_id,
-                success: false,
-                error_message: "Missing target configuration".to_string(),
-                ..Default::default()
-            })
+            return failed_response(
+                request.request_id,
+                RequestError::invalid("Missing target configuration"),
+            )
         }
     };
 
-    // Start Timer
-    let start_time = std::time::Instant::now();
-
-    // Execute Request via Cronet
-    // Note: We currently only support URL and Method. Headers/Body support pending.
     let config_default = crate::cronet_pb::ExecutionConfig::default();
     let config = request.config.as_ref().unwrap_or(&config_default);
+    if let Err(error) = crate::cache::check(config.cache, &target.method) {
+        return failed_response(request.request_id, error);
+    }
+    let route = match state.route(config) {
+        Ok(route) => route,
+        Err(error) => return failed_response(request.request_id, error),
+    };
+    route.apply_headers(&mut target);
+    let capture = if config.capture_netlog {
+        match state
+            .netlogs
+            .capture_request(route.engine(), config.proxy.as_ref())
+            .await
+        {
+            Ok(capture) => Some(capture),
+            Err(error_message) => {
+                return failed_response(
+                    request.request_id,
+                    RequestError::new(ErrorKind::Internal, error_message),
+                )
+            }
+        }
+    } else {
+        None
+    };
+    let engine = capture
+        .as_ref()
+        .map_or(route.engine(), RequestCapture::engine);
+    // The capture engine has the proxy built in
+    let capture_config;
+    let config = match &capture {
+        Some(_) if config.proxy.is_some() => {
+            capture_config = ExecutionConfig {
+                proxy: None,
+                ..config.clone()
+            };
+            &capture_config
+        }
+        _ => config,
+    };
 
-    let (request_handle, rx) = state.engine.start_request(&target, config);
+    // Wait for a slot before starting the clock
+    let permit = state.in_flight_permit(config).await;
 
-    // Wait for result
-    let execution_result = rx.await;
-    let duration_ms = start_time.elapsed().as_millis() as i64;
+    // Start Timer
+    let start_time = Instant::now();
+    let deadline = (config.timeout_ms > 0)
+        .then(|| start_time + Duration::from_millis(config.timeout_ms as u64));
 
-    // Drop the request handle after we are done
-    drop(request_handle);
+    // Execute Request via Cronet
+    let text_body = config.body_encoding() == BodyEncoding::Utf8;
+
+    // Hand the body over instead of copying it
+    let upload = upload.or_else(|| {
+        (!target.body.is_empty()).then(|| UploadBody::Bytes(std::mem::take(&mut target.body)))
+    });
+    let attempts = run_attempts(state, engine, target, config, upload, deadline, permit).await;
+    let attempt = attempts.last;
+    let duration_ms = start_time.elapsed().as_millis() as i64;
+    let netlog = match capture {
+        Some(capture) => capture.finish().await.unwrap_or_else(|e| {
+            tracing::warn!("NetLog capture failed: {}", e);
+            String::new()
+        }),
+        None => String::new(),
+    };
 
-    match execution_result {
-        Ok(Ok(res)) => {
+    let timing = attempt.timing;
+    match attempt.result {
+        Ok(mut res) => {
             // Success
-            Json(ExecuteResponse {
+            if text_body {
+                let content_type = res
+                    .headers
+                    .iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+                    .map(|(_, value)| value.clone());
+                res.body = body_encoding::to_utf8(res.body, content_type.as_deref());
+            }
+            ExecuteResponse {
                 request_id: request.request_id,
                 success: true,
                 error_message: "".to_string(),
                 duration_ms,
-                response: Some(crate::cronet_pb::TargetResponse {
-                    status_code: res.status_code,
-                    headers: Default::default(), // TODO: Maps headers
-                    body: res.body,
-                }),
-            })
+                timing,
+                response: Some(target_response(res)),
+                netlog,
+                error: None,
+                attempts: attempts.count,
+                attempt_errors: attempts.failures,
+            }
         }
-        Ok(Err(err_msg)) => {
-            // Cronet Error (Failed/Canceled)
-            Json(ExecuteResponse {
+        Err(error) => {
+            // Cronet Error (Failed/Canceled/Timeout)
+            ExecuteResponse {
                 request_id: request.request_id,
                 success: false,
-                error_message: err_msg,
+                error: Some(errors::describe(&error)),
+                error_message: error.message,
                 duration_ms,
+                timing,
                 response: None,
-            })
+                netlog,
+                attempts: attempts.count,
+                attempt_errors: attempts.failures,
+            }
         }
-        Err(_) => {
-            // RecvError (Internal Panic)
-            Json(ExecuteResponse {
-                request_id: request.request_id,
-                success: false,
-                error_message: "Internal Executor Error".to_string(),
-                duration_ms,
-                response: None,
+    }
+}
+
+/// Response for a request that failed before reaching Cronet.
+fn failed_response(request_id: String, error: RequestError) -> ExecuteResponse {
+    ExecuteResponse {
+        request_id,
+        success: false,
+        error: Some(errors::describe(&error)),
+        error_message: error.message,
+        ..Default::default()
+    }
+}
+
+/// Outcome of one Cronet request.
+struct Attempt {
+    result: Result<RequestResult, RequestError>,
+    timing: Option<Timing>,
+}
+
+/// Every attempt of a request.
+struct Attempts {
+    last: Attempt,
+    count: u32,
+    failures: Vec<AttemptError>,
+}
+
+/// Runs a request, and runs it again as its retry policy allows while
+/// `deadline` leaves time for the next attempt. `permit` is the upstream
+/// request slot of the first attempt; the slot is given up during backoff
+/// and waited for again before each retry.
+async fn run_attempts(
+    state: &AppState,
+    engine: &CronetEngine,
+    target: TargetRequest,
+    config: &ExecutionConfig,
+    mut upload: Option<UploadBody>,
+    deadline: Option<Instant>,
+    mut permit: Option<Permit>,
+) -> Attempts {
+    let retry = RetryPolicy::from_config(config, &target.method);
+    let max_attempts = retry.as_ref().map_or(1, RetryPolicy::max_attempts);
+    let mut failures = Vec::new();
+    let mut count = 0;
+    loop {
+        count += 1;
+        let start = Instant::now();
+        // Keep a copy of the body while another attempt may need it
+        let body = match upload
+            .as_ref()
+            .filter(|_| count < max_attempts)
+            .and_then(UploadBody::try_clone)
+        {
+            Some(body) => Some(body),
+            None => upload.take(),
+        };
+        let resendable = body.is_none() || upload.is_some();
+        let attempt = if cookie_jar(config).is_none() {
+            run_request(engine, &target, config, body, deadline).await
+        } else {
+            let mut target = target.clone();
+            let mut session = CookieSession::new(state, config, &mut target);
+            session.run(engine, target, config, body, deadline).await
+        };
+
+        let (mut failure, retryable, headers) = match (&attempt.result, &retry) {
+            (Ok(res), Some(retry)) if retry.retries_status(res.status_code) => (
+                AttemptError {
+                    status_code: res.status_code,
+                    error_message: format!("HTTP {}", res.status_code),
+                    ..Default::default()
+                },
+                true,
+                res.headers.as_slice(),
+            ),
+            (Ok(_), _) => {
+                return Attempts {
+                    last: attempt,
+                    count,
+                    failures,
+                }
+            }
+            (Err(error), _) => (
+                AttemptError {
+                    error_message: error.message.clone(),
+                    error: Some(errors::describe(error)),
+                    ..Default::default()
+                },
+                // Failures raised by the service itself are never retried
+                retry
+                    .as_ref()
+                    .zip(error.cronet.as_ref())
+                    .is_some_and(|(retry, error)| retry.retries_error(error)),
+                &[][..],
+            ),
+        };
+        let delay = retry
+            .as_ref()
+            .filter(|_| retryable && resendable && count < max_attempts)
+            .and_then(|retry| retry.delay(count, headers))
+            .filter(|delay| deadline.is_none_or(|deadline| Instant::now() + *delay < deadline));
+        failure.attempt = count;
+        failure.duration_ms = start.elapsed().as_millis() as i64;
+        failure.backoff_ms = delay.map_or(0, |delay| delay.as_millis() as i64);
+        failures.push(failure);
+        let Some(delay) = delay else {
+            return Attempts {
+                last: attempt,
+                count,
+                failures,
+            };
+        };
+        tracing::debug!(
+            "Retrying {} in {} ms (attempt {} of {})",
+            target.url,
+            delay.as_millis(),
+            count + 1,
+            max_attempts
+        );
+        crate::metrics::REQUEST_RETRIES.inc();
+        // Other requests may use the slot while this one backs off
+        drop(permit.take());
+        tokio::time::sleep(delay).await;
+        permit = state.in_flight_permit(config).await;
+    }
+}
+
+/// Runs one Cronet request to completion, cancelling it if `deadline`
+/// passes first.
+async fn run_request(
+    engine: &CronetEngine,
+    target: &TargetRequest,
+    config: &ExecutionConfig,
+    upload: Option<UploadBody>,
+    deadline: Option<Instant>,
+) -> Attempt {
+    let (request_handle, mut rx) = match upload {
+        Some(upload) => {
+            engine
+                .start_request_with_upload(target, config, upload)
+                .await
+        }
+        None => engine.start_request(target, config).await,
+    };
+
+    // Wait for result, cancelling the Cronet request if the deadline expires
+    let result = match deadline {
+        Some(deadline) => match tokio::time::timeout_at(deadline, &mut rx).await {
+            Ok(res) => res,
+            Err(_) => {
+                request_handle.cancel_timed_out();
+                // on_canceled (or a racing terminal callback) still completes the
+                // channel; wait for it so the handle is only dropped afterwards.
+                rx.await
+            }
+        },
+        None => rx.await,
+    };
+    let timing = request_handle.timing().map(timing_to_proto);
+
+    // Drop the request handle after we are done
+    drop(request_handle);
+
+    // RecvError means the callbacks went away without reporting (internal panic)
+    let result = result.unwrap_or_else(|_| Err(internal_executor_error()));
+    Attempt { result, timing }
+}
+
+/// The cookie jar a request uses: that of its session, else the one named
+/// by `config.cookie_jar`.
+fn cookie_jar(config: &ExecutionConfig) -> Option<&str> {
+    [&config.session_id, &config.cookie_jar]
+        .into_iter()
+        .find(|id| !id.is_empty())
+        .map(String::as_str)
+}
+
+/// Cookie handling for a request with a cookie jar.
+///
+/// Cronet follows redirects with the headers of the original request, so a
+/// cookie set by a redirect hop would never reach the next hop. Requests with
+/// a jar follow redirects here instead, one Cronet request per hop, with
+/// the same policy checks Cronet applies.
+struct CookieSession<'a> {
+    jars: &'a CookieJars,
+    jar: String,
+    policy: RedirectPolicy,
+    // Cookie header sent by the caller, kept in front of the jar's cookies.
+    // Dropped once a redirect leaves the origin of the request.
+    caller_cookies: Vec<String>,
+    hops: Vec<RedirectHop>,
+}
+
+impl<'a> CookieSession<'a> {
+    /// For a request with a cookie jar. Takes the caller's `Cookie`
+    /// headers out of `target`.
+    fn new(state: &'a AppState, config: &ExecutionConfig, target: &mut TargetRequest) -> Self {
+        let mut caller_cookies = Vec::new();
+        target.headers.retain(|name, values| {
+            let is_cookie = name.eq_ignore_ascii_case("cookie");
+            if is_cookie {
+                caller_cookies.append(&mut values.values);
+            }
+            !is_cookie
+        });
+        CookieSession {
+            jars: &state.cookies,
+            jar: cookie_jar(config).unwrap_or_default().to_string(),
+            policy: RedirectPolicy::from_config(config),
+            caller_cookies,
+            hops: Vec::new(),
+        }
+    }
+
+    /// The config for each hop: Cronet hands redirects back instead of
+    /// following them.
+    fn hop_config(config: &ExecutionConfig) -> ExecutionConfig {
+        ExecutionConfig {
+            follow_redirects: Some(false),
+            ..config.clone()
+        }
+    }
+
+    /// Sets the `Cookie` header for the next hop to `target.url`.
+    fn attach(&self, target: &mut TargetRequest) {
+        let jar_cookies = url::Url::parse(&target.url).ok().and_then(|url| {
+            self.jars
+                .with_existing(&self.jar, |jar| jar.header_for(&url))
+                .flatten()
+        });
+        let cookies: Vec<&str> = self
+            .caller_cookies
+            .iter()
+            .map(String::as_str)
+            .chain(jar_cookies.as_deref())
+            .collect();
+        if cookies.is_empty() {
+            target.headers.remove("Cookie");
+        } else {
+            target.headers.insert(
+                "Cookie".to_string(),
+                HeaderValues {
+                    values: vec![cookies.join("; ")],
+                },
+            );
+        }
+    }
+
+    /// Stores the cookies `res` sets and, if it is a redirect to follow,
+    /// points `target` at the next hop. Returns whether to follow, and if so
+    /// whether the request body is sent again.
+    fn follow(
+        &mut self,
+        target: &mut TargetRequest,
+        res: &RequestResult,
+    ) -> Result<Option<bool>, RequestError> {
+        let url = url::Url::parse(&res.url).or_else(|_| url::Url::parse(&target.url));
+        let Ok(url) = url else {
+            return Ok(None);
+        };
+        let set_cookies: Vec<&str> = res
+            .headers
+            .iter()
+            .filter(|(name, _)| name.eq_ignore_ascii_case("set-cookie"))
+            .map(|(_, value)| value.as_str())
+            .collect();
+        if !set_cookies.is_empty() {
+            self.jars.with(&self.jar, |jar| {
+                for value in set_cookies {
+                    jar.store_header(&url, value);
+                }
+            });
+        }
+
+        if !self.policy.follow || !matches!(res.status_code, 301 | 302 | 303 | 307 | 308) {
+            return Ok(None);
+        }
+        let location = res
+            .headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("location"))
+            .and_then(|(_, value)| url.join(value).ok());
+        let Some(location) = location else {
+            return Ok(None);
+        };
+        if let Some(reason) = self
+            .policy
+            .check(url.as_str(), location.as_str(), self.hops.len())
+        {
+            return Err(reason);
+        }
+        // The caller's cookies were meant for the origin it asked for
+        if location.origin() != url.origin() {
+            self.caller_cookies.clear();
+        }
+        self.hops.push(RedirectHop {
+            url: url.to_string(),
+            status_code: res.status_code,
+            headers: res.headers.clone(),
+            location: location.to_string(),
+        });
+
+        // As browsers do: POST becomes GET on 301/302, anything but HEAD
+        // becomes GET on 303, and the body goes with the method change
+        let method = target.method.to_ascii_uppercase();
+        let to_get = match res.status_code {
+            301 | 302 => method == "POST",
+            303 => method != "GET" && method != "HEAD",
+            _ => false,
+        };
+        if to_get {
+            target.method = "GET".to_string();
+            target.body.clear();
+            target.headers.retain(|name, _| {
+                !name.eq_ignore_ascii_case("content-type")
+                    && !name.eq_ignore_ascii_case("content-length")
+            });
+        }
+        target.url = location.to_string();
+        Ok(Some(!to_get))
+    }
+
+    /// Adds the hops followed so far to the final response.
+    fn finish(&mut self, mut res: RequestResult) -> RequestResult {
+        let mut url_chain: Vec<String> = self.hops.iter().map(|hop| hop.url.clone()).collect();
+        url_chain.append(&mut res.url_chain);
+        res.url_chain = url_chain;
+        res.redirects = std::mem::take(&mut self.hops);
+        res
+    }
+
+    async fn run(
+        &mut self,
+        engine: &CronetEngine,
+        mut target: TargetRequest,
+        config: &ExecutionConfig,
+        mut upload: Option<UploadBody>,
+        deadline: Option<Instant>,
+    ) -> Attempt {
+        let config = Self::hop_config(config);
+        loop {
+            self.attach(&mut target);
+            // Keep a copy of the body in case a redirect asks for it again
+            let body = match upload.as_ref().and_then(UploadBody::try_clone) {
+                Some(body) => Some(body),
+                None => upload.take(),
+            };
+            let sent_stream = body.is_some() && upload.is_none();
+            let attempt = run_request(engine, &target, &config, body, deadline).await;
+            let res = match attempt.result {
+                Ok(res) => res,
+                Err(e) => {
+                    return Attempt {
+                        result: Err(e),
+                        ..attempt
+                    }
+                }
+            };
+            let result = match self.follow(&mut target, &res) {
+                Ok(None) => Ok(self.finish(res)),
+                Ok(Some(true)) if sent_stream => Err(RequestError::new(
+                    ErrorKind::Redirect,
+                    format!(
+                        "Redirect refused: streamed request body cannot be resent to {}",
+                        target.url
+                    ),
+                )),
+                Ok(Some(true)) => continue,
+                Ok(Some(false)) => {
+                    upload = None;
+                    continue;
+                }
+                Err(e) => Err(e),
+            };
+            return Attempt { result, ..attempt };
+        }
+    }
+}
+
+pub(crate) fn target_response(res: RequestResult) -> TargetResponse {
+    TargetResponse {
+        status_code: res.status_code,
+        status_text: res.status_text,
+        url: res.url,
+        url_chain: res.url_chain,
+        negotiated_protocol: res.negotiated_protocol,
+        proxy_server: res.proxy_server,
+        was_cached: res.was_cached,
+        received_byte_count: res.received_byte_count,
+        headers: header_map(&res.headers),
+        header_list: res
+            .headers
+            .into_iter()
+            .map(|(name, value)| Header { name, value })
+            .collect(),
+        body: res.body,
+        redirects: res
+            .redirects
+            .into_iter()
+            .map(|hop| crate::cronet_pb::RedirectHop {
+                headers: header_map(&hop.headers),
+                url: hop.url,
+                status_code: hop.status_code,
+                location: hop.location,
             })
+            .collect(),
+    }
+}
+
+/// Starts a streaming request and waits for the response head. The deadline
+/// in `config.timeout_ms` covers the whole request, body included.
+pub(crate) async fn start_stream(
+    state: &AppState,
+    target: &TargetRequest,
+    config: &ExecutionConfig,
+) -> Result<(RequestResult, BodyStream), RequestError> {
+    if config.capture_netlog {
+        return Err(RequestError::invalid(
+            "Unsupported: capture_netlog needs a buffered response",
+        ));
+    }
+    if config
+        .retry
+        .as_ref()
+        .is_some_and(|retry| retry.max_attempts > 1)
+    {
+        return Err(RequestError::invalid(
+            "Unsupported: retry needs a buffered response",
+        ));
+    }
+    crate::cache::check(config.cache, &target.method)?;
+    let route = state.route(config)?;
+    let engine = route.engine();
+    let target = if route.has_headers() {
+        let mut target = target.clone();
+        route.apply_headers(&mut target);
+        Cow::Owned(target)
+    } else {
+        Cow::Borrowed(target)
+    };
+
+    let mut permit = state.in_flight_permit(config).await;
+    let deadline = (config.timeout_ms > 0)
+        .then(|| Instant::now() + Duration::from_millis(config.timeout_ms as u64));
+    if cookie_jar(config).is_none() {
+        return stream_once(engine, &target, config, deadline, permit).await;
+    }
+
+    // Follow redirects hop by hop so that cookies they set are sent on
+    let mut target = target.into_owned();
+    let mut session = CookieSession::new(state, config, &mut target);
+    let config = CookieSession::hop_config(config);
+    loop {
+        session.attach(&mut target);
+        let (head, mut stream) = stream_once(engine, &target, &config, deadline, permit).await?;
+        if session.follow(&mut target, &head)?.is_none() {
+            return Ok((session.finish(head), stream));
+        }
+        // Dropping the stream cancels the redirect's request
+        permit = stream._permit.take();
+    }
+}
+
+// Starts one streaming request and waits for its head.
+async fn stream_once(
+    engine: &CronetEngine,
+    target: &TargetRequest,
+    config: &ExecutionConfig,
+    deadline: Option<Instant>,
+    permit: Option<Permit>,
+) -> Result<(RequestResult, BodyStream), RequestError> {
+    let (request_handle, response) = engine.start_streaming_request(target, config).await;
+    let mut head = response.head;
+    let mut stream = BodyStream {
+        request: request_handle,
+        body: response.body,
+        done: Some(response.done),
+        deadline,
+        _permit: permit,
+    };
+
+    let head = match deadline {
+        Some(deadline) => match tokio::time::timeout_at(deadline, &mut head).await {
+            Ok(res) => res,
+            Err(_) => {
+                stream.cancel_timed_out();
+                head.await
+            }
+        },
+        None => head.await,
+    };
+    match head {
+        Ok(Ok(head)) => Ok((head, stream)),
+        Ok(Err(error)) => Err(error),
+        Err(_) => Err(internal_executor_error()),
+    }
+}
+
+/// Body of a streaming request in flight. Dropping it early cancels the
+/// request.
+pub(crate) struct BodyStream {
+    request: CronetRequest,
+    body: mpsc::Receiver<Vec<u8>>,
+    // Taken once the request has reported its outcome
+    done: Option<oneshot::Receiver<Result<(), RequestError>>>,
+    deadline: Option<Instant>,
+    // Upstream request slot, held until the body is done with
+    _permit: Option<Permit>,
+}
+
+impl BodyStream {
+    /// Next body chunk, the request's error once the body ends early, or
+    /// `None` when the body is complete.
+    pub(crate) async fn next_chunk(&mut self) -> Option<Result<Vec<u8>, RequestError>> {
+        self.done.as_ref()?;
+        let chunk = match self.deadline {
+            Some(deadline) => match tokio::time::timeout_at(deadline, self.body.recv()).await {
+                Ok(chunk) => chunk,
+                Err(_) => {
+                    self.cancel_timed_out();
+                    // Drain what was read before the cancel landed
+                    self.body.recv().await
+                }
+            },
+            None => self.body.recv().await,
+        };
+        if let Some(chunk) = chunk {
+            return Some(Ok(chunk));
+        }
+        match self.done.take()?.await {
+            Ok(Ok(())) => None,
+            Ok(Err(e)) => Some(Err(e)),
+            Err(_) => Some(Err(internal_executor_error())),
+        }
+    }
+
+    fn cancel_timed_out(&mut self) {
+        self.deadline = None;
+        self.request.cancel_timed_out();
+    }
+}
+
+// For a request whose callbacks went away without reporting an outcome
+fn internal_executor_error() -> RequestError {
+    RequestError::new(ErrorKind::Internal, "Internal Executor Error")
+}
+
+/// Groups ordered header pairs by name, keeping repeated values in order.
+fn header_map(headers: &[(String, String)]) -> HashMap<String, HeaderValues> {
+    let mut map: HashMap<String, HeaderValues> = HashMap::new();
+    for (name, value) in headers {
+        map.entry(name.clone())
+            .or_default()
+            .values
+            .push(value.clone());
+    }
+    map
+}
+
+fn timing_to_proto(t: RequestTiming) -> Timing {
+    // Duration of a phase, 0 if either end is missing
+    let span = |start: Option<i64>, end: Option<i64>| match (start, end) {
+        (Some(s), Some(e)) if e >= s => e - s,
+        _ => 0,
+    };
+    Timing {
+        dns_ms: span(t.dns_start, t.dns_end),
+        connect_ms: span(t.connect_start, t.connect_end),
+        ssl_ms: span(t.ssl_start, t.ssl_end),
+        send_ms: span(t.sending_start, t.sending_end),
+        wait_ms: span(t.sending_end, t.response_start),
+        total_ms: span(t.request_start, t.request_end),
+        request_start: t.request_start.unwrap_or_default(),
+        dns_start: t.dns_start.unwrap_or_default(),
+        dns_end: t.dns_end.unwrap_or_default(),
+        connect_start: t.connect_start.unwrap_or_default(),
+        connect_end: t.connect_end.unwrap_or_default(),
+        ssl_start: t.ssl_start.unwrap_or_default(),
+        ssl_end: t.ssl_end.unwrap_or_default(),
+        sending_start: t.sending_start.unwrap_or_default(),
+        sending_end: t.sending_end.unwrap_or_default(),
+        response_start: t.response_start.unwrap_or_default(),
+        request_end: t.request_end.unwrap_or_default(),
+        socket_reused: t.socket_reused,
+        sent_byte_count: t.sent_byte_count,
+        received_byte_count: t.received_byte_count,
+    }
+}
+
+/// Extracts a deadline in milliseconds from Connect (`Connect-Timeout-Ms`) or
+/// gRPC (`grpc-timeout`) request headers. Connect takes precedence.
+pub fn deadline_from_headers(headers: &HeaderMap) -> Option<u32> {
+    if let Some(value) = headers.get("connect-timeout-ms") {
+        let ms = value.to_str().ok()?.trim().parse::<u32>().ok()?;
+        return Some(ms.max(1));
+    }
+    let value = headers.get("grpc-timeout")?.to_str().ok()?;
+    parse_grpc_timeout(value)
+}
+
+/// Parses a `grpc-timeout` value (`<1-8 digits><unit>`) into milliseconds,
+/// rounding sub-millisecond deadlines up so they never become "no deadline"
+/// (a `timeout_ms` of 0).
+pub fn parse_grpc_timeout(value: &str) -> Option<u32> {
+    let value = value.trim();
+    if value.len() < 2 || value.len() > 9 {
+        return None;
+    }
+    let (digits, unit) = value.split_at(value.len() - 1);
+    let amount: u64 = digits.parse().ok()?;
+    let ms = match unit {
+        "H" => amount.checked_mul(3_600_000)?,
+        "M" => amount.checked_mul(60_000)?,
+        "S" => amount.checked_mul(1_000)?,
+        "m" => amount,
+        "u" => amount.div_ceil(1_000),
+        "n" => amount.div_ceil(1_000_000),
+        _ => return None,
+    };
+    Some(ms.clamp(1, u32::MAX as u64) as u32)
+}
+
+pub async fn get_metrics() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        crate::metrics::render(),
+    )
+}
+
+#[derive(serde::Serialize)]
+pub struct CacheInfo {
+    /// Profile name, empty for the default engine.
+    pub profile: String,
+    pub mode: &'static str,
+    pub max_size: u64,
+    /// Bytes on disk; `None` for in-memory caches, whose size Cronet does
+    /// not report.
+    pub disk_usage: Option<u64>,
+    pub storage_path: Option<String>,
+    /// Set by `DELETE`: whether the cache was cleared.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cleared: Option<bool>,
+}
+
+impl CacheInfo {
+    fn new(profile: &str, engine: &CronetEngine) -> Self {
+        let stats = engine.cache_stats();
+        CacheInfo {
+            profile: profile.to_string(),
+            mode: stats.mode.as_str(),
+            max_size: stats.max_size,
+            disk_usage: stats.disk_usage,
+            storage_path: stats
+                .storage_path
+                .map(|path| path.to_string_lossy().into_owned()),
+            cleared: None,
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct CacheParams {
+    /// Only this profile; "default" or empty for the default engine.
+    pub profile: Option<String>,
+}
+
+/// Engines selected by `params`.
+fn cache_engines<'a>(
+    state: &'a AppState,
+    params: &CacheParams,
+) -> Result<Vec<(&'a str, &'a Arc<CronetEngine>)>, String> {
+    match params.profile.as_deref() {
+        Some(profile) => Ok(vec![state.named_engine(profile)?]),
+        None => Ok(state.named_engines()),
+    }
+}
+
+/// `GET /admin/cache`: cache mode, limit and disk usage of each engine.
+pub async fn get_cache(
+    State(state): State<AppState>,
+    Query(params): Query<CacheParams>,
+) -> Response {
+    match cache_engines(&state, &params) {
+        Ok(engines) => Json(
+            engines
+                .into_iter()
+                .map(|(name, engine)| CacheInfo::new(name, engine))
+                .collect::<Vec<_>>(),
+        )
+        .into_response(),
+        Err(e) => (StatusCode::NOT_FOUND, e).into_response(),
+    }
+}
+
+/// `DELETE /admin/cache`: clears the HTTP cache of each engine, or of one
+/// profile, and reports the caches afterwards.
+pub async fn clear_cache(
+    State(state): State<AppState>,
+    Query(params): Query<CacheParams>,
+) -> Response {
+    let engines = match cache_engines(&state, &params) {
+        Ok(engines) => engines,
+        Err(e) => return (StatusCode::NOT_FOUND, e).into_response(),
+    };
+    let mut caches = Vec::new();
+    for (name, engine) in engines {
+        // Starts and shuts down engines, which waits on their network threads
+        let clearing = engine.clone();
+        let cleared = match tokio::task::spawn_blocking(move || clearing.clear_cache()).await {
+            Ok(Ok(cleared)) => cleared,
+            Ok(Err(e)) => return (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+            Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        };
+        tracing::info!("Cache of engine {:?} cleared: {}", name, cleared);
+        caches.push(CacheInfo {
+            cleared: Some(cleared),
+            ..CacheInfo::new(name, engine)
+        });
+    }
+    Json(caches).into_response()
+}
+
+#[derive(serde::Deserialize)]
+pub struct NetLogParams {
+    /// Engine of this profile; "default" or unset for the default engine.
+    #[serde(default)]
+    pub profile: String,
+    /// Also log bytes sent and received, and cookies.
+    #[serde(default)]
+    pub log_all: bool,
+    /// Size limit in bytes; `netlog.max_size` when unset.
+    pub max_size: Option<u64>,
+}
+
+/// `POST /admin/netlog/start`: starts a NetLog capture of an engine's direct
+/// requests.
+pub async fn start_netlog(
+    State(state): State<AppState>,
+    Query(params): Query<NetLogParams>,
+) -> Response {
+    let (profile, engine) = match state.named_engine(&params.profile) {
+        Ok(engine) => engine,
+        Err(e) => return (StatusCode::NOT_FOUND, e).into_response(),
+    };
+    let started = state
+        .netlogs
+        .start(profile, engine.clone(), params.log_all, params.max_size)
+        .await;
+    match started {
+        Ok(file) => (StatusCode::CREATED, Json(file)).into_response(),
+        Err(e) if e.starts_with("NetLog already running") => {
+            (StatusCode::CONFLICT, e).into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+/// `POST /admin/netlog/stop`: stops the capture of an engine and reports
+/// its file.
+pub async fn stop_netlog(
+    State(state): State<AppState>,
+    Query(params): Query<NetLogParams>,
+) -> Response {
+    let profile = match state.named_engine(&params.profile) {
+        Ok((profile, _)) => profile,
+        Err(e) => return (StatusCode::NOT_FOUND, e).into_response(),
+    };
+    // Stopping a NetLog waits for the file to be written out
+    let (netlogs, profile) = (state.netlogs.clone(), profile.to_string());
+    let stopped = tokio::task::spawn_blocking(move || netlogs.stop(&profile))
+        .await
+        .unwrap_or(None);
+    match stopped {
+        Some(file) => Json(file).into_response(),
+        None => (
+            StatusCode::NOT_FOUND,
+            format!("No NetLog running: {}", params.profile),
+        )
+            .into_response(),
+    }
+}
+
+/// `GET /admin/netlog`: capture files, finished or running.
+pub async fn list_netlogs(State(state): State<AppState>) -> Json<Vec<NetLogFile>> {
+    Json(state.netlogs.list())
+}
+
+/// `GET /admin/netlog/:name`: downloads a capture file.
+pub async fn get_netlog(State(state): State<AppState>, Path(name): Path<String>) -> Response {
+    let path = match state.netlogs.path(&name) {
+        Ok(path) => path,
+        Err(e) => return (StatusCode::NOT_FOUND, e).into_response(),
+    };
+    match tokio::fs::File::open(&path).await {
+        Ok(file) => (
+            [
+                (header::CONTENT_TYPE, "application/json".to_string()),
+                (
+                    header::CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"{}\"", name),
+                ),
+            ],
+            Body::from_stream(read_chunks(file)),
+        )
+            .into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+// The contents of `file`, a chunk at a time
+fn read_chunks(file: tokio::fs::File) -> impl Stream<Item = std::io::Result<Bytes>> {
+    stream::unfold(Some(file), |file| async move {
+        let mut file = file?;
+        let mut chunk = vec![0; 64 * 1024];
+        match file.read(&mut chunk).await {
+            Ok(0) => None,
+            Ok(n) => {
+                chunk.truncate(n);
+                Some((Ok(Bytes::from(chunk)), Some(file)))
+            }
+            Err(e) => Some((Err(e), None)),
+        }
+    })
+}
+
+/// `DELETE /admin/netlog/:name`: deletes a finished capture file.
+pub async fn delete_netlog(State(state): State<AppState>, Path(name): Path<String>) -> Response {
+    match state.netlogs.remove(&name) {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) if e.starts_with("Unknown NetLog") => (StatusCode::NOT_FOUND, e).into_response(),
+        Err(e) if e.starts_with("NetLog still running") => {
+            (StatusCode::CONFLICT, e).into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct CookieFilter {
+    pub name: Option<String>,
+    pub domain: Option<String>,
+    pub path: Option<String>,
+}
+
+/// `GET /api/v1/sessions/:session_id/cookies`: the session's cookies,
+/// optionally only those for `?domain=` and its subdomains.
+pub async fn list_cookies(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+    Query(filter): Query<CookieFilter>,
+) -> Json<Vec<Cookie>> {
+    let jar = state.cookies.get(&session_id).unwrap_or_default();
+    Json(jar.list(filter.domain.as_deref()))
+}
+
+/// `POST /api/v1/sessions/:session_id/cookies`: adds or replaces cookies.
+pub async fn set_cookies(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+    Json(cookies): Json<Vec<Cookie>>,
+) -> Response {
+    match state.cookies.with(&session_id, |jar| jar.import(cookies)) {
+        Ok(count) => Json(serde_json::json!({ "count": count })).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e).into_response(),
+    }
+}
+
+/// `DELETE /api/v1/sessions/:session_id/cookies`: removes the cookies
+/// matching `?name=`, `?domain=` and `?path=`, or all of them.
+pub async fn delete_cookies(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+    Query(filter): Query<CookieFilter>,
+) -> Json<serde_json::Value> {
+    let removed = state
+        .cookies
+        .with_existing(&session_id, |jar| {
+            jar.remove(
+                filter.name.as_deref(),
+                filter.domain.as_deref(),
+                filter.path.as_deref(),
+            )
+        })
+        .unwrap_or(0);
+    Json(serde_json::json!({ "count": removed }))
+}
+
+#[derive(serde::Deserialize)]
+pub struct CookieFormat {
+    /// `json` (default) or `netscape`.
+    pub format: Option<String>,
+}
+
+impl CookieFormat {
+    fn is_netscape(&self) -> Result<bool, String> {
+        match self.format.as_deref() {
+            None | Some("json") => Ok(false),
+            Some("netscape") => Ok(true),
+            Some(other) => Err(format!("Unknown cookie format: {}", other)),
+        }
+    }
+}
+
+/// `GET /api/v1/sessions/:session_id/cookies/export`: the session's cookies
+/// as JSON or a Netscape `cookies.txt`.
+pub async fn export_cookies(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+    Query(format): Query<CookieFormat>,
+) -> Response {
+    let jar = state.cookies.get(&session_id).unwrap_or_default();
+    match format.is_netscape() {
+        Ok(true) => (
+            [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+            jar.to_netscape(),
+        )
+            .into_response(),
+        Ok(false) => Json(jar.list(None)).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e).into_response(),
+    }
+}
+
+/// `POST /api/v1/sessions/:session_id/cookies/import`: adds the cookies of
+/// a JSON or Netscape export.
+pub async fn import_cookies(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+    Query(format): Query<CookieFormat>,
+    body: Bytes,
+) -> Response {
+    let imported = format.is_netscape().and_then(|netscape| {
+        let text = std::str::from_utf8(&body).map_err(|e| format!("Invalid cookie file: {}", e))?;
+        if netscape {
+            state
+                .cookies
+                .with(&session_id, |jar| jar.import_netscape(text))
+        } else {
+            let cookies: Vec<Cookie> =
+                serde_json::from_str(text).map_err(|e| format!("Invalid cookie JSON: {}", e))?;
+            state.cookies.with(&session_id, |jar| jar.import(cookies))
+        }
+    });
+    match imported {
+        Ok(count) => Json(serde_json::json!({ "count": count })).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e).into_response(),
+    }
+}
+
+impl AppState {
+    fn session_info(&self, session: &Session) -> SessionInfo {
+        let cookies = self.cookies.get(&session.id).map_or(0, |jar| jar.len());
+        session.info(cookies)
+    }
+}
+
+/// `POST /api/v1/sessions`: starts a session with an engine of its own.
+pub async fn create_session(
+    State(state): State<AppState>,
+    Json(options): Json<SessionOptions>,
+) -> Response {
+    let base = match state.profile(&options.profile) {
+        Ok(profile) => profile
+            .map_or(&state.engine, |profile| &profile.engine)
+            .clone(),
+        Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    };
+    // Starting the session's engine, and shutting down those of expired
+    // sessions, waits on their network threads
+    let blocking = state.clone();
+    let created = tokio::task::spawn_blocking(move || {
+        // Frees the ids and slots of expired sessions, along with their cookies
+        blocking.evict_expired_sessions();
+        blocking.sessions.create(options, &base)
+    })
+    .await
+    .unwrap_or_else(|e| Err(e.to_string()));
+    match created {
+        Ok(session) => {
+            tracing::info!(
+                "Session {} created (profile {:?})",
+                session.id,
+                session.profile
+            );
+            (StatusCode::CREATED, Json(state.session_info(&session))).into_response()
+        }
+        Err(e) => {
+            let status = if e.starts_with("Session already exists") {
+                StatusCode::CONFLICT
+            } else if e.starts_with("Too many sessions") {
+                StatusCode::TOO_MANY_REQUESTS
+            } else {
+                StatusCode::INTERNAL_SERVER_ERROR
+            };
+            (status, e).into_response()
+        }
+    }
+}
+
+/// `GET /api/v1/sessions`: the live sessions.
+pub async fn list_sessions(State(state): State<AppState>) -> Json<Vec<SessionInfo>> {
+    Json(
+        state
+            .sessions
+            .list()
+            .iter()
+            .map(|session| state.session_info(session))
+            .collect(),
+    )
+}
+
+/// `GET /api/v1/sessions/:session_id`
+pub async fn get_session(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+) -> Response {
+    match state.sessions.peek(&session_id) {
+        Some(session) => Json(state.session_info(&session)).into_response(),
+        None => (
+            StatusCode::NOT_FOUND,
+            format!("Unknown session: {}", session_id),
+        )
+            .into_response(),
+    }
+}
+
+/// `DELETE /api/v1/sessions/:session_id`: ends the session and drops its
+/// cookies. Its engine shuts down once the requests using it are done.
+pub async fn delete_session(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+) -> Response {
+    match state.sessions.remove(&session_id) {
+        Some(session) => {
+            state.cookies.remove(&session.id);
+            tracing::info!("Session {} deleted", session.id);
+            // Dropping the last handle shuts the engine down, which blocks
+            tokio::task::spawn_blocking(move || drop(session));
+            StatusCode::NO_CONTENT.into_response()
         }
+        None => (
+            StatusCode::NOT_FOUND,
+            format!("Unknown session: {}", session_id),
+        )
+            .into_response(),
     }
 }
 
@@ -0,0 +1,232 @@
+//! Named sessions: an engine of their own, default headers and a cookie jar,
+//! shared by the requests that set `config.session_id`.
+//!
+//! A session's engine keeps connections, TLS sessions and the DNS cache warm
+//! from one request to the next. Sessions expire after their TTL without use.
+
+use crate::config::Limits;
+use crate::cronet::{proxy_rules, CronetEngine};
+use crate::cronet_pb::ProxyConfig;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Body of `POST /api/v1/sessions`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SessionOptions {
+    /// Generated when empty.
+    pub id: String,
+    /// Engine settings to start from; empty for the default engine.
+    pub profile: String,
+    /// Proxy for every request of the session.
+    pub proxy: Option<ProxyConfig>,
+    /// Headers added to requests that do not set them.
+    pub headers: BTreeMap<String, String>,
+    /// Seconds without use before the session expires; the server default
+    /// when unset.
+    pub ttl_secs: Option<u64>,
+}
+
+pub struct Session {
+    pub id: String,
+    pub profile: String,
+    pub proxy: Option<ProxyConfig>,
+    pub headers: Vec<(String, String)>,
+    pub engine: Arc<CronetEngine>,
+    ttl: Duration,
+    created_at: SystemTime,
+    last_used: Mutex<Instant>,
+}
+
+/// A session as reported by the API. Proxy credentials are left out.
+#[derive(Debug, Serialize)]
+pub struct SessionInfo {
+    pub id: String,
+    pub profile: String,
+    /// `scheme://host:port`
+    pub proxy: Option<String>,
+    pub headers: BTreeMap<String, String>,
+    pub ttl_secs: u64,
+    /// Seconds since the Unix epoch.
+    pub created_at: u64,
+    pub expires_at: u64,
+    pub cookies: usize,
+}
+
+impl Session {
+    // Starts the session's engine with the settings of `base`, minus its
+    // storage path
+    fn start(
+        id: String,
+        options: SessionOptions,
+        base: &CronetEngine,
+        ttl: Duration,
+        pool: (usize, Duration),
+    ) -> Result<Session, String> {
+        let engine = base
+            .standalone(options.proxy.as_ref())?
+            .with_proxy_pool(pool.0, pool.1);
+        Ok(Session {
+            id,
+            profile: options.profile,
+            proxy: options.proxy,
+            headers: options.headers.into_iter().collect(),
+            engine: Arc::new(engine),
+            ttl,
+            created_at: SystemTime::now(),
+            last_used: Mutex::new(Instant::now()),
+        })
+    }
+
+    pub fn info(&self, cookies: usize) -> SessionInfo {
+        let unix = |time: SystemTime| time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        let idle = self.last_used.lock().unwrap().elapsed();
+        SessionInfo {
+            id: self.id.clone(),
+            profile: self.profile.clone(),
+            proxy: self.proxy.as_ref().map(|proxy| {
+                let rules = proxy_rules(proxy);
+                match rules.split_once("://") {
+                    // Drop the credentials
+                    Some((scheme, rest)) => {
+                        format!("{}://{}", scheme, rest.rsplit('@').next().unwrap_or(rest))
+                    }
+                    None => rules,
+                }
+            }),
+            headers: self.headers.iter().cloned().collect(),
+            ttl_secs: self.ttl.as_secs(),
+            created_at: unix(self.created_at),
+            expires_at: unix(SystemTime::now() + self.ttl.saturating_sub(idle)),
+            cookies,
+        }
+    }
+
+    fn is_expired(&self, now: Instant) -> bool {
+        now.duration_since(*self.last_used.lock().unwrap()) >= self.ttl
+    }
+
+    fn touch(&self) {
+        *self.last_used.lock().unwrap() = Instant::now();
+    }
+}
+
+/// Live sessions by id.
+pub struct Sessions {
+    entries: Mutex<HashMap<String, Arc<Session>>>,
+    max_sessions: usize,
+    default_ttl: Duration,
+    // Proxy engine pool of each session engine: size and idle timeout
+    proxy_pool: (usize, Duration),
+}
+
+impl Sessions {
+    pub fn new(limits: &Limits) -> Self {
+        Sessions {
+            entries: Mutex::new(HashMap::new()),
+            max_sessions: limits.max_sessions,
+            default_ttl: Duration::from_secs(limits.session_ttl_secs),
+            proxy_pool: (
+                limits.proxy_pool_size,
+                Duration::from_secs(limits.proxy_idle_timeout_secs),
+            ),
+        }
+    }
+
+    /// Starts a session whose engine has the settings of `base`. Expired
+    /// sessions still count until evicted.
+    pub fn create(
+        &self,
+        options: SessionOptions,
+        base: &CronetEngine,
+    ) -> Result<Arc<Session>, String> {
+        let (id, ttl) = self.prepare(&options)?;
+        let session = Session::start(id, options, base, ttl, self.proxy_pool)?;
+        self.insert(session)
+    }
+
+    // Id and TTL for a new session, checked against the live ones
+    fn prepare(&self, options: &SessionOptions) -> Result<(String, Duration), String> {
+        let entries = self.entries.lock().unwrap();
+        if entries.len() >= self.max_sessions {
+            return Err(format!("Too many sessions: limit is {}", self.max_sessions));
+        }
+        let id = if options.id.is_empty() {
+            crate::random::token()
+        } else {
+            options.id.clone()
+        };
+        if entries.contains_key(&id) {
+            return Err(format!("Session already exists: {}", id));
+        }
+        let ttl = options
+            .ttl_secs
+            .map_or(self.default_ttl, Duration::from_secs);
+        Ok((id, ttl))
+    }
+
+    // Adds a session started after `prepare`, unless another request took
+    // the id or the last slot in the meantime
+    fn insert(&self, session: Session) -> Result<Arc<Session>, String> {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= self.max_sessions {
+            return Err(format!("Too many sessions: limit is {}", self.max_sessions));
+        }
+        if entries.contains_key(&session.id) {
+            return Err(format!("Session already exists: {}", session.id));
+        }
+        let session = Arc::new(session);
+        entries.insert(session.id.clone(), session.clone());
+        Ok(session)
+    }
+
+    /// The live session `id`, marked as used.
+    pub fn get(&self, id: &str) -> Option<Arc<Session>> {
+        let session = self.entries.lock().unwrap().get(id).cloned()?;
+        if session.is_expired(Instant::now()) {
+            return None;
+        }
+        session.touch();
+        Some(session)
+    }
+
+    /// The live session `id`, without marking it as used.
+    pub fn peek(&self, id: &str) -> Option<Arc<Session>> {
+        let session = self.entries.lock().unwrap().get(id).cloned()?;
+        (!session.is_expired(Instant::now())).then_some(session)
+    }
+
+    pub fn remove(&self, id: &str) -> Option<Arc<Session>> {
+        self.entries.lock().unwrap().remove(id)
+    }
+
+    /// Live sessions, by id.
+    pub fn list(&self) -> Vec<Arc<Session>> {
+        let now = Instant::now();
+        let mut sessions: Vec<_> = self
+            .entries
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|session| !session.is_expired(now))
+            .cloned()
+            .collect();
+        sessions.sort_by(|a, b| a.id.cmp(&b.id));
+        sessions
+    }
+
+    /// Removes expired sessions and returns them. Their engines shut down once
+    /// the requests still using them are done.
+    pub fn evict_expired(&self) -> Vec<Arc<Session>> {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+        let expired: Vec<String> = entries
+            .iter()
+            .filter(|(_, session)| session.is_expired(now))
+            .map(|(id, _)| id.clone())
+            .collect();
+        expired.iter().filter_map(|id| entries.remove(id)).collect()
+    }
+}
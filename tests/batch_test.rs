@@ -0,0 +1,77 @@
+use reqwest::Client;
+use serde_json::json;
+
+const BATCH_URL: &str = "http://127.0.0.1:3000/api/v1/execute/batch";
+
+fn get_request(request_id: &str, url: &str) -> serde_json::Value {
+    json!({
+        "request_id": request_id,
+        "target": { "url": url, "method": "GET" }
+    })
+}
+
+async fn run_batch(client: &Client, payload: serde_json::Value) -> Vec<serde_json::Value> {
+    let resp = client
+        .post(BATCH_URL)
+        .json(&payload)
+        .send()
+        .await
+        .expect("Failed to send request");
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.headers()["content-type"], "application/x-ndjson");
+
+    let body = resp.text().await.expect("Failed to read body");
+    body.lines()
+        .map(|line| serde_json::from_str(line).expect("Invalid NDJSON line"))
+        .collect()
+}
+
+/// Every request gets a result line, keyed by request_id.
+#[tokio::test]
+async fn test_batch_collects_all() {
+    let client = Client::new();
+    let payload = json!({
+        "requests": [
+            get_request("batch-1", "https://httpbin.org/delay/1"),
+            get_request("batch-2", "https://httpbin.org/get"),
+            get_request("batch-3", "https://nonexistent.invalid/"),
+        ],
+        "max_concurrency": 3
+    });
+
+    let results = run_batch(&client, payload).await;
+    assert_eq!(results.len(), 3);
+
+    let result = |id: &str| {
+        results
+            .iter()
+            .find(|result| result["request_id"] == json!(id))
+            .unwrap_or_else(|| panic!("Missing result for {}", id))
+    };
+    assert_eq!(result("batch-1")["success"], json!(true));
+    assert_eq!(result("batch-2")["success"], json!(true));
+    assert_eq!(result("batch-3")["success"], json!(false));
+
+    // Completion order: the delayed request finishes last
+    assert_eq!(results[2]["request_id"], json!("batch-1"));
+}
+
+/// With fail_fast the stream ends at the first failure.
+#[tokio::test]
+async fn test_batch_fail_fast() {
+    let client = Client::new();
+    let payload = json!({
+        "requests": [
+            get_request("fast-1", "https://httpbin.org/get"),
+            get_request("fast-2", "https://nonexistent.invalid/"),
+            get_request("fast-3", "https://httpbin.org/get"),
+        ],
+        "max_concurrency": 1,
+        "fail_fast": true
+    });
+
+    let results = run_batch(&client, payload).await;
+    let ids: Vec<_> = results.iter().map(|r| r["request_id"].clone()).collect();
+    assert_eq!(ids, [json!("fast-1"), json!("fast-2")]);
+    assert_eq!(results[1]["success"], json!(false));
+}
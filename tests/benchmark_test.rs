@@ -14,7 +14,7 @@ async fn execute_engine_request(engine: &Arc<CronetEngine>, url: &str) {
     };
     let config = ExecutionConfig::default();
 
-    let (handle, rx) = engine.start_request(&target, &config);
+    let (handle, rx) = engine.start_request(&target, &config).await;
     let _ = rx.await;
     // Ensure handle is dropped
     drop(handle);
@@ -0,0 +1,81 @@
+use base64::Engine as _;
+use reqwest::Client;
+use serde_json::json;
+
+const EXECUTE_URL: &str = "http://127.0.0.1:3000/api/v1/execute";
+
+async fn post_echo(client: &Client, body: &str, config: serde_json::Value) -> serde_json::Value {
+    let payload = json!({
+        "request_id": "body-encoding",
+        "target": {
+            "url": "https://httpbin.org/post",
+            "method": "POST",
+            "body": body
+        },
+        "config": config
+    });
+    let resp = client
+        .post(EXECUTE_URL)
+        .json(&payload)
+        .send()
+        .await
+        .expect("Failed to send request");
+    assert!(resp.status().is_success(), "Status: {}", resp.status());
+    let result: serde_json::Value = resp.json().await.expect("Failed to parse JSON response");
+    assert_eq!(result["success"], json!(true), "Request failed: {}", result);
+    result
+}
+
+/// Bodies default to base64, as in canonical proto3 JSON.
+#[tokio::test]
+async fn test_base64_is_default() {
+    let client = Client::new();
+    let result = post_echo(&client, "aGVsbG8=", json!({})).await;
+
+    let body = base64::engine::general_purpose::STANDARD
+        .decode(result["response"]["body"].as_str().unwrap())
+        .expect("Response body is not base64");
+    let echoed: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(echoed["data"], json!("hello"));
+}
+
+#[tokio::test]
+async fn test_hex_encoding() {
+    let client = Client::new();
+    let result = post_echo(&client, "68656c6c6f", json!({ "body_encoding": "hex" })).await;
+
+    let body = hex::decode(result["response"]["body"].as_str().unwrap())
+        .expect("Response body is not hex");
+    let echoed: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(echoed["data"], json!("hello"));
+}
+
+#[tokio::test]
+async fn test_utf8_encoding() {
+    let client = Client::new();
+    let result = post_echo(
+        &client,
+        "héllo",
+        json!({ "bodyEncoding": "BODY_ENCODING_UTF8" }),
+    )
+    .await;
+
+    let body = result["response"]["body"].as_str().unwrap();
+    let echoed: serde_json::Value = serde_json::from_str(body).expect("Body is not plain JSON");
+    assert_eq!(echoed["data"], json!("héllo"));
+}
+
+#[tokio::test]
+async fn test_unknown_encoding_rejected() {
+    let client = Client::new();
+    let resp = client
+        .post(EXECUTE_URL)
+        .json(&json!({
+            "target": { "url": "https://httpbin.org/get", "method": "GET" },
+            "config": { "body_encoding": "rot13" }
+        }))
+        .send()
+        .await
+        .expect("Failed to send request");
+    assert_eq!(resp.status(), 422);
+}
@@ -0,0 +1,177 @@
+use reqwest::Client;
+use serde_json::json;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::Mutex;
+
+mod common;
+use common::execute;
+
+/// Held by tests that rely on the cache contents and by the test that clears
+/// them, which would otherwise race.
+static CACHE: Mutex<()> = Mutex::const_new(());
+
+/// A URL no engine has fetched before.
+fn fresh_url() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    format!("https://httpbin.org/cache/60?nonce={}", nanos)
+}
+
+/// The admin endpoint lists the default engine first.
+#[tokio::test]
+async fn test_cache_stats() {
+    let client = Client::new();
+    let resp = client
+        .get("http://127.0.0.1:3000/admin/cache")
+        .send()
+        .await
+        .expect("Failed to send request");
+    assert!(resp.status().is_success());
+    let caches: serde_json::Value = resp.json().await.expect("Failed to parse JSON");
+    assert_eq!(caches[0]["profile"], json!(""));
+    assert!(caches[0]["mode"].is_string(), "{}", caches);
+
+    let resp = client
+        .get("http://127.0.0.1:3000/admin/cache?profile=does-not-exist")
+        .send()
+        .await
+        .expect("Failed to send request");
+    assert_eq!(resp.status(), 404);
+}
+
+/// A bypassed request goes to the network even when a cached copy exists.
+#[tokio::test]
+async fn test_cache_bypass() {
+    let client = Client::new();
+    let url = fresh_url();
+    for id in ["cache-bypass-1", "cache-bypass-2"] {
+        let result = execute(
+            &client,
+            json!({
+                "request_id": id,
+                "target": { "url": url, "method": "GET" },
+                "config": { "cache": "bypass" }
+            }),
+        )
+        .await;
+        assert_eq!(result["success"], json!(true), "{}", result);
+        assert_eq!(result["response"]["was_cached"], json!(false));
+    }
+}
+
+/// only-if-cached fails for a URL that was never fetched, and is answered
+/// from the cache once it has been, if the engine has one.
+#[tokio::test]
+async fn test_cache_only_if_cached() {
+    let _cache = CACHE.lock().await;
+    let client = Client::new();
+    let url = fresh_url();
+    let only_if_cached = json!({
+        "request_id": "cache-only-1",
+        "target": { "url": url, "method": "GET" },
+        "config": { "cache": "only-if-cached" }
+    });
+
+    let result = execute(&client, only_if_cached.clone()).await;
+    assert_eq!(result["success"], json!(false), "{}", result);
+    assert!(result["error_message"]
+        .as_str()
+        .unwrap()
+        .starts_with("Not cached"));
+
+    let result = execute(
+        &client,
+        json!({
+            "request_id": "cache-only-2",
+            "target": { "url": url, "method": "GET" }
+        }),
+    )
+    .await;
+    assert_eq!(result["success"], json!(true), "{}", result);
+
+    let caches: serde_json::Value = client
+        .get("http://127.0.0.1:3000/admin/cache?profile=default")
+        .send()
+        .await
+        .expect("Failed to send request")
+        .json()
+        .await
+        .expect("Failed to parse JSON");
+    if caches[0]["mode"] == json!("disabled") {
+        println!("Default engine has no cache, skipping cache hit check");
+        return;
+    }
+    let result = execute(&client, only_if_cached).await;
+    assert_eq!(result["success"], json!(true), "{}", result);
+    assert_eq!(result["response"]["was_cached"], json!(true));
+}
+
+/// only-if-cached is refused for methods with side effects, since a miss
+/// still reaches the origin.
+#[tokio::test]
+async fn test_cache_only_if_cached_needs_safe_method() {
+    let client = Client::new();
+    let result = execute(
+        &client,
+        json!({
+            "request_id": "cache-only-post",
+            "target": { "url": "https://httpbin.org/post", "method": "POST" },
+            "config": { "cache": "only-if-cached" }
+        }),
+    )
+    .await;
+    assert_eq!(result["success"], json!(false), "{}", result);
+    assert_eq!(
+        result["error_message"],
+        json!("Unsupported: only-if-cached needs GET or HEAD, not POST")
+    );
+    assert_eq!(result["error"]["kind"], json!("ERROR_KIND_INVALID_REQUEST"));
+}
+
+/// Unknown policy names are rejected like other malformed requests.
+#[tokio::test]
+async fn test_cache_unknown_policy() {
+    let client = Client::new();
+    let resp = client
+        .post("http://127.0.0.1:3000/api/v1/execute")
+        .json(&json!({
+            "request_id": "cache-bad",
+            "target": { "url": "https://httpbin.org/get", "method": "GET" },
+            "config": { "cache": "sometimes" }
+        }))
+        .send()
+        .await
+        .expect("Failed to send request");
+    assert_eq!(resp.status(), 422);
+}
+
+/// Clearing reports every engine, and whether it had a cache to clear.
+#[tokio::test]
+async fn test_cache_clear() {
+    let _cache = CACHE.lock().await;
+    let client = Client::new();
+    let resp = client
+        .delete("http://127.0.0.1:3000/admin/cache")
+        .send()
+        .await
+        .expect("Failed to send request");
+    assert!(resp.status().is_success());
+    let caches: serde_json::Value = resp.json().await.expect("Failed to parse JSON");
+    for cache in caches.as_array().unwrap() {
+        let cleared = cache["mode"] != json!("disabled");
+        assert_eq!(cache["cleared"], json!(cleared), "{}", cache);
+    }
+
+    // The default engine keeps serving requests after the swap
+    let result = execute(
+        &client,
+        json!({
+            "request_id": "cache-after-clear",
+            "target": { "url": "https://httpbin.org/get", "method": "GET" }
+        }),
+    )
+    .await;
+    assert_eq!(result["success"], json!(true), "{}", result);
+}
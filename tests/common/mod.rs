@@ -0,0 +1,18 @@
+//! Helpers shared by the tests that run against a server on port 3000.
+
+#![allow(dead_code)]
+
+use reqwest::Client;
+
+pub const SERVICE_URL: &str = "http://127.0.0.1:3000/api/v1/execute";
+
+/// Posts `payload` to the REST execute route and returns the JSON response.
+pub async fn execute(client: &Client, payload: serde_json::Value) -> serde_json::Value {
+    let resp = client
+        .post(SERVICE_URL)
+        .json(&payload)
+        .send()
+        .await
+        .expect("Failed to send request");
+    resp.json().await.expect("Failed to parse JSON response")
+}
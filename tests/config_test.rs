@@ -0,0 +1,345 @@
+use cronet_cloak::config::{CacheMode, Config, Overrides, QuicMode};
+use serde_json::json;
+
+#[test]
+fn test_example_config_is_valid() {
+    let config = Config::load("config.example.toml".as_ref()).expect("Example config invalid");
+    config.validate().expect("Example config invalid");
+    assert_eq!(config.listen, "0.0.0.0:3000");
+    assert_eq!(config.engine.cache_mode, CacheMode::Disabled);
+}
+
+#[test]
+fn test_yaml_config() {
+    let config = Config::from_yaml(
+        "listen: 127.0.0.1:8080\nengine:\n  quic_mode: off\n  cache_mode: memory\nlimits:\n  max_in_flight: 32\n",
+    )
+    .expect("Failed to parse YAML");
+    config.validate().expect("Config invalid");
+    assert_eq!(config.listen, "127.0.0.1:8080");
+    assert_eq!(config.engine.quic_mode, QuicMode::Off);
+    assert!(config.engine.enable_http2);
+    assert_eq!(config.engine.cache_mode, CacheMode::Memory);
+    assert_eq!(config.limits.max_in_flight, 32);
+}
+
+#[test]
+fn test_engine_params() {
+    let config = Config::from_toml(
+        r#"
+[engine]
+accept_language = "de-DE,de;q=0.9"
+network_thread_priority = -5
+[engine.experimental_options]
+HostResolverRules = { host_resolver_rules = "MAP * 127.0.0.1" }
+"#,
+    )
+    .expect("Failed to parse TOML");
+    config.validate().expect("Config invalid");
+    assert_eq!(
+        config.engine.accept_language.as_deref(),
+        Some("de-DE,de;q=0.9")
+    );
+    let options = config.engine.experimental_options.as_ref().unwrap();
+    assert_eq!(
+        options["HostResolverRules"]["host_resolver_rules"],
+        "MAP * 127.0.0.1"
+    );
+    let engine = format!("{:?}", config.engine.engine_config());
+    assert!(engine.contains("de-DE"), "{}", engine);
+}
+
+/// Profiles inherit the `[engine]` settings they do not override.
+#[test]
+fn test_profiles_inherit_engine() {
+    let config = Config::from_toml(
+        r#"
+[engine]
+user_agent = "Base/1.0"
+enable_brotli = false
+
+[profiles.no-quic]
+engine = { quic_mode = "off" }
+headers = { "Accept-Language" = "de-DE" }
+"#,
+    )
+    .expect("Failed to parse TOML");
+    config.validate().expect("Config invalid");
+
+    let profile = &config.profiles["no-quic"];
+    assert_eq!(profile.engine.user_agent, "Base/1.0");
+    assert!(!profile.engine.enable_brotli);
+    assert_eq!(profile.engine.quic_mode, QuicMode::Off);
+    assert_eq!(config.engine.quic_mode, QuicMode::Auto);
+    assert_eq!(profile.headers["Accept-Language"], "de-DE");
+}
+
+#[test]
+fn test_profiles_need_own_storage_path() {
+    let dir = std::env::temp_dir();
+    let config = Config::from_yaml(&format!(
+        "engine:\n  storage_path: {dir:?}\nprofiles:\n  cached:\n    engine:\n      storage_path: {dir:?}\n"
+    ))
+    .expect("Failed to parse YAML");
+    let err = config.validate().unwrap_err();
+    assert!(
+        err.contains("profiles.cached.engine.storage_path"),
+        "{}",
+        err
+    );
+}
+
+/// Pins are passed to the engine, and profiles can replace them.
+#[test]
+fn test_public_key_pins() {
+    let config = Config::from_toml(
+        r#"
+[engine]
+pin_bypass_for_local_trust_anchors = false
+
+[[engine.public_key_pins]]
+host = "api.example.com"
+pins = ["sha256/AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA="]
+include_subdomains = true
+expires_at = 4102444800
+
+[profiles.inspected]
+engine = { pin_bypass_for_local_trust_anchors = true }
+
+[profiles.unpinned]
+engine = { public_key_pins = [] }
+"#,
+    )
+    .expect("Failed to parse TOML");
+    config.validate().expect("Config invalid");
+    let pins = &config.engine.public_key_pins[0];
+    assert_eq!(pins.host, "api.example.com");
+    assert!(pins.include_subdomains);
+    assert!(!config.engine.pin_bypass_for_local_trust_anchors);
+
+    let inspected = &config.profiles["inspected"].engine;
+    assert_eq!(inspected.public_key_pins.len(), 1);
+    assert!(inspected.pin_bypass_for_local_trust_anchors);
+    assert!(config.profiles["unpinned"]
+        .engine
+        .public_key_pins
+        .is_empty());
+
+    let engine = format!("{:?}", config.engine.engine_config());
+    assert!(
+        engine.contains("expiration_date: 4102444800000"),
+        "{}",
+        engine
+    );
+    assert!(
+        engine.contains("pin_bypass_for_local_trust_anchors: false"),
+        "{}",
+        engine
+    );
+}
+
+#[test]
+fn test_invalid_public_key_pins() {
+    let config = Config::from_toml(
+        r#"
+[[engine.public_key_pins]]
+host = "https://api.example.com/"
+pins = ["sha1/AAAAAAAAAAAAAAAAAAAAAAAAAAA=", "sha256/tooshort"]
+"#,
+    )
+    .expect("Failed to parse TOML");
+    let err = config.validate().unwrap_err();
+    assert!(err.contains("public_key_pins[0].host"), "{}", err);
+    assert!(err.contains("sha1/"), "{}", err);
+    assert!(err.contains("sha256/tooshort"), "{}", err);
+}
+
+/// Expired pins are valid config but left out of the engine; pins without
+/// an expiry never expire.
+#[test]
+fn test_expired_public_key_pins() {
+    let config = Config::from_toml(
+        r#"
+[[engine.public_key_pins]]
+host = "expired.example.com"
+pins = ["sha256/AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA="]
+expires_at = 1
+
+[[engine.public_key_pins]]
+host = "forever.example.com"
+pins = ["sha256/AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA="]
+"#,
+    )
+    .expect("Failed to parse TOML");
+    config.validate().expect("Config invalid");
+    assert_eq!(config.engine.public_key_pins[1].expires_at, None);
+
+    let engine = format!("{:?}", config.engine.engine_config());
+    assert!(!engine.contains("expired.example.com"), "{}", engine);
+    assert!(engine.contains("forever.example.com"), "{}", engine);
+    assert!(
+        engine.contains(&format!("expiration_date: {}", i64::MAX)),
+        "{}",
+        engine
+    );
+}
+
+/// QUIC hints default to port 443, and profiles can force or forbid QUIC.
+#[test]
+fn test_quic_settings() {
+    let config = Config::from_toml(
+        r#"
+[[engine.quic_hints]]
+host = "www.example.com"
+
+[[engine.quic_hints]]
+host = "api.example.com"
+port = 8443
+alternate_port = 9443
+
+[profiles.h3-only]
+engine = { quic_mode = "force", experimental_options = { QUIC = { origins_to_force_quic_on = "cdn.example.com:443" } } }
+
+[profiles.tcp-only]
+engine = { quic_mode = "off" }
+"#,
+    )
+    .expect("Failed to parse TOML");
+    config.validate().expect("Config invalid");
+    assert_eq!(config.engine.quic_mode, QuicMode::Auto);
+    let hint = &config.engine.quic_hints[0];
+    assert_eq!((hint.port, hint.alternate_port), (443, 443));
+
+    let h3_only = &config.profiles["h3-only"].engine;
+    assert_eq!(h3_only.quic_mode, QuicMode::Force);
+    assert_eq!(h3_only.quic_hints.len(), 2);
+    let engine = format!("{:?}", h3_only.engine_config());
+    assert!(engine.contains("enable_quic: true"), "{}", engine);
+    assert!(engine.contains("alternate_port: 9443"), "{}", engine);
+    // The hinted origins are forced onto QUIC, after those already listed
+    assert_eq!(
+        serde_json::Value::Object(h3_only.effective_experimental_options()),
+        json!({
+            "QUIC": {
+                "origins_to_force_quic_on":
+                    "cdn.example.com:443,www.example.com:443,api.example.com:8443"
+            }
+        })
+    );
+    // and handed to Cronet as that JSON
+    let forced = r#"{"QUIC":{"origins_to_force_quic_on":"cdn.example.com:443,www.example.com:443,api.example.com:8443"}}"#;
+    assert!(engine.contains(&format!("{:?}", forced)), "{}", engine);
+
+    assert!(config.engine.effective_experimental_options().is_empty());
+    let engine = format!("{:?}", config.engine.engine_config());
+    assert!(engine.contains("experimental_options: None"), "{}", engine);
+
+    let engine = format!("{:?}", config.profiles["tcp-only"].engine.engine_config());
+    assert!(engine.contains("enable_quic: false"), "{}", engine);
+}
+
+#[test]
+fn test_invalid_quic_settings() {
+    let config = Config::from_toml(
+        r#"
+[engine]
+quic_mode = "force"
+
+[engine.experimental_options]
+QUIC = "on"
+
+[[engine.quic_hints]]
+host = "example.com:443"
+alternate_port = 0
+
+[profiles.unhinted]
+engine = { quic_mode = "force", quic_hints = [] }
+"#,
+    )
+    .expect("Failed to parse TOML");
+    let err = config.validate().unwrap_err();
+    assert!(err.contains("engine.experimental_options.QUIC"), "{}", err);
+    assert!(err.contains("quic_hints[0].host"), "{}", err);
+    assert!(err.contains("quic_hints[0]: ports"), "{}", err);
+    assert!(
+        err.contains("profiles.unhinted.engine.quic_mode: force needs quic_hints"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn test_unknown_keys_rejected() {
+    let err = Config::from_toml("[engine]\nenable_quik = false\n").unwrap_err();
+    assert!(err.contains("enable_quik"), "{}", err);
+}
+
+#[test]
+fn test_validation_reports_every_problem() {
+    let config = Config::from_toml(
+        "listen = \"nowhere\"\nlog_level = \"loud\"\n[engine]\ncache_mode = \"disk\"\n",
+    )
+    .expect("Failed to parse TOML");
+    let err = config.validate().unwrap_err();
+    assert!(err.contains("listen"), "{}", err);
+    assert!(err.contains("log_level"), "{}", err);
+    assert!(err.contains("storage_path"), "{}", err);
+}
+
+/// Flags and environment variables win over the file.
+#[test]
+fn test_overrides() {
+    let mut config = Config::from_toml("listen = \"127.0.0.1:3000\"\n").unwrap();
+    let overrides = Overrides {
+        listen: Some("127.0.0.1:4000".to_string()),
+        enable_brotli: Some(false),
+        quic_mode: Some(QuicMode::Off),
+        pin_bypass_for_local_trust_anchors: Some(false),
+        max_batch_size: Some(10),
+        ..Default::default()
+    };
+    overrides.apply(&mut config);
+    assert_eq!(config.listen, "127.0.0.1:4000");
+    assert!(!config.engine.enable_brotli);
+    assert_eq!(config.engine.quic_mode, QuicMode::Off);
+    assert!(!config.engine.pin_bypass_for_local_trust_anchors);
+    assert_eq!(config.limits.max_batch_size, 10);
+    assert_eq!(config.grpc_listen(), None);
+
+    config.grpc_port = Some(50051);
+    assert_eq!(
+        config.grpc_listen(),
+        Some("127.0.0.1:50051".parse().unwrap())
+    );
+}
+
+/// Engine overrides reach profiles too, unless a profile sets the key itself.
+#[test]
+fn test_overrides_reach_profiles() {
+    let path = std::env::temp_dir().join(format!("cronet-cloak-{}.toml", std::process::id()));
+    std::fs::write(
+        &path,
+        "[engine]\nuser_agent = \"File/1.0\"\nenable_brotli = true\n\n\
+         [profiles.mobile.engine]\nenable_brotli = true\n\n\
+         [profiles.desktop]\n",
+    )
+    .unwrap();
+    let overrides = Overrides {
+        config: Some(path.clone()),
+        user_agent: Some("Flag/2.0".to_string()),
+        enable_brotli: Some(false),
+        ..Default::default()
+    };
+    let config = Config::from_overrides(&overrides);
+    std::fs::remove_file(&path).unwrap();
+    let config = config.expect("Invalid config");
+
+    assert_eq!(config.engine.user_agent, "Flag/2.0");
+    assert!(!config.engine.enable_brotli);
+    let mobile = &config.profiles["mobile"].engine;
+    assert_eq!(mobile.user_agent, "Flag/2.0");
+    assert!(mobile.enable_brotli);
+    let desktop = &config.profiles["desktop"].engine;
+    assert_eq!(desktop.user_agent, "Flag/2.0");
+    assert!(!desktop.enable_brotli);
+}
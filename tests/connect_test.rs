@@ -0,0 +1,138 @@
+use base64::Engine as _;
+use cronet_cloak::cronet_pb::{Error, ExecuteRequest, ExecuteResponse, TargetRequest};
+use prost::Message;
+use reqwest::Client;
+use serde_json::json;
+
+const CONNECT_URL: &str = "http://127.0.0.1:3000/cronet.engine.v1.EngineService/Execute";
+
+fn get_request(url: &str) -> ExecuteRequest {
+    ExecuteRequest {
+        request_id: "connect-proto".to_string(),
+        target: Some(TargetRequest {
+            method: "GET".to_string(),
+            url: url.to_string(),
+            ..Default::default()
+        }),
+        config: None,
+    }
+}
+
+/// Binary protobuf in, binary protobuf out.
+#[tokio::test]
+async fn test_connect_proto() {
+    let client = Client::new();
+    let resp = client
+        .post(CONNECT_URL)
+        .header("Content-Type", "application/proto")
+        .body(get_request("https://httpbin.org/get").encode_to_vec())
+        .send()
+        .await
+        .expect("Failed to send request");
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.headers()["content-type"], "application/proto");
+
+    let data = resp.bytes().await.unwrap();
+    let response = ExecuteResponse::decode(data).expect("Invalid protobuf response");
+    assert!(response.success);
+    assert_eq!(response.response.unwrap().status_code, 200);
+}
+
+/// Connect GET carries the request in the query string.
+#[tokio::test]
+async fn test_connect_get() {
+    let client = Client::new();
+    let message = get_request("https://httpbin.org/get").encode_to_vec();
+    let message = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(message);
+
+    let resp = client
+        .get(CONNECT_URL)
+        .query(&[
+            ("connect", "v1"),
+            ("encoding", "proto"),
+            ("base64", "1"),
+            ("message", &message),
+        ])
+        .send()
+        .await
+        .expect("Failed to send request");
+    assert_eq!(resp.status(), 200);
+    let response = ExecuteResponse::decode(resp.bytes().await.unwrap()).unwrap();
+    assert!(response.success);
+}
+
+/// Failures come back as Connect error envelopes with a matching status.
+#[tokio::test]
+async fn test_connect_errors() {
+    let client = Client::new();
+
+    let resp = client
+        .post(CONNECT_URL)
+        .json(&json!({
+            "requestId": "connect-error",
+            "target": { "url": "https://nonexistent.invalid/", "method": "GET" }
+        }))
+        .send()
+        .await
+        .expect("Failed to send request");
+    assert_eq!(resp.status(), 503);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["code"], json!("unavailable"));
+    assert!(!body["message"].as_str().unwrap().is_empty());
+    // The structured error travels as a detail
+    let detail = &body["details"][0];
+    assert_eq!(detail["type"], json!("cronet.engine.v1.Error"));
+    assert_eq!(detail["debug"]["kind"], json!("ERROR_KIND_DNS"));
+    let value = base64::engine::general_purpose::STANDARD_NO_PAD
+        .decode(detail["value"].as_str().unwrap())
+        .unwrap();
+    let error = Error::decode(value.as_slice()).expect("Invalid error detail");
+    assert_eq!(error.internal_error_code, -105);
+
+    let resp = client
+        .post(CONNECT_URL)
+        .json(&json!({ "requestId": "connect-missing-target" }))
+        .send()
+        .await
+        .expect("Failed to send request");
+    assert_eq!(resp.status(), 400);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["code"], json!("invalid_argument"));
+
+    let resp = client
+        .post(CONNECT_URL)
+        .header("Content-Type", "text/plain")
+        .body("hello")
+        .send()
+        .await
+        .expect("Failed to send request");
+    assert_eq!(resp.status(), 415);
+}
+
+/// Connect JSON clients send enums by name, the proxy type included.
+#[tokio::test]
+async fn test_connect_json_enum_names() {
+    let client = Client::new();
+    let resp = client
+        .post(CONNECT_URL)
+        .json(&json!({
+            "requestId": "connect-proxy-type",
+            "target": { "url": "https://httpbin.org/get", "method": "GET" },
+            "config": {
+                "priority": "PRIORITY_HIGHEST",
+                "proxy": { "type": "SOCKS5", "host": "127.0.0.1", "port": 1 }
+            }
+        }))
+        .send()
+        .await
+        .expect("Failed to send request");
+    let body: serde_json::Value = resp.json().await.unwrap();
+    // Parsed, so the request went out through the (closed) SOCKS5 proxy
+    assert_ne!(body["code"], json!("invalid_argument"), "Got: {}", body);
+    assert_eq!(
+        body["details"][0]["debug"]["kind"],
+        json!("ERROR_KIND_PROXY"),
+        "Got: {}",
+        body
+    );
+}
@@ -0,0 +1,332 @@
+use cronet_cloak::cookies::{
+    parse_cookie_date, parse_set_cookie, CookieJar, CookieJars, MAX_COOKIES, MAX_COOKIES_PER_DOMAIN,
+};
+use reqwest::Client;
+use serde_json::json;
+use std::time::Duration;
+use url::Url;
+
+mod common;
+use common::execute;
+
+const NOW: i64 = 1_700_000_000;
+
+fn url(s: &str) -> Url {
+    Url::parse(s).unwrap()
+}
+
+#[test]
+fn test_cookie_dates() {
+    assert_eq!(
+        parse_cookie_date("Sun, 06 Nov 1994 08:49:37 GMT"),
+        Some(784111777)
+    );
+    // RFC 850 and asctime forms
+    assert_eq!(
+        parse_cookie_date("Sunday, 06-Nov-94 08:49:37 GMT"),
+        Some(784111777)
+    );
+    assert_eq!(
+        parse_cookie_date("Sun Nov  6 08:49:37 1994"),
+        Some(784111777)
+    );
+    assert_eq!(parse_cookie_date("Wed, 31 Foo 2025 10:00:00 GMT"), None);
+    assert_eq!(parse_cookie_date("not a date"), None);
+}
+
+#[test]
+fn test_set_cookie_attributes() {
+    let origin = url("https://www.example.com/account/login");
+
+    let cookie = parse_set_cookie(&origin, "sid=abc; Path=/; Secure; HttpOnly", NOW).unwrap();
+    assert_eq!(cookie.domain, "www.example.com");
+    assert!(cookie.host_only && cookie.secure && cookie.http_only);
+    assert_eq!(cookie.expires, None);
+
+    let cookie = parse_set_cookie(&origin, "a=1; Domain=.Example.com; Max-Age=60", NOW).unwrap();
+    assert_eq!(cookie.domain, "example.com");
+    assert!(!cookie.host_only);
+    assert_eq!(cookie.expires, Some(NOW + 60));
+    // Default path is the directory of the request path
+    assert_eq!(cookie.path, "/account");
+
+    // Max-Age wins over Expires
+    let cookie = parse_set_cookie(
+        &origin,
+        "a=1; Max-Age=10; Expires=Sun, 06 Nov 1994 08:49:37 GMT",
+        NOW,
+    )
+    .unwrap();
+    assert_eq!(cookie.expires, Some(NOW + 10));
+
+    // Foreign domains, public suffixes and no name are refused
+    assert!(parse_set_cookie(&origin, "a=1; Domain=other.com", NOW).is_none());
+    assert!(parse_set_cookie(&origin, "a=1; Domain=com", NOW).is_none());
+    assert!(parse_set_cookie(&origin, "=1", NOW).is_none());
+    // Secure cookies need a secure origin
+    assert!(parse_set_cookie(&url("http://example.com/"), "a=1; Secure", NOW).is_none());
+}
+
+#[test]
+fn test_jar_matching() {
+    let mut jar = CookieJar::default();
+    let origin = url("https://example.com/app/page");
+    assert!(jar.store_header(&origin, "root=1; Path=/"));
+    assert!(jar.store_header(&origin, "app=2; Path=/app"));
+    assert!(jar.store_header(&origin, "shared=3; Domain=example.com; Path=/"));
+    assert!(jar.store_header(&origin, "tls=4; Path=/; Secure"));
+
+    assert_eq!(
+        jar.header_for(&url("https://example.com/app/x")).as_deref(),
+        Some("app=2; root=1; shared=3; tls=4")
+    );
+    assert_eq!(
+        jar.header_for(&url("http://sub.example.com/")).as_deref(),
+        Some("shared=3")
+    );
+    // /application does not path-match /app
+    assert_eq!(
+        jar.header_for(&url("http://example.com/application"))
+            .as_deref(),
+        Some("root=1; shared=3")
+    );
+    assert_eq!(jar.header_for(&url("https://other.com/")), None);
+
+    // Same name, domain and path replaces; an expired one deletes
+    assert!(jar.store_header(&origin, "root=5; Path=/"));
+    assert_eq!(jar.len(), 4);
+    assert!(jar.store_header(&origin, "root=; Path=/; Max-Age=0"));
+    assert_eq!(jar.len(), 3);
+
+    assert_eq!(jar.remove(Some("app"), None, None), 1);
+    assert_eq!(jar.list(Some("example.com")).len(), 2);
+}
+
+#[test]
+fn test_netscape_round_trip() {
+    let mut jar = CookieJar::default();
+    let origin = url("https://example.com/");
+    jar.store_header(&origin, "a=1; Path=/; HttpOnly; Max-Age=3600");
+    jar.store_header(&origin, "b=2; Domain=example.com; Path=/; Secure");
+
+    let text = jar.to_netscape();
+    assert!(text.starts_with("# Netscape HTTP Cookie File\n"));
+    assert!(text.contains("#HttpOnly_example.com\tFALSE\t/\tFALSE\t"));
+    assert!(text.contains(".example.com\tTRUE\t/\tTRUE\t0\tb\t2\n"));
+
+    let mut imported = CookieJar::default();
+    assert_eq!(imported.import_netscape(&text), Ok(2));
+    assert_eq!(imported.list(None), jar.list(None));
+
+    assert!(imported.import_netscape("example.com\tFALSE\t/\n").is_err());
+}
+
+/// Jars are bounded in number and dropped once idle, and looking one up or
+/// deleting from it does not create it.
+#[test]
+fn test_jar_limits() {
+    let origin = url("https://example.com/");
+    let jars = CookieJars::new(2, Duration::from_secs(3600));
+    for session in ["a", "b", "c"] {
+        jars.with(session, |jar| jar.store_header(&origin, "x=1"));
+    }
+    assert_eq!(jars.len(), 2);
+    assert!(jars.get("a").is_none(), "least recently used jar kept");
+    assert!(jars.with_existing("missing", |jar| jar.len()).is_none());
+    assert!(jars.get("missing").is_none());
+
+    let jars = CookieJars::new(2, Duration::ZERO);
+    jars.with("a", |jar| jar.store_header(&origin, "x=1"));
+    assert_eq!(jars.evict_idle(), 1);
+    assert!(jars.is_empty());
+}
+
+/// Full jars and domains drop their oldest cookies first.
+#[test]
+fn test_cookie_limits() {
+    let mut jar = CookieJar::default();
+    let origin = url("https://example.com/");
+    for i in 0..MAX_COOKIES_PER_DOMAIN + 2 {
+        jar.store_header(&origin, &format!("c{}=1", i));
+    }
+    let cookies = jar.list(None);
+    assert_eq!(cookies.len(), MAX_COOKIES_PER_DOMAIN);
+    assert_eq!(cookies[0].name, "c2");
+    assert_eq!(
+        cookies.last().unwrap().name,
+        format!("c{}", MAX_COOKIES_PER_DOMAIN + 1)
+    );
+    // Replacing a cookie keeps its place
+    jar.store_header(&origin, "c2=2");
+    jar.store_header(&origin, "new=1");
+    assert_eq!(jar.list(None)[0].name, "c3");
+
+    let mut jar = CookieJar::default();
+    let domains = MAX_COOKIES / MAX_COOKIES_PER_DOMAIN + 1;
+    for d in 0..domains {
+        let origin = url(&format!("https://d{}.example/", d));
+        for i in 0..MAX_COOKIES_PER_DOMAIN {
+            jar.store_header(&origin, &format!("c{}=1", i));
+        }
+    }
+    let cookies = jar.list(None);
+    assert_eq!(cookies.len(), MAX_COOKIES);
+    let evicted = domains * MAX_COOKIES_PER_DOMAIN - MAX_COOKIES;
+    assert_eq!(cookies[0].domain, "d0.example");
+    assert_eq!(cookies[0].name, format!("c{}", evicted));
+}
+
+fn body_json(result: &serde_json::Value) -> serde_json::Value {
+    let body = result["response"]["body"].as_str().unwrap();
+    serde_json::from_str(body).expect("Body is not JSON")
+}
+
+/// A cookie set on a redirect hop is sent to the next hop and kept for the
+/// jar's later requests.
+#[tokio::test]
+async fn test_jar_cookies() {
+    let client = Client::new();
+    let jar = "cookie-test-jar";
+    let cookies_url = format!("http://127.0.0.1:3000/api/v1/sessions/{}/cookies", jar);
+    client.delete(&cookies_url).send().await.unwrap();
+
+    let result = execute(
+        &client,
+        json!({
+            "request_id": "cookies-1",
+            "target": { "url": "https://httpbin.org/cookies/set?flavor=oat", "method": "GET" },
+            "config": { "follow_redirects": true, "cookie_jar": jar, "body_encoding": "utf8" }
+        }),
+    )
+    .await;
+    assert_eq!(result["success"], json!(true), "{}", result);
+    assert_eq!(
+        result["response"]["redirects"][0]["status_code"],
+        json!(302)
+    );
+    assert_eq!(body_json(&result)["cookies"]["flavor"], json!("oat"));
+
+    let result = execute(
+        &client,
+        json!({
+            "request_id": "cookies-2",
+            "target": {
+                "url": "https://httpbin.org/cookies",
+                "method": "GET",
+                "headers": { "Cookie": { "values": ["own=1"] } }
+            },
+            "config": { "cookie_jar": jar, "body_encoding": "utf8" }
+        }),
+    )
+    .await;
+    assert_eq!(result["success"], json!(true), "{}", result);
+    let cookies = &body_json(&result)["cookies"];
+    assert_eq!(cookies["flavor"], json!("oat"));
+    assert_eq!(cookies["own"], json!("1"));
+
+    let listed: serde_json::Value = client
+        .get(&cookies_url)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(listed[0]["name"], json!("flavor"));
+    assert_eq!(listed[0]["domain"], json!("httpbin.org"));
+
+    // Requests without the jar stay stateless
+    let result = execute(
+        &client,
+        json!({
+            "request_id": "cookies-3",
+            "target": { "url": "https://httpbin.org/cookies", "method": "GET" },
+            "config": { "body_encoding": "utf8" }
+        }),
+    )
+    .await;
+    assert_eq!(body_json(&result)["cookies"], json!({}));
+}
+
+/// A Cookie header set by the caller is not sent on to another origin.
+#[tokio::test]
+async fn test_caller_cookies_stay_on_origin() {
+    let client = Client::new();
+    let result = execute(
+        &client,
+        json!({
+            "request_id": "cookies-4",
+            "target": {
+                "url": "https://httpbin.org/redirect-to?url=http%3A%2F%2Fhttpbin.org%2Fcookies",
+                "method": "GET",
+                "headers": { "Cookie": { "values": ["own=1"] } }
+            },
+            "config": { "cookie_jar": "cookie-origin-jar", "body_encoding": "utf8" }
+        }),
+    )
+    .await;
+    assert_eq!(result["success"], json!(true), "{}", result);
+    assert_eq!(result["response"]["redirects"].as_array().unwrap().len(), 1);
+    assert_eq!(body_json(&result)["cookies"], json!({}));
+}
+
+/// Cookies can be set, exported, deleted and imported through the API.
+#[tokio::test]
+async fn test_cookie_api() {
+    let client = Client::new();
+    let cookies_url = "http://127.0.0.1:3000/api/v1/sessions/cookie-api-session/cookies";
+    client.delete(cookies_url).send().await.unwrap();
+
+    let resp = client
+        .post(cookies_url)
+        .json(&json!([{ "name": "token", "value": "t1", "domain": "httpbin.org" }]))
+        .send()
+        .await
+        .unwrap();
+    assert!(resp.status().is_success());
+
+    let exported = client
+        .get(format!("{}/export?format=netscape", cookies_url))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(exported.contains(".httpbin.org\tTRUE\t/\tFALSE\t0\ttoken\tt1"));
+
+    let removed: serde_json::Value = client
+        .delete(format!("{}?name=token", cookies_url))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(removed["count"], json!(1));
+
+    let resp = client
+        .post(format!("{}/import?format=netscape", cookies_url))
+        .body(exported)
+        .send()
+        .await
+        .unwrap();
+    assert!(resp.status().is_success());
+    let listed: serde_json::Value = client
+        .get(cookies_url)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(listed[0]["value"], json!("t1"));
+
+    let resp = client
+        .post(format!("{}/import?format=xml", cookies_url))
+        .body("")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 400);
+}
@@ -0,0 +1,158 @@
+use cronet_cloak::connect;
+use cronet_cloak::cronet::CronetError;
+use cronet_cloak::cronet_pb::ErrorKind;
+use cronet_cloak::errors::{cronet_kind, describe, net_error_kind, quic_error_name, RequestError};
+use reqwest::Client;
+use serde_json::json;
+
+mod common;
+use common::execute;
+
+#[test]
+fn test_net_error_kinds() {
+    assert_eq!(net_error_kind(-105), Some(ErrorKind::Dns));
+    assert_eq!(net_error_kind(-802), Some(ErrorKind::Dns));
+    assert_eq!(net_error_kind(-102), Some(ErrorKind::Connection));
+    assert_eq!(net_error_kind(-118), Some(ErrorKind::Connection));
+    assert_eq!(net_error_kind(-202), Some(ErrorKind::Tls));
+    assert_eq!(net_error_kind(-107), Some(ErrorKind::Tls));
+    assert_eq!(net_error_kind(-172), Some(ErrorKind::Tls));
+    assert_eq!(net_error_kind(-183), Some(ErrorKind::Tls));
+    // Unrelated codes between the TLS ones
+    assert_eq!(net_error_kind(-173), None);
+    assert_eq!(net_error_kind(-174), None);
+    assert_eq!(net_error_kind(-176), None);
+    assert_eq!(net_error_kind(-150), Some(ErrorKind::PinViolation));
+    assert_eq!(net_error_kind(-130), Some(ErrorKind::Proxy));
+    assert_eq!(net_error_kind(-7), Some(ErrorKind::Timeout));
+    assert_eq!(net_error_kind(-356), Some(ErrorKind::Quic));
+    assert_eq!(net_error_kind(-380), Some(ErrorKind::Quic));
+    assert_eq!(net_error_kind(-21), Some(ErrorKind::NetworkChanged));
+    assert_eq!(net_error_kind(-2), None);
+}
+
+/// Net errors not in the table fall back to Cronet's error category.
+#[test]
+fn test_cronet_kind_fallback() {
+    let error = |error_code, internal_error_code| CronetError {
+        error_code,
+        internal_error_code,
+        ..Default::default()
+    };
+    assert_eq!(cronet_kind(&error(11, -105)), ErrorKind::Dns);
+    assert_eq!(cronet_kind(&error(10, -2)), ErrorKind::Quic);
+    assert_eq!(cronet_kind(&error(8, -2)), ErrorKind::Connection);
+    assert_eq!(cronet_kind(&error(11, -2)), ErrorKind::Other);
+}
+
+/// QUIC error codes are reported by name, and make an otherwise unclassified
+/// failure a QUIC one.
+#[test]
+fn test_quic_error_details() {
+    assert_eq!(quic_error_name(0), "");
+    assert_eq!(quic_error_name(25), "QUIC_NETWORK_IDLE_TIMEOUT");
+    assert_eq!(quic_error_name(9999), "QUIC_ERROR_9999");
+
+    let failure = CronetError {
+        error_code: 11,
+        internal_error_code: -2,
+        quic_detailed_error_code: 25,
+        immediately_retryable: false,
+    };
+    assert_eq!(cronet_kind(&failure), ErrorKind::Quic);
+    let error = describe(&RequestError::cronet("net::ERR_FAILED", failure));
+    assert_eq!(error.kind, ErrorKind::Quic as i32);
+    assert_eq!(error.quic_error, "QUIC_NETWORK_IDLE_TIMEOUT");
+}
+
+/// Failures raised by the service keep the kind they were raised with,
+/// whatever their message says.
+#[test]
+fn test_service_error_kinds() {
+    let error = describe(&RequestError::new(ErrorKind::Timeout, "Canceled"));
+    assert_eq!(error.kind, ErrorKind::Timeout as i32);
+    let error = describe(&RequestError::invalid("Unknown profile: x"));
+    assert_eq!(error.kind, ErrorKind::InvalidRequest as i32);
+
+    assert_eq!(connect::error_code(ErrorKind::Timeout), "deadline_exceeded");
+    assert_eq!(connect::error_code(ErrorKind::Canceled), "canceled");
+    assert_eq!(
+        connect::error_code(ErrorKind::InvalidRequest),
+        "invalid_argument"
+    );
+    assert_eq!(connect::error_code(ErrorKind::NotCached), "not_found");
+    assert_eq!(connect::error_code(ErrorKind::Tls), "unavailable");
+
+    // Codes are only set for failures from the network stack
+    assert_eq!(error.internal_error_code, 0);
+    let error = describe(&RequestError::cronet(
+        "net::ERR_NAME_NOT_RESOLVED",
+        CronetError {
+            error_code: 1,
+            internal_error_code: -105,
+            quic_detailed_error_code: 0,
+            immediately_retryable: false,
+        },
+    ));
+    assert_eq!(error.kind, ErrorKind::Dns as i32);
+    assert_eq!(
+        serde_json::to_value(error).unwrap()["kind"],
+        json!("ERROR_KIND_DNS")
+    );
+}
+
+/// Network failures carry Cronet's codes along with their kind.
+#[tokio::test]
+async fn test_dns_failure() {
+    let client = Client::new();
+    let result = execute(
+        &client,
+        json!({
+            "request_id": "error-dns",
+            "target": { "url": "https://does-not-exist.invalid/", "method": "GET" }
+        }),
+    )
+    .await;
+    assert_eq!(result["success"], json!(false));
+    let error = &result["error"];
+    assert_eq!(error["kind"], json!("ERROR_KIND_DNS"), "{}", result);
+    assert_eq!(error["internal_error_code"], json!(-105));
+    assert_eq!(error["error_code"], json!(1));
+}
+
+/// Failures raised by the service have a kind but no codes.
+#[tokio::test]
+async fn test_service_failures() {
+    let client = Client::new();
+    let result = execute(
+        &client,
+        json!({
+            "request_id": "error-timeout",
+            "target": { "url": "https://httpbin.org/delay/5", "method": "GET" },
+            "config": { "timeout_ms": 200 }
+        }),
+    )
+    .await;
+    assert_eq!(
+        result["error"]["kind"],
+        json!("ERROR_KIND_TIMEOUT"),
+        "{}",
+        result
+    );
+    assert_eq!(result["error"]["internal_error_code"], json!(0));
+
+    let result = execute(&client, json!({ "request_id": "error-no-target" })).await;
+    assert_eq!(result["error"]["kind"], json!("ERROR_KIND_INVALID_REQUEST"));
+
+    // Successful responses have no error
+    let result = execute(
+        &client,
+        json!({
+            "request_id": "error-none",
+            "target": { "url": "https://httpbin.org/get", "method": "GET" }
+        }),
+    )
+    .await;
+    assert_eq!(result["success"], json!(true), "{}", result);
+    assert_eq!(result["error"], json!(null));
+}
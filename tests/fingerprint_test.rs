@@ -12,7 +12,7 @@ async fn execute_request(engine: &Arc<CronetEngine>, url: &str) -> serde_json::V
     };
     let config = ExecutionConfig::default();
 
-    let (_handle, rx) = engine.start_request(&target, &config);
+    let (_handle, rx) = engine.start_request(&target, &config).await;
     let result = rx.await.expect("Channel closed").expect("Request failed");
 
     // Parse body as string then JSON
@@ -0,0 +1,149 @@
+use cronet_cloak::cronet_pb::engine_service_client::EngineServiceClient;
+use cronet_cloak::cronet_pb::{
+    Error, ErrorKind, ExecuteBatchRequest, ExecuteRequest, TargetRequest,
+};
+use prost::Message;
+use tonic::{Code, Status};
+
+const SERVICE_ADDR: &str = "http://127.0.0.1:3000";
+
+fn get_request(request_id: &str, url: &str) -> ExecuteRequest {
+    ExecuteRequest {
+        request_id: request_id.to_string(),
+        target: Some(TargetRequest {
+            method: "GET".to_string(),
+            url: url.to_string(),
+            ..Default::default()
+        }),
+        config: None,
+    }
+}
+
+// `google.rpc.Status`, as sent in `grpc-status-details-bin`
+#[derive(Clone, PartialEq, Message)]
+struct RpcStatus {
+    #[prost(int32, tag = "1")]
+    code: i32,
+    #[prost(string, tag = "2")]
+    message: String,
+    #[prost(message, repeated, tag = "3")]
+    details: Vec<prost_types::Any>,
+}
+
+// The `Error` in the details of a failed call
+fn error_detail(status: &Status) -> Error {
+    let details = RpcStatus::decode(status.details()).expect("Invalid status details");
+    assert_eq!(details.code, status.code() as i32);
+    let any = &details.details[0];
+    assert_eq!(any.type_url, "type.googleapis.com/cronet.engine.v1.Error");
+    Error::decode(any.value.as_slice()).expect("Invalid error detail")
+}
+
+/// Execute over native gRPC returns the binary response body.
+#[tokio::test]
+async fn test_grpc_execute() {
+    let mut client = EngineServiceClient::connect(SERVICE_ADDR)
+        .await
+        .expect("Failed to connect");
+
+    let response = client
+        .execute(get_request("grpc-1", "https://httpbin.org/get"))
+        .await
+        .expect("Execute failed")
+        .into_inner();
+    assert!(response.success);
+
+    let target = response.response.unwrap();
+    assert_eq!(target.status_code, 200);
+    let body: serde_json::Value = serde_json::from_slice(&target.body).unwrap();
+    assert_eq!(body["url"], "https://httpbin.org/get");
+}
+
+/// Failures map to gRPC status codes.
+#[tokio::test]
+async fn test_grpc_errors() {
+    let mut client = EngineServiceClient::connect(SERVICE_ADDR)
+        .await
+        .expect("Failed to connect");
+
+    let status = client
+        .execute(ExecuteRequest::default())
+        .await
+        .expect_err("Missing target should fail");
+    assert_eq!(status.code(), Code::InvalidArgument);
+
+    let status = client
+        .execute(get_request("grpc-2", "https://nonexistent.invalid/"))
+        .await
+        .expect_err("Unknown host should fail");
+    assert_eq!(status.code(), Code::Unavailable);
+    let error = error_detail(&status);
+    assert_eq!(error.kind(), ErrorKind::Dns);
+    assert_eq!(error.internal_error_code, -105);
+
+    let mut request = tonic::Request::new(get_request("grpc-3", "https://httpbin.org/delay/10"));
+    request.set_timeout(std::time::Duration::from_secs(1));
+    let status = client.execute(request).await.expect_err("Should time out");
+    assert_eq!(status.code(), Code::DeadlineExceeded);
+}
+
+/// ExecuteStream sends the head first, then the body in chunks.
+#[tokio::test]
+async fn test_grpc_execute_stream() {
+    let mut client = EngineServiceClient::connect(SERVICE_ADDR)
+        .await
+        .expect("Failed to connect");
+
+    let mut stream = client
+        .execute_stream(get_request("grpc-4", "https://httpbin.org/bytes/50000"))
+        .await
+        .expect("ExecuteStream failed")
+        .into_inner();
+
+    let first = stream.message().await.unwrap().expect("Missing head");
+    assert_eq!(first.head.unwrap().status_code, 200);
+
+    let mut body_len = 0;
+    while let Some(message) = stream.message().await.expect("Stream failed") {
+        assert!(message.head.is_none());
+        body_len += message.chunk.len();
+    }
+    assert_eq!(body_len, 50000);
+}
+
+/// ExecuteBatch streams one response per request.
+#[tokio::test]
+async fn test_grpc_execute_batch() {
+    let mut client = EngineServiceClient::connect(SERVICE_ADDR)
+        .await
+        .expect("Failed to connect");
+
+    let batch = ExecuteBatchRequest {
+        requests: (0..4)
+            .map(|i| get_request(&format!("grpc-batch-{}", i), "https://httpbin.org/get"))
+            .collect(),
+        max_concurrency: 2,
+        fail_fast: false,
+    };
+    let mut stream = client
+        .execute_batch(batch)
+        .await
+        .expect("ExecuteBatch failed")
+        .into_inner();
+
+    let mut ids = Vec::new();
+    while let Some(response) = stream.message().await.expect("Stream failed") {
+        assert!(response.success, "{}", response.error_message);
+        ids.push(response.request_id);
+    }
+    ids.sort();
+    assert_eq!(
+        ids,
+        [
+            "grpc-batch-0",
+            "grpc-batch-1",
+            "grpc-batch-2",
+            "grpc-batch-3"
+        ]
+    );
+}
@@ -0,0 +1,98 @@
+use reqwest::Client;
+use serde_json::json;
+
+const SERVICE_URL: &str = "http://127.0.0.1:3000/api/v1/execute";
+
+/// Response headers are returned, with repeated headers kept as multiple values.
+#[tokio::test]
+async fn test_response_headers() {
+    let client = Client::new();
+
+    let payload = json!({
+        "request_id": "headers-test",
+        "target": {
+            "url": "https://httpbin.org/response-headers?Set-Cookie=a%3D1&Set-Cookie=b%3D2",
+            "method": "GET"
+        }
+    });
+
+    let resp = client
+        .post(SERVICE_URL)
+        .json(&payload)
+        .send()
+        .await
+        .expect("Failed to send request");
+    let body: serde_json::Value = resp.json().await.expect("Failed to parse JSON response");
+    assert_eq!(body["success"], json!(true), "Request failed: {}", body);
+
+    let headers = body["response"]["headers"]
+        .as_object()
+        .expect("Missing headers");
+    println!("Headers: {:?}", headers.keys());
+
+    let content_type = headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case("content-type"))
+        .expect("Missing Content-Type");
+    assert!(content_type.1["values"][0]
+        .as_str()
+        .unwrap()
+        .contains("application/json"));
+
+    let cookies = headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case("set-cookie"))
+        .map(|(_, v)| v["values"].as_array().unwrap().clone())
+        .expect("Missing Set-Cookie");
+    assert_eq!(cookies, vec![json!("a=1"), json!("b=2")]);
+
+    // The ordered list carries the same headers
+    let header_list = body["response"]["header_list"].as_array().unwrap();
+    let total: usize = headers
+        .values()
+        .map(|v| v["values"].as_array().unwrap().len())
+        .sum();
+    assert_eq!(header_list.len(), total);
+}
+
+/// Final URL, URL chain and connection metadata are reported.
+#[tokio::test]
+async fn test_response_metadata() {
+    let client = Client::new();
+
+    let payload = json!({
+        "request_id": "metadata-test",
+        "target": {
+            "url": "https://httpbin.org/redirect/1",
+            "method": "GET"
+        },
+        "config": {
+            "follow_redirects": true
+        }
+    });
+
+    let resp = client
+        .post(SERVICE_URL)
+        .json(&payload)
+        .send()
+        .await
+        .expect("Failed to send request");
+    let body: serde_json::Value = resp.json().await.expect("Failed to parse JSON response");
+    assert_eq!(body["success"], json!(true), "Request failed: {}", body);
+
+    let response = &body["response"];
+    println!(
+        "url={} protocol={} proxy={} cached={} bytes={}",
+        response["url"],
+        response["negotiated_protocol"],
+        response["proxy_server"],
+        response["was_cached"],
+        response["received_byte_count"]
+    );
+
+    assert_eq!(response["url"], json!("https://httpbin.org/get"));
+    assert_eq!(response["url_chain"].as_array().unwrap().len(), 2);
+    assert_eq!(response["status_text"], json!("OK"));
+    assert!(!response["negotiated_protocol"].as_str().unwrap().is_empty());
+    assert!(response["received_byte_count"].as_i64().unwrap() > 0);
+}
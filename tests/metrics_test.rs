@@ -0,0 +1,93 @@
+use reqwest::Client;
+use serde_json::json;
+use std::time::Duration;
+
+const SERVICE_URL: &str = "http://127.0.0.1:3000/api/v1/execute";
+const METRICS_URL: &str = "http://127.0.0.1:3000/metrics";
+
+async fn counter(client: &Client, name: &str) -> u64 {
+    let text = client
+        .get(METRICS_URL)
+        .send()
+        .await
+        .expect("Failed to fetch metrics")
+        .text()
+        .await
+        .expect("Failed to read metrics");
+    text.lines()
+        .find_map(|line| line.strip_prefix(name)?.strip_prefix(' '))
+        .and_then(|value| value.trim().parse().ok())
+        .expect("Counter missing from metrics")
+}
+
+/// A client that gives up on a slow request gets the upstream request
+/// cancelled, which shows up in the abandoned counter.
+#[tokio::test]
+async fn test_disconnect_cancels_request() {
+    let client = Client::new();
+    let before = counter(&client, "cronet_cloak_requests_abandoned_total").await;
+
+    let payload = json!({
+        "request_id": "abandoned-1",
+        "target": {
+            "url": "https://httpbin.org/delay/10",
+            "method": "GET"
+        }
+    });
+    let result = client
+        .post(SERVICE_URL)
+        .timeout(Duration::from_secs(1))
+        .json(&payload)
+        .send()
+        .await;
+    assert!(result.is_err(), "Request should have timed out client-side");
+
+    // Give the server a moment to notice the disconnect
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    let after = counter(&client, "cronet_cloak_requests_abandoned_total").await;
+    assert!(
+        after > before,
+        "Expected abandoned counter to grow: {} -> {}",
+        before,
+        after
+    );
+}
+
+async fn protocol_counts(client: &Client) -> u64 {
+    counter(client, "cronet_cloak_responses_http3_total").await
+        + counter(client, "cronet_cloak_responses_tcp_total").await
+}
+
+/// Every network response on the QUIC-enabled default engine is counted as
+/// either HTTP/3 or TCP.
+#[tokio::test]
+async fn test_protocol_counters() {
+    let client = Client::new();
+    let before = protocol_counts(&client).await;
+
+    let payload = json!({
+        "request_id": "protocol-1",
+        "target": {
+            "url": "https://www.google.com/generate_204",
+            "method": "GET"
+        }
+    });
+    let resp: serde_json::Value = client
+        .post(SERVICE_URL)
+        .json(&payload)
+        .send()
+        .await
+        .expect("Failed to send request")
+        .json()
+        .await
+        .expect("Failed to parse response");
+    assert!(resp["success"].as_bool().unwrap_or(false), "{}", resp);
+
+    let after = protocol_counts(&client).await;
+    assert!(
+        after > before,
+        "Expected protocol counters to grow: {} -> {}",
+        before,
+        after
+    );
+}
@@ -0,0 +1,160 @@
+use reqwest::Client;
+use serde_json::json;
+
+mod common;
+use common::execute;
+
+const NETLOG_URL: &str = "http://127.0.0.1:3000/admin/netlog";
+
+/// A capture of the default engine can be started, stopped, listed,
+/// downloaded and deleted.
+#[tokio::test]
+async fn test_netlog_capture() {
+    let client = Client::new();
+    // Left over from an interrupted run
+    client
+        .post(format!("{}/stop", NETLOG_URL))
+        .send()
+        .await
+        .unwrap();
+
+    let resp = client
+        .post(format!("{}/start?log_all=true", NETLOG_URL))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 201);
+    let started: serde_json::Value = resp.json().await.unwrap();
+    let name = started["name"].as_str().unwrap().to_string();
+    assert_eq!(started["capturing"], json!(""));
+    assert_eq!(started["log_all"], json!(true));
+
+    // One capture per engine
+    let resp = client
+        .post(format!("{}/start", NETLOG_URL))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 409);
+
+    let result = execute(
+        &client,
+        json!({
+            "request_id": "netlog-1",
+            "target": { "url": "https://httpbin.org/get", "method": "GET" }
+        }),
+    )
+    .await;
+    assert_eq!(result["success"], json!(true), "{}", result);
+
+    let resp = client
+        .post(format!("{}/stop?profile=default", NETLOG_URL))
+        .send()
+        .await
+        .unwrap();
+    assert!(resp.status().is_success());
+    let stopped: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(stopped["name"], json!(name));
+    assert_eq!(stopped["capturing"], json!(null));
+
+    let listed: serde_json::Value = client
+        .get(NETLOG_URL)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert!(listed
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|file| file["name"] == json!(name)));
+
+    let log: serde_json::Value = client
+        .get(format!("{}/{}", NETLOG_URL, name))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .expect("NetLog is not JSON");
+    assert!(log["constants"].is_object());
+    assert!(log["events"]
+        .as_array()
+        .is_some_and(|events| !events.is_empty()));
+
+    let resp = client
+        .delete(format!("{}/{}", NETLOG_URL, name))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 204);
+    let resp = client
+        .get(format!("{}/{}", NETLOG_URL, name))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 404);
+}
+
+/// Unknown profiles and file names outside the directory are refused.
+#[tokio::test]
+async fn test_netlog_errors() {
+    let client = Client::new();
+    let resp = client
+        .post(format!("{}/start?profile=does-not-exist", NETLOG_URL))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 404);
+
+    let resp = client
+        .get(format!("{}/..%2Fpasswd", NETLOG_URL))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 404);
+}
+
+/// A request with `capture_netlog` gets its own log back, failed or not.
+#[tokio::test]
+async fn test_request_netlog() {
+    let client = Client::new();
+    let result = execute(
+        &client,
+        json!({
+            "request_id": "netlog-request-1",
+            "target": { "url": "https://httpbin.org/get", "method": "GET" },
+            "config": { "capture_netlog": true }
+        }),
+    )
+    .await;
+    assert_eq!(result["success"], json!(true), "{}", result);
+    let log: serde_json::Value =
+        serde_json::from_str(result["netlog"].as_str().unwrap()).expect("NetLog is not JSON");
+    assert!(log["events"].is_array());
+
+    let result = execute(
+        &client,
+        json!({
+            "request_id": "netlog-request-2",
+            "target": { "url": "https://does-not-exist.invalid/", "method": "GET" },
+            "config": { "captureNetlog": true }
+        }),
+    )
+    .await;
+    assert_eq!(result["success"], json!(false));
+    assert!(!result["netlog"].as_str().unwrap().is_empty());
+
+    // Not captured unless asked for
+    let result = execute(
+        &client,
+        json!({
+            "request_id": "netlog-request-3",
+            "target": { "url": "https://httpbin.org/get", "method": "GET" }
+        }),
+    )
+    .await;
+    assert!(result.get("netlog").is_none());
+}
@@ -0,0 +1,133 @@
+use cronet_cloak::cronet_pb::proxy_config::ProxyType;
+use cronet_cloak::cronet_pb::{
+    BodyEncoding, CachePolicy, ErrorKind, ExecutionConfig, Idempotency, RequestPriority,
+};
+use cronet_cloak::scheduler::Scheduler;
+use reqwest::Client;
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Enum fields are accepted by number, proto name or short name.
+#[test]
+fn test_config_names() {
+    let config: ExecutionConfig =
+        serde_json::from_value(json!({ "priority": "highest", "idempotency": "not-idempotent" }))
+            .unwrap();
+    assert_eq!(config.priority(), RequestPriority::PriorityHighest);
+    assert_eq!(config.idempotency(), Idempotency::NotIdempotent);
+
+    let config: ExecutionConfig =
+        serde_json::from_value(json!({ "priority": "PRIORITY_IDLE", "idempotency": 1 })).unwrap();
+    assert_eq!(config.priority(), RequestPriority::PriorityIdle);
+    assert_eq!(config.idempotency(), Idempotency::Idempotent);
+
+    let error = serde_json::from_value::<ExecutionConfig>(json!({ "priority": "urgent" }))
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("unknown priority: urgent"), "Got: {}", error);
+
+    let config: ExecutionConfig = serde_json::from_value(json!({
+        "cache": "only-if-cached",
+        "body_encoding": "utf-8",
+        "retry": { "retry_on_errors": ["dns", "ERROR_KIND_TLS", 2] }
+    }))
+    .unwrap();
+    assert_eq!(config.cache(), CachePolicy::CacheOnlyIfCached);
+    assert_eq!(config.body_encoding(), BodyEncoding::Utf8);
+    let retry = config.retry.unwrap();
+    assert_eq!(
+        retry.retry_on_errors,
+        [ErrorKind::Dns, ErrorKind::Tls, ErrorKind::Connection].map(|kind| kind as i32)
+    );
+    assert_eq!(
+        serde_json::to_value(&retry).unwrap()["retry_on_errors"],
+        json!(["ERROR_KIND_DNS", "ERROR_KIND_TLS", "ERROR_KIND_CONNECTION"])
+    );
+
+    let config: ExecutionConfig =
+        serde_json::from_value(json!({ "proxy": { "type": "SOCKS5", "host": "127.0.0.1" } }))
+            .unwrap();
+    assert_eq!(config.proxy.unwrap().r#type(), ProxyType::Socks5);
+}
+
+// Waits until `count` requests are queued
+async fn queued(scheduler: &Scheduler, count: usize) {
+    while scheduler.waiting() < count {
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+}
+
+/// Freed slots go to the highest priority waiting, then the earliest.
+#[tokio::test]
+async fn test_scheduler_order() {
+    let scheduler = Scheduler::new(1);
+    let first = scheduler.acquire(RequestPriority::PriorityDefault).await;
+
+    let order = Arc::new(Mutex::new(Vec::new()));
+    let mut tasks = Vec::new();
+    for (name, priority) in [
+        ("low", RequestPriority::PriorityLow),
+        ("medium-1", RequestPriority::PriorityMedium),
+        ("highest", RequestPriority::PriorityHighest),
+        ("medium-2", RequestPriority::PriorityDefault),
+        ("idle", RequestPriority::PriorityIdle),
+    ] {
+        let waiter = scheduler.clone();
+        let order = order.clone();
+        tasks.push(tokio::spawn(async move {
+            let _permit = waiter.acquire(priority).await;
+            order.lock().unwrap().push(name);
+        }));
+        queued(&scheduler, tasks.len()).await;
+    }
+
+    drop(first);
+    for task in tasks {
+        task.await.unwrap();
+    }
+    assert_eq!(
+        *order.lock().unwrap(),
+        ["highest", "medium-1", "medium-2", "low", "idle"]
+    );
+}
+
+/// A request that gives up waiting does not take a slot with it.
+#[tokio::test]
+async fn test_scheduler_abandoned_wait() {
+    let scheduler = Scheduler::new(1);
+    let first = scheduler.acquire(RequestPriority::PriorityDefault).await;
+
+    let waiting = tokio::time::timeout(
+        Duration::from_millis(20),
+        scheduler.acquire(RequestPriority::PriorityHighest),
+    )
+    .await;
+    assert!(waiting.is_err());
+
+    drop(first);
+    let again = tokio::time::timeout(
+        Duration::from_secs(1),
+        scheduler.acquire(RequestPriority::PriorityLow),
+    )
+    .await;
+    assert!(again.is_ok());
+}
+
+/// Requests with a priority and idempotency run as usual.
+#[tokio::test]
+async fn test_request_priority() {
+    let client = Client::new();
+    let resp = client
+        .post("http://127.0.0.1:3000/api/v1/execute")
+        .json(&json!({
+            "request_id": "priority-1",
+            "target": { "url": "https://httpbin.org/get", "method": "GET" },
+            "config": { "priority": "highest", "idempotency": "idempotent" }
+        }))
+        .send()
+        .await
+        .expect("Failed to send request");
+    let result: serde_json::Value = resp.json().await.expect("Failed to parse JSON");
+    assert_eq!(result["success"], json!(true), "{}", result);
+}
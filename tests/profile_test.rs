@@ -0,0 +1,121 @@
+use cronet_cloak::config::{Config, EngineSettings};
+use cronet_cloak::cronet::CronetEngine;
+use cronet_cloak::cronet_pb::ExecuteRequest;
+use cronet_cloak::service::{self, AppState, Profile};
+use reqwest::Client;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Requests naming a profile the server does not define are rejected.
+#[tokio::test]
+async fn test_unknown_profile() {
+    let client = Client::new();
+    let payload = json!({
+        "request_id": "profile-1",
+        "target": { "url": "https://httpbin.org/get", "method": "GET" },
+        "config": { "profile": "does-not-exist" }
+    });
+
+    let resp = client
+        .post("http://127.0.0.1:3000/api/v1/execute")
+        .json(&payload)
+        .send()
+        .await
+        .expect("Failed to send request");
+    let result: serde_json::Value = resp.json().await.expect("Failed to parse JSON");
+    assert_eq!(result["success"], json!(false));
+    assert_eq!(
+        result["error_message"],
+        json!("Unknown profile: does-not-exist")
+    );
+
+    let resp = client
+        .post("http://127.0.0.1:3000/cronet.engine.v1.EngineService/Execute")
+        .json(&payload)
+        .send()
+        .await
+        .expect("Failed to send request");
+    assert_eq!(resp.status(), 400);
+    let error: serde_json::Value = resp.json().await.expect("Failed to parse JSON");
+    assert_eq!(error["code"], json!("invalid_argument"));
+}
+
+/// An empty profile selects the default engine.
+#[tokio::test]
+async fn test_default_profile() {
+    let client = Client::new();
+    let payload = json!({
+        "request_id": "profile-2",
+        "target": { "url": "https://httpbin.org/get", "method": "GET" },
+        "config": { "profile": "" }
+    });
+
+    let resp = client
+        .post("http://127.0.0.1:3000/api/v1/execute")
+        .json(&payload)
+        .send()
+        .await
+        .expect("Failed to send request");
+    let result: serde_json::Value = resp.json().await.expect("Failed to parse JSON");
+    assert_eq!(result["success"], json!(true), "{}", result);
+}
+
+/// A configured profile runs on its own engine, here one with another
+/// User-Agent, and adds its headers to the request.
+#[tokio::test]
+async fn test_configured_profile() {
+    let config = Config::from_toml(
+        r#"
+[engine]
+user_agent = "ProfileTest/default"
+
+[profiles.custom.engine]
+user_agent = "ProfileTest/custom"
+
+[profiles.custom.headers]
+X-Profile = "custom"
+"#,
+    )
+    .expect("Invalid config");
+    let start = |settings: &EngineSettings| {
+        Arc::new(CronetEngine::with_config(settings.engine_config()).expect("Engine failed"))
+    };
+    let profiles: HashMap<String, Profile> = config
+        .profiles
+        .iter()
+        .map(|(name, profile)| {
+            let profile = Profile {
+                engine: start(&profile.engine),
+                headers: profile.headers.clone().into_iter().collect(),
+            };
+            (name.clone(), profile)
+        })
+        .collect();
+    let state = AppState::new(start(&config.engine), &config.limits).with_profiles(profiles);
+
+    let headers_seen = |profile: &str| {
+        let request: ExecuteRequest = serde_json::from_value(json!({
+            "request_id": "profile-3",
+            "target": { "url": "https://httpbin.org/headers", "method": "GET" },
+            "config": { "profile": profile }
+        }))
+        .unwrap();
+        let state = state.clone();
+        async move {
+            let response = service::execute(&state, request).await;
+            assert!(response.success, "{}", response.error_message);
+            let body = response.response.unwrap().body;
+            let echoed: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            echoed["headers"].clone()
+        }
+    };
+
+    let custom = headers_seen("custom").await;
+    assert_eq!(custom["User-Agent"], json!("ProfileTest/custom"));
+    assert_eq!(custom["X-Profile"], json!("custom"));
+
+    let default = headers_seen("").await;
+    assert_eq!(default["User-Agent"], json!("ProfileTest/default"));
+    assert!(default.get("X-Profile").is_none());
+}
@@ -256,3 +256,25 @@ async fn test_no_proxy_direct() {
     assert!(success, "Direct connection should succeed");
     println!("Direct (no proxy) Test PASSED");
 }
+
+/// Requests through the same proxy share a pooled engine, so the second one
+/// reuses the first one's connection.
+#[tokio::test]
+async fn test_proxy_engine_reuse() {
+    let client = Client::new();
+
+    let first = make_proxy_request(&client, "SOCKS5", "127.0.0.1", 1080, None, None).await;
+    // Same proxy, spelled differently: must map to the same engine
+    let second = make_proxy_request(&client, "SOCKS5", "127.0.0.1 ", 1080, None, None).await;
+
+    let succeeded = |result: &serde_json::Value| result["success"].as_bool().unwrap_or(false);
+    if succeeded(&first) && succeeded(&second) {
+        assert_eq!(second["timing"]["socket_reused"], json!(true));
+    } else {
+        // No local SOCKS5 proxy: both fail the same way without crashing
+        println!(
+            "Proxy reuse test skipped: {} / {}",
+            first["error_message"], second["error_message"]
+        );
+    }
+}
@@ -0,0 +1,33 @@
+use cronet_cloak::config::Config;
+use cronet_cloak::cronet::CronetEngine;
+use cronet_cloak::cronet_pb::{ExecutionConfig, TargetRequest};
+
+/// With `quic_mode = "force"` a hinted origin is reached over HTTP/3 from
+/// the first request, with no Alt-Svc round trip.
+#[tokio::test]
+async fn test_forced_quic_negotiates_h3() {
+    let config = Config::from_toml(
+        r#"
+[engine]
+quic_mode = "force"
+
+[[engine.quic_hints]]
+host = "cloudflare-quic.com"
+"#,
+    )
+    .expect("Invalid config");
+    config.validate().expect("Config invalid");
+    let engine = CronetEngine::with_config(config.engine.engine_config()).expect("Engine failed");
+
+    let target = TargetRequest {
+        url: "https://cloudflare-quic.com/".to_string(),
+        method: "GET".to_string(),
+        ..Default::default()
+    };
+    let (_request, rx) = engine
+        .start_request(&target, &ExecutionConfig::default())
+        .await;
+    let result = rx.await.expect("Channel closed").expect("Request failed");
+    assert_eq!(result.status_code, 200);
+    assert_eq!(result.negotiated_protocol, "h3");
+}
@@ -0,0 +1,105 @@
+use reqwest::Client;
+use serde_json::json;
+
+mod common;
+
+async fn get(client: &Client, url: &str, config: serde_json::Value) -> serde_json::Value {
+    let payload = json!({
+        "request_id": "redirect-test",
+        "target": {
+            "url": url,
+            "method": "GET"
+        },
+        "config": config
+    });
+    common::execute(client, payload).await
+}
+
+/// Every followed hop is recorded with its URL, status and Location.
+#[tokio::test]
+async fn test_redirect_hops_recorded() {
+    let client = Client::new();
+    let body = get(
+        &client,
+        "https://httpbin.org/redirect/3",
+        json!({ "follow_redirects": true }),
+    )
+    .await;
+
+    assert_eq!(body["success"], json!(true), "Request failed: {}", body);
+    assert_eq!(body["response"]["status_code"], json!(200));
+
+    let hops = body["response"]["redirects"]
+        .as_array()
+        .expect("Missing redirects");
+    assert_eq!(hops.len(), 3);
+    for hop in hops {
+        println!(
+            "{} -> {} ({})",
+            hop["url"], hop["location"], hop["status_code"]
+        );
+        assert_eq!(hop["status_code"], json!(302));
+    }
+}
+
+/// Redirects are followed when `follow_redirects` is left unset.
+#[tokio::test]
+async fn test_redirect_followed_by_default() {
+    let client = Client::new();
+    let body = get(&client, "https://httpbin.org/redirect/1", json!({})).await;
+
+    assert_eq!(body["success"], json!(true), "Request failed: {}", body);
+    assert_eq!(body["response"]["status_code"], json!(200));
+    assert_eq!(body["response"]["redirects"].as_array().unwrap().len(), 1);
+}
+
+/// With `follow_redirects` off the 3xx response itself is returned.
+#[tokio::test]
+async fn test_redirect_not_followed() {
+    let client = Client::new();
+    let body = get(
+        &client,
+        "https://httpbin.org/redirect/1",
+        json!({ "follow_redirects": false }),
+    )
+    .await;
+
+    assert_eq!(body["success"], json!(true), "Request failed: {}", body);
+    assert_eq!(body["response"]["status_code"], json!(302));
+
+    let headers = body["response"]["headers"].as_object().unwrap();
+    let has_location = headers.keys().any(|k| k.eq_ignore_ascii_case("location"));
+    assert!(has_location, "Missing Location header: {:?}", headers);
+}
+
+/// Exceeding `max_redirects` fails the request.
+#[tokio::test]
+async fn test_max_redirects() {
+    let client = Client::new();
+    let body = get(
+        &client,
+        "https://httpbin.org/redirect/5",
+        json!({ "follow_redirects": true, "max_redirects": 2 }),
+    )
+    .await;
+
+    assert_eq!(body["success"], json!(false));
+    let error = body["error_message"].as_str().unwrap_or_default();
+    assert!(error.contains("Too many redirects"), "Got: {}", error);
+}
+
+/// Cross-origin redirects are refused when `deny_cross_origin_redirects` is set.
+#[tokio::test]
+async fn test_cross_origin_redirect_refused() {
+    let client = Client::new();
+    let body = get(
+        &client,
+        "https://httpbin.org/redirect-to?url=https%3A%2F%2Fexample.com%2F",
+        json!({ "follow_redirects": true, "deny_cross_origin_redirects": true }),
+    )
+    .await;
+
+    assert_eq!(body["success"], json!(false));
+    let error = body["error_message"].as_str().unwrap_or_default();
+    assert!(error.contains("cross-origin"), "Got: {}", error);
+}
@@ -0,0 +1,225 @@
+use cronet_cloak::cronet::CronetError;
+use cronet_cloak::cronet_pb::{
+    ErrorKind, ExecutionConfig, Idempotency, RetryPolicy as RetryConfig,
+};
+use cronet_cloak::retry::{is_idempotent, retry_after, RetryPolicy, MAX_ATTEMPTS, MAX_BACKOFF};
+use reqwest::Client;
+use serde_json::json;
+use std::time::Duration;
+
+mod common;
+use common::execute;
+
+fn config(retry: RetryConfig) -> ExecutionConfig {
+    ExecutionConfig {
+        retry: Some(retry),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_retry_after() {
+    assert_eq!(retry_after("120", 0), Some(Duration::from_secs(120)));
+    assert_eq!(
+        retry_after("Wed, 21 Oct 2015 07:28:00 GMT", 1445412470),
+        Some(Duration::from_secs(10))
+    );
+    // Dates in the past mean now
+    assert_eq!(
+        retry_after("Wed, 21 Oct 2015 07:28:00 GMT", 1445412490),
+        Some(Duration::ZERO)
+    );
+    assert_eq!(retry_after("soon", 0), None);
+}
+
+/// Only idempotent methods are retried unless the policy opts in.
+#[test]
+fn test_idempotency() {
+    assert!(is_idempotent("get"));
+    assert!(is_idempotent("PUT"));
+    assert!(!is_idempotent("POST"));
+    assert!(!is_idempotent("PATCH"));
+
+    let retry = RetryConfig {
+        max_attempts: 3,
+        ..Default::default()
+    };
+    assert!(RetryPolicy::from_config(&config(retry.clone()), "GET").is_some());
+    assert!(RetryPolicy::from_config(&config(retry.clone()), "POST").is_none());
+    let opted_in = RetryConfig {
+        retry_non_idempotent: true,
+        ..retry.clone()
+    };
+    assert!(RetryPolicy::from_config(&config(opted_in), "POST").is_some());
+
+    // The request's idempotency overrides its method
+    let marked = |idempotency: Idempotency| ExecutionConfig {
+        idempotency: idempotency as i32,
+        ..config(retry.clone())
+    };
+    assert!(RetryPolicy::from_config(&marked(Idempotency::Idempotent), "POST").is_some());
+    assert!(RetryPolicy::from_config(&marked(Idempotency::NotIdempotent), "GET").is_none());
+
+    let single = RetryConfig {
+        max_attempts: 1,
+        ..Default::default()
+    };
+    assert!(RetryPolicy::from_config(&config(single), "GET").is_none());
+    assert!(RetryPolicy::from_config(&ExecutionConfig::default(), "GET").is_none());
+}
+
+#[test]
+fn test_retryable() {
+    let retry = RetryConfig {
+        max_attempts: 2,
+        ..Default::default()
+    };
+    let policy = RetryPolicy::from_config(&config(retry), "GET").unwrap();
+    assert!(policy.retries_status(503));
+    assert!(!policy.retries_status(500));
+
+    let error = |internal_error_code, immediately_retryable| CronetError {
+        error_code: 11,
+        internal_error_code,
+        quic_detailed_error_code: 0,
+        immediately_retryable,
+    };
+    // ERR_CONNECTION_RESET, ERR_NAME_NOT_RESOLVED, ERR_CERT_DATE_INVALID
+    assert!(policy.retries_error(&error(-101, false)));
+    assert!(!policy.retries_error(&error(-105, false)));
+    assert!(!policy.retries_error(&error(-201, false)));
+    assert!(policy.retries_error(&error(-201, true)));
+
+    // Kinds by name or number
+    let config: ExecutionConfig = serde_json::from_value(json!({
+        "retry": { "maxAttempts": 2, "retryOnErrors": ["ERROR_KIND_DNS", 4] }
+    }))
+    .unwrap();
+    let retry = config.retry.as_ref().unwrap();
+    assert_eq!(
+        retry.retry_on_errors,
+        vec![ErrorKind::Dns as i32, ErrorKind::Proxy as i32]
+    );
+    let policy = RetryPolicy::from_config(&config, "GET").unwrap();
+    assert!(policy.retries_error(&error(-105, false)));
+    assert!(!policy.retries_error(&error(-101, false)));
+}
+
+#[test]
+fn test_delay() {
+    let retry = RetryConfig {
+        max_attempts: 5,
+        initial_backoff_ms: 100,
+        max_backoff_ms: 300,
+        ..Default::default()
+    };
+    let policy = RetryPolicy::from_config(&config(retry), "GET").unwrap();
+    let delay = |retry| policy.delay(retry, &[]).unwrap();
+    assert!((50..=100).contains(&delay(1).as_millis()));
+    assert!((100..=200).contains(&delay(2).as_millis()));
+    assert!((150..=300).contains(&delay(4).as_millis()));
+
+    let headers = |value: &str| vec![("Retry-After".to_string(), value.to_string())];
+    assert_eq!(policy.delay(1, &headers("0")), Some(Duration::ZERO));
+    // Longer than the policy waits
+    assert_eq!(policy.delay(1, &headers("1")), None);
+}
+
+/// Attempts and delays beyond the server's limits are clamped to them.
+#[test]
+fn test_limits() {
+    let retry = RetryConfig {
+        max_attempts: u32::MAX,
+        initial_backoff_ms: u32::MAX,
+        max_backoff_ms: u32::MAX,
+        ..Default::default()
+    };
+    let policy = RetryPolicy::from_config(&config(retry), "GET").unwrap();
+    assert_eq!(policy.max_attempts(), MAX_ATTEMPTS);
+    for retry in [1, 5, 40] {
+        let delay = policy.delay(retry, &[]).unwrap();
+        assert!(
+            delay <= MAX_BACKOFF && delay >= MAX_BACKOFF / 2,
+            "{:?}",
+            delay
+        );
+    }
+    let headers = vec![("Retry-After".to_string(), "120".to_string())];
+    assert_eq!(policy.delay(1, &headers), None);
+}
+
+/// A status to retry is retried until the attempts run out, and each
+/// attempt is reported.
+#[tokio::test]
+async fn test_retry_status() {
+    let client = Client::new();
+    let result = execute(
+        &client,
+        json!({
+            "request_id": "retry-status",
+            "target": { "url": "https://httpbin.org/status/503", "method": "GET" },
+            "config": { "retry": { "maxAttempts": 3, "initialBackoffMs": 50 } }
+        }),
+    )
+    .await;
+    assert_eq!(result["success"], json!(true), "{}", result);
+    assert_eq!(result["response"]["status_code"], json!(503));
+    assert_eq!(result["attempts"], json!(3));
+    let failures = result["attempt_errors"].as_array().unwrap();
+    assert_eq!(failures.len(), 3);
+    assert_eq!(failures[0]["attempt"], json!(1));
+    assert_eq!(failures[0]["status_code"], json!(503));
+    assert!(failures[0]["backoff_ms"].as_i64().unwrap() > 0);
+    assert_eq!(failures[2]["backoff_ms"], json!(0));
+
+    // Not without opting in for POST
+    let result = execute(
+        &client,
+        json!({
+            "request_id": "retry-post",
+            "target": { "url": "https://httpbin.org/status/503", "method": "POST" },
+            "config": { "retry": { "max_attempts": 3, "initial_backoff_ms": 50 } }
+        }),
+    )
+    .await;
+    assert_eq!(result["attempts"], json!(1), "{}", result);
+    assert_eq!(result["attempt_errors"], json!([]));
+}
+
+/// Failures of a listed kind are retried, and each attempt's error is kept.
+#[tokio::test]
+async fn test_retry_error() {
+    let client = Client::new();
+    let result = execute(
+        &client,
+        json!({
+            "request_id": "retry-dns",
+            "target": { "url": "https://does-not-exist.invalid/", "method": "GET" },
+            "config": {
+                "retry": {
+                    "max_attempts": 2,
+                    "initial_backoff_ms": 50,
+                    "retry_on_errors": ["ERROR_KIND_DNS"]
+                }
+            }
+        }),
+    )
+    .await;
+    assert_eq!(result["success"], json!(false));
+    assert_eq!(result["attempts"], json!(2), "{}", result);
+    let failures = result["attempt_errors"].as_array().unwrap();
+    assert_eq!(failures.len(), 2);
+    assert_eq!(failures[1]["error"]["kind"], json!("ERROR_KIND_DNS"));
+
+    // The default kinds leave DNS failures alone
+    let result = execute(
+        &client,
+        json!({
+            "request_id": "retry-dns-default",
+            "target": { "url": "https://does-not-exist.invalid/", "method": "GET" },
+            "config": { "retry": { "max_attempts": 2 } }
+        }),
+    )
+    .await;
+    assert_eq!(result["attempts"], json!(1), "{}", result);
+}
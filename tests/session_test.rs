@@ -0,0 +1,162 @@
+use reqwest::Client;
+use serde_json::json;
+
+mod common;
+use common::execute;
+
+const SESSIONS_URL: &str = "http://127.0.0.1:3000/api/v1/sessions";
+
+async fn create(client: &Client, options: serde_json::Value) -> reqwest::Response {
+    client
+        .post(SESSIONS_URL)
+        .json(&options)
+        .send()
+        .await
+        .expect("Failed to send request")
+}
+
+/// Sessions can be created, listed, described and deleted.
+#[tokio::test]
+async fn test_session_lifecycle() {
+    let client = Client::new();
+    let url = format!("{}/lifecycle-session", SESSIONS_URL);
+    client.delete(&url).send().await.unwrap();
+
+    let resp = create(
+        &client,
+        json!({ "id": "lifecycle-session", "headers": { "X-Session": "1" }, "ttl_secs": 600 }),
+    )
+    .await;
+    assert_eq!(resp.status(), 201);
+    let info: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(info["id"], json!("lifecycle-session"));
+    assert_eq!(info["ttl_secs"], json!(600));
+    assert_eq!(info["headers"]["X-Session"], json!("1"));
+
+    // Ids are unique
+    let resp = create(&client, json!({ "id": "lifecycle-session" })).await;
+    assert_eq!(resp.status(), 409);
+
+    let listed: serde_json::Value = client
+        .get(SESSIONS_URL)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert!(listed
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|session| session["id"] == json!("lifecycle-session")));
+
+    let resp = client.get(&url).send().await.unwrap();
+    assert!(resp.status().is_success());
+
+    let resp = client.delete(&url).send().await.unwrap();
+    assert_eq!(resp.status(), 204);
+    let resp = client.get(&url).send().await.unwrap();
+    assert_eq!(resp.status(), 404);
+    let resp = client.delete(&url).send().await.unwrap();
+    assert_eq!(resp.status(), 404);
+}
+
+/// Requests of a session get its headers and reuse its connections.
+#[tokio::test]
+async fn test_session_requests() {
+    let client = Client::new();
+    let resp = create(&client, json!({ "headers": { "X-Session": "warm" } })).await;
+    assert_eq!(resp.status(), 201);
+    let info: serde_json::Value = resp.json().await.unwrap();
+    let session_id = info["id"].as_str().unwrap().to_string();
+    assert!(!session_id.is_empty());
+
+    let request = |id: &str| {
+        json!({
+            "request_id": id,
+            "target": { "url": "https://httpbin.org/headers", "method": "GET" },
+            "config": { "session_id": session_id, "body_encoding": "utf8" }
+        })
+    };
+    let first = execute(&client, request("session-1")).await;
+    assert_eq!(first["success"], json!(true), "{}", first);
+    let body: serde_json::Value =
+        serde_json::from_str(first["response"]["body"].as_str().unwrap()).unwrap();
+    assert_eq!(body["headers"]["X-Session"], json!("warm"));
+
+    let second = execute(&client, request("session-2")).await;
+    assert_eq!(second["success"], json!(true), "{}", second);
+    assert_eq!(second["timing"]["socket_reused"], json!(true));
+
+    // A session keeps the profile it was created with
+    let result = execute(
+        &client,
+        json!({
+            "request_id": "session-3",
+            "target": { "url": "https://httpbin.org/get", "method": "GET" },
+            "config": { "session_id": session_id, "profile": "does-not-exist" }
+        }),
+    )
+    .await;
+    assert_eq!(result["success"], json!(false));
+    assert!(result["error_message"]
+        .as_str()
+        .unwrap()
+        .starts_with("Profile mismatch"));
+
+    client
+        .delete(format!("{}/{}", SESSIONS_URL, session_id))
+        .send()
+        .await
+        .unwrap();
+}
+
+/// A session id must name a live session.
+#[tokio::test]
+async fn test_unknown_session() {
+    let client = Client::new();
+    let result = execute(
+        &client,
+        json!({
+            "request_id": "session-unknown",
+            "target": { "url": "https://httpbin.org/get", "method": "GET" },
+            "config": { "session_id": "no-such-session" }
+        }),
+    )
+    .await;
+    assert_eq!(result["success"], json!(false));
+    assert_eq!(
+        result["error_message"],
+        json!("Unknown session: no-such-session")
+    );
+    assert_eq!(result["error"]["kind"], json!("ERROR_KIND_INVALID_REQUEST"));
+}
+
+/// A session unused for longer than its TTL is gone.
+#[tokio::test]
+async fn test_session_expiry() {
+    let client = Client::new();
+    let resp = create(&client, json!({ "id": "expiring-session", "ttl_secs": 1 })).await;
+    assert_eq!(resp.status(), 201);
+
+    tokio::time::sleep(std::time::Duration::from_millis(1500)).await;
+    let resp = client
+        .get(format!("{}/expiring-session", SESSIONS_URL))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 404);
+
+    // The id can be taken again
+    let resp = create(&client, json!({ "id": "expiring-session", "ttl_secs": 1 })).await;
+    assert_eq!(resp.status(), 201);
+}
+
+/// Sessions cannot start from an unknown profile.
+#[tokio::test]
+async fn test_session_unknown_profile() {
+    let client = Client::new();
+    let resp = create(&client, json!({ "profile": "does-not-exist" })).await;
+    assert_eq!(resp.status(), 400);
+}
@@ -0,0 +1,104 @@
+use reqwest::Client;
+use serde_json::json;
+
+const STREAM_URL: &str = "http://127.0.0.1:3000/api/v1/execute/stream";
+const CONNECT_URL: &str = "http://127.0.0.1:3000/cronet.engine.v1.EngineService/ExecuteStream";
+
+/// The streaming route passes the target's status and headers through and
+/// forwards the raw body.
+#[tokio::test]
+async fn test_stream_route() {
+    let client = Client::new();
+    let payload = json!({
+        "request_id": "stream-1",
+        "target": {
+            "url": "https://httpbin.org/stream-bytes/200000?chunk_size=4096",
+            "method": "GET"
+        }
+    });
+
+    let resp = client
+        .post(STREAM_URL)
+        .json(&payload)
+        .send()
+        .await
+        .expect("Failed to send request");
+    assert_eq!(resp.status(), 200);
+    let body = resp.bytes().await.expect("Failed to read body");
+    assert_eq!(body.len(), 200000);
+}
+
+/// Failures before the response starts come back as a JSON error.
+#[tokio::test]
+async fn test_stream_route_error() {
+    let client = Client::new();
+    let payload = json!({
+        "request_id": "stream-2",
+        "target": {
+            "url": "https://nonexistent.invalid/",
+            "method": "GET"
+        }
+    });
+
+    let resp = client
+        .post(STREAM_URL)
+        .json(&payload)
+        .send()
+        .await
+        .expect("Failed to send request");
+    assert_eq!(resp.status(), 502);
+    let body: serde_json::Value = resp.json().await.expect("Failed to parse JSON");
+    assert_eq!(body["success"], json!(false));
+}
+
+/// ExecuteStream sends the head first, then body chunks, then a clean
+/// end-of-stream message.
+#[tokio::test]
+async fn test_connect_execute_stream() {
+    let client = Client::new();
+    let message = json!({
+        "request_id": "stream-3",
+        "target": {
+            "url": "https://httpbin.org/bytes/50000",
+            "method": "GET"
+        }
+    })
+    .to_string();
+    let mut frame = vec![0u8];
+    frame.extend_from_slice(&(message.len() as u32).to_be_bytes());
+    frame.extend_from_slice(message.as_bytes());
+
+    let resp = client
+        .post(CONNECT_URL)
+        .header("Content-Type", "application/connect+json")
+        .body(frame)
+        .send()
+        .await
+        .expect("Failed to send request");
+    assert_eq!(resp.status(), 200);
+    let data = resp.bytes().await.expect("Failed to read body");
+
+    let mut messages = Vec::new();
+    let mut rest = &data[..];
+    while rest.len() >= 5 {
+        let flags = rest[0];
+        let len = u32::from_be_bytes([rest[1], rest[2], rest[3], rest[4]]) as usize;
+        let value: serde_json::Value = serde_json::from_slice(&rest[5..5 + len]).unwrap();
+        messages.push((flags, value));
+        rest = &rest[5 + len..];
+    }
+
+    let (flags, head) = &messages[0];
+    assert_eq!(*flags, 0);
+    assert_eq!(head["head"]["status_code"], json!(200));
+
+    let (flags, end) = messages.last().unwrap();
+    assert_eq!(*flags, 2);
+    assert!(end.get("error").is_none(), "Stream failed: {}", end);
+
+    let body_len: usize = messages[1..messages.len() - 1]
+        .iter()
+        .map(|(_, m)| m["chunk"].as_str().unwrap().len() / 2)
+        .sum();
+    assert_eq!(body_len, 50000);
+}
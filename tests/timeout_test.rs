@@ -0,0 +1,89 @@
+use reqwest::Client;
+use serde_json::json;
+use std::time::Instant;
+
+const SERVICE_URL: &str = "http://127.0.0.1:3000/api/v1/execute";
+const CONNECT_URL: &str = "http://127.0.0.1:3000/cronet.engine.v1.EngineService/Execute";
+
+/// A slow upstream must be cancelled once `timeout_ms` expires.
+#[tokio::test]
+async fn test_timeout_cancels_request() {
+    let client = Client::new();
+
+    let payload = json!({
+        "request_id": "timeout-test",
+        "target": {
+            "url": "https://httpbin.org/delay/10",
+            "method": "GET"
+        },
+        "config": {
+            "timeout_ms": 1000
+        }
+    });
+
+    let start = Instant::now();
+    let resp = client
+        .post(SERVICE_URL)
+        .json(&payload)
+        .send()
+        .await
+        .expect("Failed to send request");
+    let elapsed = start.elapsed();
+
+    assert!(resp.status().is_success());
+    let body: serde_json::Value = resp.json().await.expect("Failed to parse JSON response");
+
+    let success = body
+        .get("success")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(true);
+    let error = body
+        .get("error_message")
+        .and_then(|v| v.as_str())
+        .unwrap_or_default();
+    println!("Elapsed: {:?}, error: {}", elapsed, error);
+
+    assert!(!success, "Request should have timed out");
+    assert!(
+        error.contains("Timeout"),
+        "Expected timeout error, got: {}",
+        error
+    );
+    assert!(elapsed.as_secs() < 5, "Request was not cancelled in time");
+}
+
+/// The Connect route also accepts the deadline through `Connect-Timeout-Ms`.
+#[tokio::test]
+async fn test_connect_timeout_header() {
+    let client = Client::new();
+
+    let payload = json!({
+        "request_id": "connect-timeout-test",
+        "target": {
+            "url": "https://httpbin.org/delay/10",
+            "method": "GET"
+        }
+    });
+
+    let resp = client
+        .post(CONNECT_URL)
+        .header("Connect-Timeout-Ms", "1000")
+        .json(&payload)
+        .send()
+        .await
+        .expect("Failed to send request");
+
+    assert_eq!(resp.status(), 504);
+    let body: serde_json::Value = resp.json().await.expect("Failed to parse JSON response");
+    assert_eq!(body["code"], json!("deadline_exceeded"));
+    let error = body
+        .get("message")
+        .and_then(|v| v.as_str())
+        .unwrap_or_default();
+
+    assert!(
+        error.contains("Timeout"),
+        "Expected timeout error, got: {}",
+        error
+    );
+}
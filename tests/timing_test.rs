@@ -0,0 +1,38 @@
+use reqwest::Client;
+use serde_json::json;
+
+mod common;
+
+async fn get(client: &Client, request_id: &str) -> serde_json::Value {
+    let payload = json!({
+        "request_id": request_id,
+        "target": {
+            "url": "https://httpbin.org/get",
+            "method": "GET"
+        }
+    });
+    common::execute(client, payload).await
+}
+
+/// Each response carries a timing breakdown, and a second request to the same
+/// host on the shared engine reuses the connection.
+#[tokio::test]
+async fn test_timing_breakdown() {
+    let client = Client::new();
+
+    let first = get(&client, "timing-1").await;
+    assert_eq!(first["success"], json!(true), "Request failed: {}", first);
+    let timing = &first["timing"];
+    println!("First: {}", timing);
+
+    assert!(timing["request_start"].as_i64().unwrap() > 0);
+    assert!(timing["total_ms"].as_i64().unwrap() > 0);
+    assert!(timing["received_byte_count"].as_i64().unwrap() > 0);
+
+    let second = get(&client, "timing-2").await;
+    assert_eq!(second["success"], json!(true), "Request failed: {}", second);
+    println!("Second: {}", second["timing"]);
+
+    assert_eq!(second["timing"]["socket_reused"], json!(true));
+    assert_eq!(second["timing"]["dns_ms"], json!(0));
+}
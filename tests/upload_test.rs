@@ -0,0 +1,53 @@
+use base64::Engine as _;
+use reqwest::Client;
+use serde_json::json;
+
+const UPLOAD_URL: &str = "http://127.0.0.1:3000/api/v1/execute/upload";
+
+/// The raw request body is uploaded to the target; the request itself travels
+/// in the `request` query parameter.
+#[tokio::test]
+async fn test_streaming_upload() {
+    let client = Client::new();
+    let request = json!({
+        "request_id": "upload-1",
+        "target": {
+            "url": "https://httpbin.org/post",
+            "method": "POST",
+            "headers": {
+                "Content-Type": { "values": ["application/octet-stream"] }
+            }
+        }
+    });
+    let body = vec![b'a'; 300_000];
+
+    let resp = client
+        .post(UPLOAD_URL)
+        .query(&[("request", request.to_string())])
+        .body(body)
+        .send()
+        .await
+        .expect("Failed to send request");
+    let result: serde_json::Value = resp.json().await.expect("Failed to parse JSON response");
+    assert_eq!(result["success"], json!(true), "Request failed: {}", result);
+
+    let response = &result["response"];
+    assert_eq!(response["status_code"], json!(200));
+    let echoed = base64::engine::general_purpose::STANDARD
+        .decode(response["body"].as_str().unwrap())
+        .unwrap();
+    let echoed: serde_json::Value = serde_json::from_slice(&echoed).unwrap();
+    assert_eq!(echoed["data"].as_str().unwrap().len(), 300_000);
+}
+
+#[tokio::test]
+async fn test_upload_missing_request() {
+    let client = Client::new();
+    let resp = client
+        .post(UPLOAD_URL)
+        .body("data")
+        .send()
+        .await
+        .expect("Failed to send request");
+    assert_eq!(resp.status(), 400);
+}